@@ -0,0 +1,186 @@
+use bitcoin::base64::engine::general_purpose::STANDARD;
+use bitcoin::base64::Engine;
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::{Amount, FeeRate, OutPoint, Script, ScriptBuf, Transaction, TxOut, Txid};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use crate::backend::{ChainBackend, ChainUtxo, ScriptHistory};
+use crate::Error;
+
+/// RPC error code Bitcoin Core returns for an unknown transaction.
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+
+/// Bitcoin Core over JSON-RPC.
+///
+/// UTXOs are found with `scantxoutset`, which needs no wallet but only sees
+/// confirmed outputs. Looking up transactions that are neither in the
+/// mempool nor in the wallet needs `-txindex`.
+pub struct BitcoindBackend {
+    url: String,
+    authorization: Option<String>,
+    agent: ureq::Agent,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ScanResult {
+    unspents: Vec<ScanUnspent>,
+}
+
+#[derive(Deserialize)]
+struct ScanUnspent {
+    txid: Txid,
+    vout: u32,
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: String,
+    amount: f64,
+    height: u32,
+}
+
+#[derive(Deserialize)]
+struct FeeEstimate {
+    /// Fee rate in BTC/kvB, missing when the node has too little data.
+    feerate: Option<f64>,
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+impl BitcoindBackend {
+    /// Connects to the RPC server at `url`, e.g. `http://127.0.0.1:18443`,
+    /// without credentials.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            authorization: None,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build(),
+        }
+    }
+
+    /// Authenticates with an `rpcuser`/`rpcpassword` pair.
+    pub fn with_user_pass(mut self, user: &str, password: &str) -> Self {
+        let credentials = STANDARD.encode(format!("{}:{}", user, password));
+        self.authorization = Some(format!("Basic {}", credentials));
+        self
+    }
+
+    /// Authenticates with the `.cookie` file the node writes to its data directory.
+    pub fn with_cookie_file(self, path: &Path) -> Result<Self, Error> {
+        let cookie = fs::read_to_string(path)
+            .map_err(|e| Error::BackendError(format!("Failed to read {}: {}", path.display(), e)))?;
+        let (user, password) = cookie
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| Error::BackendError(format!("Invalid cookie file {}", path.display())))?;
+        Ok(self.with_user_pass(user, password))
+    }
+
+    /// Calls an RPC method, returning the RPC error code and message on failure.
+    fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<Result<T, RpcError>, Error> {
+        let body = json!({ "jsonrpc": "1.0", "id": "mallowbtc", "method": method, "params": params });
+        let mut request = self.agent.post(&self.url);
+        if let Some(authorization) = &self.authorization {
+            request = request.set("Authorization", authorization);
+        }
+
+        // Bitcoin Core answers RPC errors with an HTTP error status and a JSON body
+        let response = match request.send_json(body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(401, _)) => {
+                return Err(Error::BackendError("RPC authentication failed; check the credentials".to_string()));
+            }
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(Error::BackendError(format!("RPC request {} failed: {}", method, e))),
+        };
+        let response: RpcResponse<T> = response
+            .into_json()
+            .map_err(|e| Error::BackendError(format!("Invalid RPC response to {}: {}", method, e)))?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Ok(Err(error)),
+            (Some(result), None) => Ok(Ok(result)),
+            (None, None) => Err(Error::BackendError(format!("Empty RPC response to {}", method))),
+        }
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        self.request(method, params)?.map_err(|error| {
+            Error::BackendError(format!("{} failed ({}): {}", method, error.code, error.message))
+        })
+    }
+}
+
+impl ChainBackend for BitcoindBackend {
+    fn script_utxos(&self, script_pubkey: &Script) -> Result<Vec<ChainUtxo>, Error> {
+        let descriptor = format!("raw({})", script_pubkey.to_hex_string());
+        let scan: ScanResult = self.call("scantxoutset", json!(["start", [descriptor]]))?;
+
+        scan.unspents
+            .into_iter()
+            .map(|unspent| {
+                let script_pubkey = ScriptBuf::from_hex(&unspent.script_pubkey)
+                    .map_err(|e| Error::BackendError(format!("Invalid scriptPubKey from scantxoutset: {}", e)))?;
+                let value = Amount::from_btc(unspent.amount)
+                    .map_err(|e| Error::BackendError(format!("Invalid amount from scantxoutset: {}", e)))?;
+                Ok(ChainUtxo {
+                    outpoint: OutPoint::new(unspent.txid, unspent.vout),
+                    txout: TxOut { value, script_pubkey },
+                    confirmation_height: Some(unspent.height),
+                })
+            })
+            .collect()
+    }
+
+    /// Bitcoin Core indexes no scripts outside its wallets, so it can only
+    /// report unspent outputs.
+    fn script_history(&self, _script_pubkey: &Script) -> Result<Option<ScriptHistory>, Error> {
+        Ok(None)
+    }
+
+    fn tip_height(&self) -> Result<u32, Error> {
+        self.call("getblockcount", json!([]))
+    }
+
+    fn transaction(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        match self.request::<String>("getrawtransaction", json!([txid]))? {
+            Ok(hex) => deserialize_hex(&hex)
+                .map(Some)
+                .map_err(|e| Error::BackendError(format!("Invalid transaction from getrawtransaction: {}", e))),
+            Err(error) if error.code == RPC_INVALID_ADDRESS_OR_KEY => Ok(None),
+            Err(error) => Err(Error::BackendError(format!(
+                "getrawtransaction failed ({}): {}",
+                error.code, error.message
+            ))),
+        }
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        self.call("sendrawtransaction", json!([serialize_hex(tx)]))
+    }
+
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, Error> {
+        let estimate: FeeEstimate = self.call("estimatesmartfee", json!([target_blocks]))?;
+        let btc_per_kvb = estimate.feerate.ok_or_else(|| {
+            Error::BackendError(format!("No fee estimate from the node: {}", estimate.errors.join("; ")))
+        })?;
+
+        // Whole sat/kvB first, so float noise can't round the rate up; a vbyte is 4 weight units
+        let sat_per_kvb = (btc_per_kvb * 100_000_000.0).round() as u64;
+        let sat_per_kwu = sat_per_kvb.div_ceil(4);
+        Ok(FeeRate::from_sat_per_kwu(sat_per_kwu.max(FeeRate::BROADCAST_MIN.to_sat_per_kwu())))
+    }
+}
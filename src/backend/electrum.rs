@@ -0,0 +1,185 @@
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{Amount, FeeRate, OutPoint, Script, Transaction, TxOut, Txid};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use crate::backend::{ChainBackend, ChainUtxo, ScriptHistory};
+use crate::Error;
+
+/// An Electrum server, such as electrs or Fulcrum, over plain TCP.
+///
+/// TLS (`ssl://`) endpoints are not supported; reach those through a local
+/// server or a tunnel.
+pub struct ElectrumBackend {
+    address: String,
+    connection: RefCell<Option<BufReader<TcpStream>>>,
+    next_id: RefCell<u64>,
+}
+
+#[derive(Deserialize)]
+struct ElectrumUtxo {
+    tx_hash: Txid,
+    tx_pos: u32,
+    /// Confirmation height; 0 or -1 for mempool transactions.
+    height: i64,
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct ElectrumHistory {
+    tx_hash: Txid,
+    /// Confirmation height; 0 or -1 for mempool transactions.
+    height: i64,
+}
+
+#[derive(Deserialize)]
+struct HeaderNotification {
+    height: u32,
+}
+
+impl ElectrumBackend {
+    /// Uses the server at `url`, as `tcp://host:port` or `host:port`.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        if url.starts_with("ssl://") {
+            return Err(Error::BackendError(
+                "ssl:// Electrum servers are not supported; use a tcp:// server".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            address: url.trim_start_matches("tcp://").to_string(),
+            connection: RefCell::new(None),
+            next_id: RefCell::new(0),
+        })
+    }
+
+    /// The scripthash Electrum indexes scripts by: their SHA256, byte-reversed.
+    pub fn script_hash(script_pubkey: &Script) -> String {
+        let mut hash = sha256::Hash::hash(script_pubkey.as_bytes()).to_byte_array();
+        hash.reverse();
+        hex::encode(hash)
+    }
+
+    /// Calls a method, returning the server's error message on failure.
+    fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<Result<T, String>, Error> {
+        let failed = |e: std::io::Error| Error::BackendError(format!("Electrum {} failed: {}", method, e));
+
+        let mut connection = self.connection.borrow_mut();
+        if connection.is_none() {
+            let stream = TcpStream::connect(&self.address).map_err(failed)?;
+            stream.set_read_timeout(Some(Duration::from_secs(60))).map_err(failed)?;
+            *connection = Some(BufReader::new(stream));
+        }
+        let reader = connection.as_mut().expect("connected above");
+
+        let id = {
+            let mut next_id = self.next_id.borrow_mut();
+            *next_id += 1;
+            *next_id
+        };
+        let mut line = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string();
+        line.push('\n');
+        reader.get_mut().write_all(line.as_bytes()).map_err(failed)?;
+
+        // Skip notifications from subscriptions until our response arrives
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).map_err(failed)? == 0 {
+                *connection = None;
+                return Err(Error::BackendError(format!("Electrum server closed the connection during {}", method)));
+            }
+            let response: Value = serde_json::from_str(&line)
+                .map_err(|e| Error::BackendError(format!("Invalid Electrum response to {}: {}", method, e)))?;
+            if response.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+
+            if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+                let message = error.get("message").and_then(Value::as_str).map(str::to_string);
+                return Ok(Err(message.unwrap_or_else(|| error.to_string())));
+            }
+            let result = response.get("result").cloned().unwrap_or(Value::Null);
+            return serde_json::from_value(result)
+                .map(Ok)
+                .map_err(|e| Error::BackendError(format!("Invalid Electrum result for {}: {}", method, e)));
+        }
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        self.request(method, params)?
+            .map_err(|message| Error::BackendError(format!("Electrum {} failed: {}", method, message)))
+    }
+}
+
+impl ChainBackend for ElectrumBackend {
+    fn script_utxos(&self, script_pubkey: &Script) -> Result<Vec<ChainUtxo>, Error> {
+        let utxos: Vec<ElectrumUtxo> =
+            self.call("blockchain.scripthash.listunspent", json!([Self::script_hash(script_pubkey)]))?;
+
+        Ok(utxos
+            .into_iter()
+            .map(|utxo| ChainUtxo {
+                outpoint: OutPoint::new(utxo.tx_hash, utxo.tx_pos),
+                txout: TxOut {
+                    value: Amount::from_sat(utxo.value),
+                    script_pubkey: script_pubkey.to_owned(),
+                },
+                confirmation_height: u32::try_from(utxo.height).ok().filter(|height| *height > 0),
+            })
+            .collect())
+    }
+
+    fn script_history(&self, script_pubkey: &Script) -> Result<Option<ScriptHistory>, Error> {
+        let history: Vec<ElectrumHistory> =
+            self.call("blockchain.scripthash.get_history", json!([Self::script_hash(script_pubkey)]))?;
+
+        history
+            .into_iter()
+            .map(|entry| {
+                let tx = self.transaction(&entry.tx_hash)?.ok_or_else(|| {
+                    Error::BackendError(format!("Electrum lists {} but has no such transaction", entry.tx_hash))
+                })?;
+                Ok((tx, u32::try_from(entry.height).ok().filter(|height| *height > 0)))
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Some)
+    }
+
+    fn tip_height(&self) -> Result<u32, Error> {
+        let header: HeaderNotification = self.call("blockchain.headers.subscribe", json!([]))?;
+        Ok(header.height)
+    }
+
+    fn transaction(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        match self.request::<String>("blockchain.transaction.get", json!([txid]))? {
+            Ok(hex) => deserialize_hex(&hex)
+                .map(Some)
+                .map_err(|e| Error::BackendError(format!("Invalid transaction from Electrum: {}", e))),
+            // Servers word this differently, but all say the transaction is unknown
+            Err(message) if message.to_lowercase().contains("not found") || message.contains("No such") => Ok(None),
+            Err(message) => Err(Error::BackendError(format!("Electrum blockchain.transaction.get failed: {}", message))),
+        }
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        self.call("blockchain.transaction.broadcast", json!([serialize_hex(tx)]))
+    }
+
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, Error> {
+        // BTC per kilobyte, or -1 when the server has no estimate
+        let btc_per_kvb: f64 = self.call("blockchain.estimatefee", json!([target_blocks]))?;
+        if btc_per_kvb <= 0.0 {
+            return Err(Error::BackendError("Electrum server has no fee estimate".to_string()));
+        }
+
+        let sat_per_kvb = (btc_per_kvb * 100_000_000.0).round() as u64;
+        Ok(FeeRate::from_sat_per_kwu(
+            sat_per_kvb.div_ceil(4).max(FeeRate::BROADCAST_MIN.to_sat_per_kwu()),
+        ))
+    }
+}
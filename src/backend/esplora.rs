@@ -0,0 +1,162 @@
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{Amount, FeeRate, OutPoint, Script, Transaction, TxOut, Txid};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::backend::{ChainBackend, ChainUtxo, ScriptHistory};
+use crate::Error;
+
+/// An Esplora REST server, such as blockstream.info or mempool.space.
+pub struct EsploraBackend {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Deserialize)]
+struct EsploraUtxo {
+    txid: Txid,
+    vout: u32,
+    value: u64,
+    status: EsploraStatus,
+}
+
+#[derive(Deserialize)]
+struct EsploraTx {
+    txid: Txid,
+    status: EsploraStatus,
+}
+
+#[derive(Deserialize)]
+struct EsploraStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+}
+
+impl EsploraBackend {
+    /// Uses the API at `base_url`, e.g. `https://blockstream.info/testnet/api`.
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build(),
+        }
+    }
+
+    /// Sends a request, returning `None` when the server answers 404.
+    fn send(&self, method: &str, path: &str, body: Option<&str>) -> Result<Option<String>, Error> {
+        let request = self.agent.request(method, &format!("{}{}", self.base_url, path));
+        let response = match body {
+            Some(body) => request.send_string(body),
+            None => request.call(),
+        };
+
+        match response {
+            Ok(response) => response
+                .into_string()
+                .map(Some)
+                .map_err(|e| Error::BackendError(format!("Failed to read Esplora response to {}: {}", path, e))),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(status, response)) => Err(Error::BackendError(format!(
+                "Esplora {} {} failed ({}): {}",
+                method,
+                path,
+                status,
+                response.into_string().unwrap_or_default().trim()
+            ))),
+            Err(e) => Err(Error::BackendError(format!("Esplora {} {} failed: {}", method, path, e))),
+        }
+    }
+
+    fn get_text(&self, path: &str) -> Result<String, Error> {
+        self.send("GET", path, None)?
+            .ok_or_else(|| Error::BackendError(format!("Esplora has no {}", path)))
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        serde_json::from_str(&self.get_text(path)?)
+            .map_err(|e| Error::BackendError(format!("Invalid Esplora response to {}: {}", path, e)))
+    }
+}
+
+impl ChainBackend for EsploraBackend {
+    fn script_utxos(&self, script_pubkey: &Script) -> Result<Vec<ChainUtxo>, Error> {
+        let script_hash = sha256::Hash::hash(script_pubkey.as_bytes());
+        let utxos: Vec<EsploraUtxo> = self.get_json(&format!("/scripthash/{}/utxo", script_hash))?;
+
+        Ok(utxos
+            .into_iter()
+            .map(|utxo| ChainUtxo {
+                outpoint: OutPoint::new(utxo.txid, utxo.vout),
+                txout: TxOut {
+                    value: Amount::from_sat(utxo.value),
+                    script_pubkey: script_pubkey.to_owned(),
+                },
+                confirmation_height: utxo.status.block_height.filter(|_| utxo.status.confirmed),
+            })
+            .collect())
+    }
+
+    fn script_history(&self, script_pubkey: &Script) -> Result<Option<ScriptHistory>, Error> {
+        // The newest transactions, which is all of them for a gift's handful
+        let script_hash = sha256::Hash::hash(script_pubkey.as_bytes());
+        let history: Vec<EsploraTx> = self.get_json(&format!("/scripthash/{}/txs", script_hash))?;
+
+        history
+            .into_iter()
+            .map(|entry| {
+                let tx = self
+                    .transaction(&entry.txid)?
+                    .ok_or_else(|| Error::BackendError(format!("Esplora lists {} but has no such transaction", entry.txid)))?;
+                Ok((tx, entry.status.block_height.filter(|_| entry.status.confirmed)))
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Some)
+    }
+
+    fn tip_height(&self) -> Result<u32, Error> {
+        let height = self.get_text("/blocks/tip/height")?;
+        height
+            .trim()
+            .parse()
+            .map_err(|_| Error::BackendError(format!("Invalid tip height from Esplora: {}", height)))
+    }
+
+    fn transaction(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        self.send("GET", &format!("/tx/{}/hex", txid), None)?
+            .map(|hex| {
+                deserialize_hex(hex.trim())
+                    .map_err(|e| Error::BackendError(format!("Invalid transaction from Esplora: {}", e)))
+            })
+            .transpose()
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        let txid = self
+            .send("POST", "/tx", Some(&serialize_hex(tx)))?
+            .ok_or_else(|| Error::BackendError("Esplora has no /tx endpoint".to_string()))?;
+        Txid::from_str(txid.trim())
+            .map_err(|e| Error::BackendError(format!("Invalid txid from Esplora broadcast: {}", e)))
+    }
+
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, Error> {
+        // Estimates in sat/vB, keyed by confirmation target in blocks
+        let estimates: BTreeMap<String, f64> = self.get_json("/fee-estimates")?;
+        let estimates: BTreeMap<u16, f64> = estimates
+            .into_iter()
+            .filter_map(|(target, rate)| Some((target.parse().ok()?, rate)))
+            .collect();
+
+        // The estimate for the longest target that is still no later than the one asked for
+        let sat_per_vb = estimates
+            .range(..=target_blocks)
+            .next_back()
+            .or_else(|| estimates.iter().next())
+            .map(|(_, rate)| *rate)
+            .ok_or_else(|| Error::BackendError("Esplora returned no fee estimates".to_string()))?;
+
+        let sat_per_kwu = (sat_per_vb * 250.0).ceil() as u64;
+        Ok(FeeRate::from_sat_per_kwu(sat_per_kwu.max(FeeRate::BROADCAST_MIN.to_sat_per_kwu())))
+    }
+}
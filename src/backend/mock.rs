@@ -0,0 +1,170 @@
+use bitcoin::{FeeRate, OutPoint, Script, Transaction, Txid};
+use std::cell::RefCell;
+use crate::backend::{ChainBackend, ChainUtxo, ScriptHistory};
+use crate::Error;
+
+/// An in-memory chain for tests.
+///
+/// It holds the transactions it is given, each confirmed at a height or
+/// waiting in the mempool, and treats every output no known transaction
+/// spends as unspent. Scripts and signatures are not checked.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    state: RefCell<MockState>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    tip_height: u32,
+    transactions: Vec<(Transaction, Option<u32>)>,
+    fee_rate: Option<FeeRate>,
+}
+
+impl MockBackend {
+    /// Creates an empty chain with its tip at `tip_height`.
+    pub fn new(tip_height: u32) -> Self {
+        let backend = Self::default();
+        backend.state.borrow_mut().tip_height = tip_height;
+        backend
+    }
+
+    /// Sets the fee rate returned by every estimate; without one,
+    /// estimates fail as they do on a fresh regtest node.
+    pub fn with_fee_rate(self, fee_rate: FeeRate) -> Self {
+        self.state.borrow_mut().fee_rate = Some(fee_rate);
+        self
+    }
+
+    /// Adds a transaction confirmed at `height`, or to the mempool if `None`.
+    pub fn add_transaction(&self, tx: Transaction, height: Option<u32>) {
+        let mut state = self.state.borrow_mut();
+        if let Some(height) = height {
+            state.tip_height = state.tip_height.max(height);
+        }
+        state.transactions.push((tx, height));
+    }
+
+    /// Mines `blocks` blocks, confirming every mempool transaction in the first.
+    pub fn mine(&self, blocks: u32) {
+        if blocks == 0 {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        let first = state.tip_height + 1;
+        for (_, height) in state.transactions.iter_mut().filter(|(_, height)| height.is_none()) {
+            *height = Some(first);
+        }
+        state.tip_height += blocks;
+    }
+
+    /// Returns the confirmation height of a known transaction, `None`
+    /// inside if it is in the mempool.
+    pub fn confirmation(&self, txid: &Txid) -> Option<Option<u32>> {
+        self.state
+            .borrow()
+            .transactions
+            .iter()
+            .find(|(tx, _)| tx.compute_txid() == *txid)
+            .map(|(_, height)| *height)
+    }
+
+    fn spender(state: &MockState, outpoint: &OutPoint) -> Option<Txid> {
+        state
+            .transactions
+            .iter()
+            .find(|(tx, _)| tx.input.iter().any(|input| input.previous_output == *outpoint))
+            .map(|(tx, _)| tx.compute_txid())
+    }
+}
+
+impl ChainBackend for MockBackend {
+    fn script_utxos(&self, script_pubkey: &Script) -> Result<Vec<ChainUtxo>, Error> {
+        let state = self.state.borrow();
+        let mut utxos = Vec::new();
+        for (tx, height) in &state.transactions {
+            let txid = tx.compute_txid();
+            for (vout, txout) in tx.output.iter().enumerate() {
+                let outpoint = OutPoint::new(txid, vout as u32);
+                if txout.script_pubkey.as_script() == script_pubkey && Self::spender(&state, &outpoint).is_none() {
+                    utxos.push(ChainUtxo {
+                        outpoint,
+                        txout: txout.clone(),
+                        confirmation_height: *height,
+                    });
+                }
+            }
+        }
+
+        Ok(utxos)
+    }
+
+    fn script_history(&self, script_pubkey: &Script) -> Result<Option<ScriptHistory>, Error> {
+        let state = self.state.borrow();
+        let pays = |tx: &Transaction| tx.output.iter().any(|txout| txout.script_pubkey.as_script() == script_pubkey);
+        let paid: Vec<OutPoint> = state
+            .transactions
+            .iter()
+            .flat_map(|(tx, _)| {
+                let txid = tx.compute_txid();
+                tx.output
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, txout)| txout.script_pubkey.as_script() == script_pubkey)
+                    .map(move |(vout, _)| OutPoint::new(txid, vout as u32))
+            })
+            .collect();
+
+        Ok(Some(
+            state
+                .transactions
+                .iter()
+                .filter(|(tx, _)| pays(tx) || tx.input.iter().any(|input| paid.contains(&input.previous_output)))
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    fn tip_height(&self) -> Result<u32, Error> {
+        Ok(self.state.borrow().tip_height)
+    }
+
+    fn transaction(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        Ok(self
+            .state
+            .borrow()
+            .transactions
+            .iter()
+            .find(|(tx, _)| tx.compute_txid() == *txid)
+            .map(|(tx, _)| tx.clone()))
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        let txid = tx.compute_txid();
+        {
+            let state = self.state.borrow();
+            for input in &tx.input {
+                if let Some(spender) = Self::spender(&state, &input.previous_output) {
+                    if spender != txid {
+                        return Err(Error::BackendError(format!(
+                            "Input {} is already spent by {}",
+                            input.previous_output, spender
+                        )));
+                    }
+                }
+            }
+            if state.transactions.iter().any(|(known, _)| known.compute_txid() == txid) {
+                return Ok(txid);
+            }
+        }
+
+        self.add_transaction(tx.clone(), None);
+        Ok(txid)
+    }
+
+    fn estimate_fee_rate(&self, _target_blocks: u16) -> Result<FeeRate, Error> {
+        self.state
+            .borrow()
+            .fee_rate
+            .ok_or_else(|| Error::BackendError("Insufficient data for a fee estimate".to_string()))
+    }
+}
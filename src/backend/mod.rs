@@ -0,0 +1,95 @@
+use bitcoin::{FeeRate, OutPoint, Script, Transaction, TxOut, Txid};
+use std::ops::Range;
+use crate::transaction::{GiftTransaction, GiftUtxo};
+use crate::Error;
+
+pub mod bitcoind;
+pub mod electrum;
+pub mod esplora;
+pub mod mock;
+
+pub use bitcoind::BitcoindBackend;
+pub use electrum::ElectrumBackend;
+pub use esplora::EsploraBackend;
+pub use mock::MockBackend;
+
+/// Confirmation target, in blocks, of fee estimates made for the user.
+pub const DEFAULT_FEE_TARGET: u16 = 6;
+
+/// Number of giver descriptor indices searched for UTXOs by default.
+pub const DEFAULT_SCAN_INDICES: u32 = 20;
+
+/// An unspent output as a backend reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainUtxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    /// Block the output confirmed in; `None` while it is in the mempool.
+    pub confirmation_height: Option<u32>,
+}
+
+/// Transactions touching a script, each with its confirmation height
+/// (`None` while in the mempool).
+pub type ScriptHistory = Vec<(Transaction, Option<u32>)>;
+
+/// A source of chain data and a way to publish transactions.
+///
+/// Commands that need the chain go through this trait, so the same code
+/// runs against a node, an indexer or the in-memory [`MockBackend`].
+pub trait ChainBackend {
+    /// Returns the unspent outputs paying `script_pubkey`.
+    fn script_utxos(&self, script_pubkey: &Script) -> Result<Vec<ChainUtxo>, Error>;
+
+    /// Returns every transaction that pays or spends `script_pubkey`, or
+    /// `None` if the backend keeps no index of scripts to answer from.
+    fn script_history(&self, script_pubkey: &Script) -> Result<Option<ScriptHistory>, Error>;
+
+    /// Returns the height of the best block.
+    fn tip_height(&self) -> Result<u32, Error>;
+
+    /// Returns a transaction by txid, or `None` if the backend doesn't know it.
+    fn transaction(&self, txid: &Txid) -> Result<Option<Transaction>, Error>;
+
+    /// Publishes a transaction and returns its txid.
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error>;
+
+    /// Estimates the fee rate needed to confirm within `target_blocks`.
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, Error>;
+}
+
+/// Looks up the giver's UTXOs among the given indices of the giver descriptor.
+///
+/// # Arguments
+///
+/// * `backend` - Where to look
+/// * `gift_tx` - Gift whose giver descriptor is searched
+/// * `indices` - Child indices of the giver descriptor to search
+///
+/// # Returns
+///
+/// The UTXOs found, with their index and confirmation height, in index order.
+pub fn giver_utxos(
+    backend: &dyn ChainBackend,
+    gift_tx: &GiftTransaction,
+    indices: Range<u32>,
+) -> Result<Vec<GiftUtxo>, Error> {
+    let mut utxos = Vec::new();
+    for index in indices {
+        let script_pubkey = gift_tx.giver_script_pubkey(index)?;
+        for found in backend.script_utxos(&script_pubkey)? {
+            let utxo = GiftUtxo::new(found.outpoint, found.txout.value, script_pubkey.clone(), index);
+            utxos.push(match found.confirmation_height {
+                Some(height) => utxo.with_confirmation_height(height),
+                None => utxo,
+            });
+        }
+    }
+
+    Ok(utxos)
+}
+
+/// Looks up the outputs paying a gift's address.
+pub fn gift_utxos(backend: &dyn ChainBackend, gift_tx: &GiftTransaction) -> Result<Vec<ChainUtxo>, Error> {
+    let address = gift_tx.script().create_address(gift_tx.keys())?;
+    backend.script_utxos(&address.script_pubkey())
+}
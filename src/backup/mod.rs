@@ -0,0 +1,223 @@
+use bitcoin::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::network::parse_network;
+use crate::registry::GiftRecord;
+use crate::script::LeafDescription;
+use crate::status::WatchedGift;
+use crate::timelock::Timelock;
+use crate::Error;
+
+/// Format name every backup starts with, so other JSON files are refused.
+pub const BACKUP_FORMAT: &str = "mallowbtc-gift-backup";
+
+/// Version of the backup format written by this build.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// A self-contained backup of one gift: what it takes to rebuild it and
+/// what it takes to spend it, so losing either half is survivable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GiftBackup {
+    pub format: String,
+    pub version: u32,
+    /// Hex SHA-256 of the compact JSON encoding of `gift`.
+    pub checksum: String,
+    pub gift: BackupGift,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupGift {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Unix time the gift was created.
+    pub created_at: u64,
+    /// Tool and version that wrote the backup.
+    pub created_by: String,
+    pub network: String,
+    pub address: String,
+    /// The descriptor `create` printed, to import the gift as watch-only.
+    pub descriptor: String,
+    pub index: u32,
+    pub giver: BackupKey,
+    pub receiver: BackupKey,
+    pub timelock: Timelock,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clawback: Option<Timelock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf_depths: Option<Vec<u8>>,
+    /// MuSig2 aggregate key, x-only hex.
+    pub internal_key: String,
+    pub leaves: Vec<LeafDescription>,
+    /// Gift output in the funding transaction, `txid:vout`, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_outpoint: Option<String>,
+}
+
+impl BackupGift {
+    /// Returns the hex SHA-256 of the gift's compact JSON encoding.
+    pub fn checksum(&self) -> Result<String, Error> {
+        let bytes = serde_json::to_vec(self)
+            .map_err(|e| Error::BackupError(format!("Failed to encode backup: {}", e)))?;
+        Ok(sha256::Hash::hash(&bytes).to_string())
+    }
+}
+
+/// One party's key: the descriptor it was derived from and where it ended up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupKey {
+    /// The descriptor as given, before derivation at the gift's index.
+    pub descriptor: String,
+    /// Full derivation path of the gift key, `[fingerprint/path]`, if the
+    /// descriptor carries an origin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The gift key, compressed hex.
+    pub public_key: String,
+}
+
+impl GiftBackup {
+    /// Backs up a recorded gift.
+    pub fn new(record: &GiftRecord) -> Result<Self, Error> {
+        let gift = contents(record)?;
+        Ok(Self {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            checksum: gift.checksum()?,
+            gift,
+        })
+    }
+
+    /// Parses a backup, checking its format, version and checksum.
+    ///
+    /// This only proves the file is intact; [`GiftBackup::verify`] proves
+    /// it describes the gift it claims to.
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let backup: Self = serde_json::from_str(text)
+            .map_err(|e| Error::BackupError(format!("Not a gift backup: {}", e)))?;
+        if backup.format != BACKUP_FORMAT {
+            return Err(Error::BackupError(format!("Unknown backup format '{}'", backup.format)));
+        }
+        if backup.version > BACKUP_VERSION {
+            return Err(Error::BackupError(format!(
+                "Backup version {} is newer than the version {} this build understands",
+                backup.version, BACKUP_VERSION
+            )));
+        }
+        if backup.gift.checksum()? != backup.checksum {
+            return Err(Error::BackupError(
+                "Checksum mismatch: the backup was modified or damaged".to_string(),
+            ));
+        }
+
+        Ok(backup)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| Error::BackupError(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::from_json(&text)
+    }
+
+    /// Writes the backup as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::BackupError(format!("Failed to encode backup: {}", e)))?;
+        fs::write(path, text).map_err(|e| Error::BackupError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// Rebuilds the gift from its keys and timelocks and checks it matches
+    /// every address, key, script and control block in the backup.
+    ///
+    /// # Returns
+    ///
+    /// The gift as a registry record, or a BackupError naming the first
+    /// part of the backup that doesn't match the rebuilt gift.
+    pub fn verify(&self) -> Result<GiftRecord, Error> {
+        let gift = &self.gift;
+        let network = parse_network(&gift.network)?;
+
+        let mut record = GiftRecord::rebuild(
+            &gift.giver.descriptor,
+            &gift.receiver.descriptor,
+            gift.index,
+            network,
+            gift.timelock,
+            gift.clawback,
+            gift.leaf_depths.as_deref(),
+        )?
+        .with_label(gift.label.clone());
+        record.created_at = gift.created_at;
+        record.funding_outpoint = gift.funding_outpoint.clone();
+        record.funding_txid = gift
+            .funding_outpoint
+            .as_ref()
+            .and_then(|outpoint| outpoint.split(':').next())
+            .map(str::to_string);
+
+        if record.address != gift.address {
+            return Err(Error::BackupError(format!(
+                "The keys and timelocks rebuild address {}, not the backed up {}",
+                record.address, gift.address
+            )));
+        }
+        let watched = WatchedGift::from_descriptor_str(&gift.descriptor, gift.index, network)?;
+        if watched.address.to_string() != gift.address {
+            return Err(Error::BackupError(format!(
+                "The descriptor pays {}, not the backed up {}",
+                watched.address, gift.address
+            )));
+        }
+
+        let rebuilt = contents(&record)?;
+        let checks = [
+            ("descriptor", rebuilt.descriptor == gift.descriptor),
+            ("giver key", rebuilt.giver == gift.giver),
+            ("receiver key", rebuilt.receiver == gift.receiver),
+            ("internal key", rebuilt.internal_key == gift.internal_key),
+            ("leaves", rebuilt.leaves == gift.leaves),
+        ];
+        if let Some((part, _)) = checks.iter().find(|(_, matches)| !matches) {
+            return Err(Error::BackupError(format!("The backed up {} doesn't match the rebuilt gift", part)));
+        }
+
+        Ok(record)
+    }
+}
+
+/// Gathers everything a backup holds about a recorded gift.
+fn contents(record: &GiftRecord) -> Result<BackupGift, Error> {
+    let (keys, script) = record.gift()?;
+    let leaves = script.describe_leaves(&keys)?;
+
+    let key_path = |origin: Option<bitcoin::bip32::KeySource>| {
+        origin.map(|(fingerprint, path)| format!("[{}/{}]", fingerprint, path))
+    };
+
+    Ok(BackupGift {
+        label: record.label.clone(),
+        created_at: record.created_at,
+        created_by: format!("mallowbtc {}", env!("CARGO_PKG_VERSION")),
+        network: record.network.clone(),
+        address: record.address.clone(),
+        descriptor: record.descriptor.clone(),
+        index: record.index,
+        giver: BackupKey {
+            descriptor: record.giver.clone(),
+            path: key_path(keys.giver_key_origin()?),
+            public_key: keys.giver_pub()?.to_string(),
+        },
+        receiver: BackupKey {
+            descriptor: record.receiver.clone(),
+            path: key_path(keys.receiver_key_origin()?),
+            public_key: keys.receiver_pub()?.to_string(),
+        },
+        timelock: record.timelock,
+        clawback: record.clawback,
+        leaf_depths: record.leaf_depths.clone(),
+        internal_key: keys.aggregate_musig2_key()?.to_string(),
+        leaves,
+        funding_outpoint: record.funding_outpoint.clone(),
+    })
+}
+
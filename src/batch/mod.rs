@@ -0,0 +1,323 @@
+use bitcoin::{Address, Amount, FeeRate, Network, Psbt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use crate::coin_selection::{self, SelectionOptions};
+use crate::keys::GiftKeys;
+use crate::network::network_name;
+use crate::script::{GiftScript, LeafDescription};
+use crate::timelock::Timelock;
+use crate::transaction::{GiftTransaction, GiftUtxo};
+use crate::Error;
+
+/// One row of a batch manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchGift {
+    pub label: String,
+    /// Receiver descriptor key, `[fingerprint/path]tpub.../0/*`.
+    pub receiver: String,
+    pub amount: Amount,
+    pub timelock: Timelock,
+    /// Derivation index of the gift; by default the batch's first index plus
+    /// the row's position.
+    pub index: Option<u32>,
+}
+
+/// A manifest row as written in JSON, where the timelock may be a number
+/// of blocks or any notation `Timelock` parses.
+#[derive(Deserialize)]
+struct JsonGift {
+    label: String,
+    receiver: String,
+    amount: u64,
+    timelock: JsonTimelock,
+    #[serde(default)]
+    index: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonTimelock {
+    Blocks(u32),
+    Spec(String),
+}
+
+/// Reads a manifest, as JSON if the file ends in `.json` and as CSV otherwise.
+pub fn load_manifest(path: &Path) -> Result<Vec<BatchGift>, Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::BatchError(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if is_json {
+        parse_json_manifest(&text)
+    } else {
+        parse_csv_manifest(&text)
+    }
+}
+
+/// Parses a JSON manifest: an array of objects with `label`, `receiver`,
+/// `amount` (satoshis), `timelock` and an optional `index`.
+pub fn parse_json_manifest(text: &str) -> Result<Vec<BatchGift>, Error> {
+    let rows: Vec<JsonGift> =
+        serde_json::from_str(text).map_err(|e| Error::BatchError(format!("Invalid JSON manifest: {}", e)))?;
+
+    rows.into_iter()
+        .map(|row| {
+            let timelock = match row.timelock {
+                JsonTimelock::Blocks(blocks) => Timelock::Blocks(blocks),
+                JsonTimelock::Spec(spec) => spec.parse()?,
+            };
+            Ok(BatchGift {
+                label: row.label,
+                receiver: row.receiver,
+                amount: Amount::from_sat(row.amount),
+                timelock,
+                index: row.index,
+            })
+        })
+        .collect()
+}
+
+/// Parses a CSV manifest with a header row naming the `label`, `receiver`,
+/// `amount` (satoshis) and `timelock` columns, plus an optional `index`
+/// column. Fields may be double-quoted; blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_csv_manifest(text: &str) -> Result<Vec<BatchGift>, Error> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let (_, header) = lines
+        .next()
+        .ok_or_else(|| Error::BatchError("Manifest is empty".to_string()))?;
+    let columns: Vec<String> = split_csv_line(header)?
+        .iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| columns.iter().position(|column| column == name);
+    let required = |name: &str| {
+        column(name).ok_or_else(|| Error::BatchError(format!("Manifest has no '{}' column", name)))
+    };
+    let (label, receiver, amount, timelock) =
+        (required("label")?, required("receiver")?, required("amount")?, required("timelock")?);
+    let index = column("index");
+
+    lines
+        .map(|(number, line)| {
+            let invalid = |reason: String| Error::BatchError(format!("Manifest line {}: {}", number + 1, reason));
+            let fields = split_csv_line(line)?;
+            if fields.len() != columns.len() {
+                return Err(invalid(format!("expected {} fields, found {}", columns.len(), fields.len())));
+            }
+            let field = |position: usize| fields[position].trim();
+
+            Ok(BatchGift {
+                label: field(label).to_string(),
+                receiver: field(receiver).to_string(),
+                amount: field(amount)
+                    .parse()
+                    .map(Amount::from_sat)
+                    .map_err(|_| invalid("amount must be a whole number of satoshis".to_string()))?,
+                timelock: field(timelock).parse().map_err(|e: Error| invalid(e.to_string()))?,
+                index: match index.map(field) {
+                    Some(index) if !index.is_empty() => Some(
+                        index
+                            .parse()
+                            .map_err(|_| invalid("index must be a derivation index".to_string()))?,
+                    ),
+                    _ => None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Splits one CSV line into fields, honouring double quotes and `""` escapes.
+fn split_csv_line(line: &str) -> Result<Vec<String>, Error> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(Error::BatchError(format!("Unterminated quote in manifest line: {}", line)));
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
+
+/// A manifest row turned into a gift.
+pub struct PlannedGift {
+    pub gift: BatchGift,
+    pub index: u32,
+    pub transaction: GiftTransaction,
+    pub address: Address,
+}
+
+/// Every gift of a manifest, derived from one giver and funded together.
+pub struct BatchPlan {
+    pub network: Network,
+    pub gifts: Vec<PlannedGift>,
+}
+
+impl BatchPlan {
+    /// Derives one gift per manifest row.
+    ///
+    /// # Arguments
+    ///
+    /// * `giver` - The giver's descriptor key, shared by every gift
+    /// * `manifest` - The gifts to create
+    /// * `first_index` - Index of the first row that names none
+    /// * `network` - Network the gifts live on
+    ///
+    /// # Returns
+    ///
+    /// The plan, or an error naming the row whose keys or timelock are
+    /// invalid, or a BatchError if two rows would share an address.
+    pub fn new(giver: &str, manifest: Vec<BatchGift>, first_index: u32, network: Network) -> Result<Self, Error> {
+        if manifest.is_empty() {
+            return Err(Error::BatchError("Manifest lists no gifts".to_string()));
+        }
+
+        let mut addresses = HashSet::new();
+        let mut gifts = Vec::with_capacity(manifest.len());
+        for (position, gift) in manifest.into_iter().enumerate() {
+            let index = match gift.index {
+                Some(index) => index,
+                None => first_index
+                    .checked_add(position as u32)
+                    .ok_or_else(|| Error::BatchError("Gift index overflows".to_string()))?,
+            };
+            let in_row = |e: Error| Error::BatchError(format!("Gift '{}': {}", gift.label, e));
+
+            let keys = GiftKeys::from_descriptor_strings(giver, &gift.receiver)
+                .and_then(|keys| keys.at_index(index))
+                .and_then(|keys| keys.with_network(network))
+                .map_err(in_row)?;
+            let script = GiftScript::from_timelock(gift.timelock).with_network(network);
+            let address = script.create_address(&keys).map_err(in_row)?;
+            if !addresses.insert(address.clone()) {
+                return Err(Error::BatchError(format!(
+                    "Gift '{}' has the same address as an earlier gift; give it another index",
+                    gift.label
+                )));
+            }
+
+            gifts.push(PlannedGift {
+                gift,
+                index,
+                transaction: GiftTransaction::new(keys, script),
+                address,
+            });
+        }
+
+        Ok(Self { network, gifts })
+    }
+
+    /// Returns the total amount of all gifts.
+    pub fn total(&self) -> Amount {
+        self.gifts.iter().map(|planned| planned.gift.amount).sum()
+    }
+
+    /// Returns each gift's transaction builder with its amount, in order.
+    pub fn outputs(&self) -> Vec<(&GiftTransaction, Amount)> {
+        self.gifts
+            .iter()
+            .map(|planned| (&planned.transaction, planned.gift.amount))
+            .collect()
+    }
+
+    /// Builds one unsigned PSBT with an output per gift, in manifest order,
+    /// from UTXOs chosen among `utxos` by `options`.
+    pub fn fund(
+        &self,
+        utxos: &[GiftUtxo],
+        fee_rate: FeeRate,
+        change_index: u32,
+        options: &SelectionOptions,
+    ) -> Result<Psbt, Error> {
+        let outputs = self.outputs();
+        let selected = coin_selection::select_batch_utxos(&outputs, utxos, fee_rate, change_index, options)?;
+        GiftTransaction::fund_batch(&outputs, &selected, fee_rate, change_index)
+    }
+
+    /// Lists every gift with what its receiver needs to find and spend it.
+    pub fn report(&self, psbt: &Psbt) -> Result<BatchReport, Error> {
+        let txid = psbt.unsigned_tx.compute_txid();
+        let gifts = self
+            .gifts
+            .iter()
+            .enumerate()
+            .map(|(vout, planned)| {
+                let keys = planned.transaction.keys();
+                let script = planned.transaction.script();
+                Ok(ReportGift {
+                    label: planned.gift.label.clone(),
+                    receiver: planned.gift.receiver.clone(),
+                    index: planned.index,
+                    amount_sat: planned.gift.amount.to_sat(),
+                    timelock: planned.gift.timelock.to_string(),
+                    outpoint: format!("{}:{}", txid, vout),
+                    address: planned.address.to_string(),
+                    descriptor: script.descriptor(keys)?.to_string(),
+                    leaves: script.describe_leaves(keys)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(BatchReport {
+            network: network_name(self.network).to_string(),
+            funding_txid: txid.to_string(),
+            gifts,
+        })
+    }
+}
+
+/// The record written after a batch is planned, one entry per gift.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub network: String,
+    /// Txid of the funding transaction; it does not change when signed.
+    pub funding_txid: String,
+    pub gifts: Vec<ReportGift>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportGift {
+    pub label: String,
+    pub receiver: String,
+    pub index: u32,
+    pub amount_sat: u64,
+    pub timelock: String,
+    /// Gift output in the funding transaction, `txid:vout`.
+    pub outpoint: String,
+    pub address: String,
+    /// Watch-only `tr()` descriptor with checksum.
+    pub descriptor: String,
+    pub leaves: Vec<LeafDescription>,
+}
+
+impl BatchReport {
+    /// Writes the report as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::BatchError(format!("Failed to encode report: {}", e)))?;
+        fs::write(path, text).map_err(|e| Error::BatchError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
@@ -0,0 +1,301 @@
+use bdk_wallet::chain::{BlockId, ChainPosition, ConfirmationBlockTime};
+use bdk_wallet::coin_selection::{
+    BranchAndBoundCoinSelection, CoinSelectionAlgorithm, CoinSelectionResult, InsufficientFunds,
+    LargestFirstCoinSelection, OldestFirstCoinSelection, SingleRandomDraw,
+};
+use bdk_wallet::{KeychainKind, LocalOutput, Utxo, WeightedUtxo};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::rand::thread_rng;
+use bitcoin::{Amount, BlockHash, FeeRate, OutPoint, ScriptBuf, TxOut, Weight};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use crate::fee::fee_at;
+use crate::transaction::{GiftTransaction, GiftUtxo};
+use crate::Error;
+
+/// Weight of the segwit marker and flag, which bdk leaves out of the target.
+const SEGWIT_MARKER_WEIGHT: Weight = Weight::from_wu(2);
+
+/// Weight of the scriptSig length and witness count bytes, which bdk counts
+/// with the rest of the input rather than in its satisfaction weight.
+const INPUT_LENGTH_PREFIX_WEIGHT: Weight = Weight::from_wu(5);
+
+/// How the UTXOs that fund a gift are chosen from those available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoinSelection {
+    /// Spend every available UTXO.
+    #[default]
+    All,
+    /// Branch and bound: look for a set that needs no change, falling back
+    /// to a single random draw with change when there is none.
+    BranchAndBound,
+    /// Spend the largest UTXOs first.
+    LargestFirst,
+    /// Spend the longest-confirmed UTXOs first; unconfirmed ones go last.
+    OldestFirst,
+}
+
+impl fmt::Display for CoinSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoinSelection::All => write!(f, "all"),
+            CoinSelection::BranchAndBound => write!(f, "bnb"),
+            CoinSelection::LargestFirst => write!(f, "largest-first"),
+            CoinSelection::OldestFirst => write!(f, "oldest-first"),
+        }
+    }
+}
+
+impl FromStr for CoinSelection {
+    type Err = Error;
+
+    /// Parses `all`, `bnb`, `largest-first` or `oldest-first`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(CoinSelection::All),
+            "bnb" | "branch-and-bound" => Ok(CoinSelection::BranchAndBound),
+            "largest-first" => Ok(CoinSelection::LargestFirst),
+            "oldest-first" => Ok(CoinSelection::OldestFirst),
+            _ => Err(Error::CoinSelectionError(format!(
+                "Unknown coin selection '{}' (expected all, bnb, largest-first or oldest-first)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What to spend when funding a gift.
+#[derive(Debug, Clone, Default)]
+pub struct SelectionOptions {
+    pub strategy: CoinSelection,
+    /// UTXOs that must be spent.
+    pub include: Vec<OutPoint>,
+    /// UTXOs that must not be spent.
+    pub exclude: Vec<OutPoint>,
+    /// Only spend UTXOs that share one output script, so the funding
+    /// transaction does not link the giver's addresses together.
+    pub privacy: bool,
+}
+
+/// Chooses which of `utxos` fund a gift of `amount`.
+///
+/// Selection runs through `bdk_wallet`'s coin selection algorithms, with
+/// each UTXO weighted by the witness that spends it. In privacy mode the
+/// UTXOs are grouped by output script and the cheapest group that covers
+/// the gift is used.
+///
+/// # Arguments
+///
+/// * `gift_tx` - The gift being funded
+/// * `utxos` - Giver UTXOs available to spend
+/// * `amount` - Amount the gift address receives
+/// * `fee_rate` - Fee rate for the funding transaction
+/// * `change_index` - Giver descriptor index change would be sent to
+/// * `options` - Strategy, includes, excludes and privacy mode
+///
+/// # Returns
+///
+/// The UTXOs to spend, or a CoinSelectionError if the includes and
+/// excludes conflict or no allowed set covers the gift and fee.
+pub fn select_utxos(
+    gift_tx: &GiftTransaction,
+    utxos: &[GiftUtxo],
+    amount: Amount,
+    fee_rate: FeeRate,
+    change_index: u32,
+    options: &SelectionOptions,
+) -> Result<Vec<GiftUtxo>, Error> {
+    select_batch_utxos(&[(gift_tx, amount)], utxos, fee_rate, change_index, options)
+}
+
+/// Chooses which of `utxos` fund several gifts in one transaction, as
+/// `GiftTransaction::fund_batch` builds it. Works like `select_utxos`.
+pub fn select_batch_utxos(
+    gifts: &[(&GiftTransaction, Amount)],
+    utxos: &[GiftUtxo],
+    fee_rate: FeeRate,
+    change_index: u32,
+    options: &SelectionOptions,
+) -> Result<Vec<GiftUtxo>, Error> {
+    let known: HashSet<OutPoint> = utxos.iter().map(|utxo| utxo.outpoint).collect();
+    for outpoint in options.include.iter().chain(&options.exclude) {
+        if !known.contains(outpoint) {
+            return Err(Error::CoinSelectionError(format!("UTXO {} is not among the available UTXOs", outpoint)));
+        }
+    }
+    if let Some(outpoint) = options.include.iter().find(|outpoint| options.exclude.contains(outpoint)) {
+        return Err(Error::CoinSelectionError(format!("UTXO {} is both included and excluded", outpoint)));
+    }
+
+    let candidates: Vec<GiftUtxo> = utxos
+        .iter()
+        .filter(|utxo| !options.exclude.contains(&utxo.outpoint))
+        .cloned()
+        .collect();
+
+    if !options.privacy {
+        return select_from(gifts, &candidates, fee_rate, change_index, options).map(|(selected, _)| selected);
+    }
+
+    // Group by output script, in order of first appearance
+    let mut scripts: Vec<ScriptBuf> = Vec::new();
+    for utxo in &candidates {
+        if !scripts.contains(&utxo.script_pubkey) {
+            scripts.push(utxo.script_pubkey.clone());
+        }
+    }
+
+    let included_scripts: HashSet<&ScriptBuf> = candidates
+        .iter()
+        .filter(|utxo| options.include.contains(&utxo.outpoint))
+        .map(|utxo| &utxo.script_pubkey)
+        .collect();
+    if included_scripts.len() > 1 {
+        return Err(Error::CoinSelectionError(
+            "Privacy mode cannot spend included UTXOs of different scripts together".to_string(),
+        ));
+    }
+
+    let mut best: Option<(Vec<GiftUtxo>, Amount)> = None;
+    let mut last_error = None;
+    for script in scripts {
+        if included_scripts.iter().any(|included| **included != script) {
+            continue;
+        }
+        let group: Vec<GiftUtxo> = candidates.iter().filter(|utxo| utxo.script_pubkey == script).cloned().collect();
+        match select_from(gifts, &group, fee_rate, change_index, options) {
+            Ok((selected, fee)) if best.as_ref().is_none_or(|(_, best_fee)| fee < *best_fee) => {
+                best = Some((selected, fee))
+            }
+            Ok(_) => {}
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match (best, last_error) {
+        (Some((selected, _)), _) => Ok(selected),
+        (None, Some(e)) => Err(Error::CoinSelectionError(format!("No single script's UTXOs cover the gift: {}", e))),
+        (None, None) => Err(Error::CoinSelectionError("No UTXOs to fund the gift with".to_string())),
+    }
+}
+
+/// Runs the selection strategy over `utxos` and returns the chosen UTXOs
+/// with the fee bdk attributes to them.
+fn select_from(
+    gifts: &[(&GiftTransaction, Amount)],
+    utxos: &[GiftUtxo],
+    fee_rate: FeeRate,
+    change_index: u32,
+    options: &SelectionOptions,
+) -> Result<(Vec<GiftUtxo>, Amount), Error> {
+    if utxos.is_empty() {
+        return Err(Error::CoinSelectionError("No UTXOs to fund the gift with".to_string()));
+    }
+    if options.strategy == CoinSelection::All {
+        // Every UTXO is spent; check they cover the gifts and the fee of a
+        // transaction without change
+        let needed = gifts.iter().map(|(_, amount)| *amount).sum::<Amount>();
+        let fee = fee_at(fee_rate, GiftTransaction::batch_funding_weight(gifts, utxos, None)?)?;
+        let available = utxos.iter().map(|utxo| utxo.amount).sum::<Amount>();
+        if available < needed + fee {
+            return Err(Error::CoinSelectionError(format!(
+                "UTXOs total {} but the gift and fee need {}",
+                available,
+                needed + fee
+            )));
+        }
+        return Ok((utxos.to_vec(), fee));
+    }
+
+    let (funder, _) = gifts
+        .first()
+        .ok_or_else(|| Error::CoinSelectionError("No gifts to fund".to_string()))?;
+
+    let mut required = Vec::new();
+    let mut optional = Vec::new();
+    for utxo in utxos {
+        let weighted = weighted_utxo(funder, utxo)?;
+        if options.include.contains(&utxo.outpoint) {
+            required.push(weighted);
+        } else {
+            optional.push(weighted);
+        }
+    }
+
+    // bdk counts the inputs; the target covers the gift outputs, the
+    // transaction header and the segwit marker
+    let amount = gifts.iter().map(|(_, amount)| *amount).sum::<Amount>();
+    let outputs_weight = GiftTransaction::batch_funding_weight(gifts, &[], None)?;
+    let target = amount + fee_at(fee_rate, outputs_weight + SEGWIT_MARKER_WEIGHT)?;
+    let change_script = funder.giver_script_pubkey(change_index)?;
+
+    let result = match options.strategy {
+        CoinSelection::All => unreachable!("handled above"),
+        CoinSelection::BranchAndBound => {
+            // Cost of a P2TR change output: value, script length and script
+            let size_of_change = 8 + 1 + change_script.len() as u64;
+            BranchAndBoundCoinSelection::new(size_of_change, SingleRandomDraw).coin_select(
+                required,
+                optional,
+                fee_rate,
+                target,
+                &change_script,
+                &mut thread_rng(),
+            )
+        }
+        CoinSelection::LargestFirst => {
+            LargestFirstCoinSelection.coin_select(required, optional, fee_rate, target, &change_script, &mut thread_rng())
+        }
+        CoinSelection::OldestFirst => {
+            OldestFirstCoinSelection.coin_select(required, optional, fee_rate, target, &change_script, &mut thread_rng())
+        }
+    };
+    let CoinSelectionResult { selected, fee_amount, .. } = result.map_err(insufficient_funds)?;
+
+    let selected = selected
+        .iter()
+        .filter_map(|chosen| utxos.iter().find(|utxo| utxo.outpoint == chosen.outpoint()))
+        .cloned()
+        .collect();
+    Ok((selected, fee_amount))
+}
+
+/// Describes a UTXO to bdk, weighted by the witness that spends it.
+fn weighted_utxo(gift_tx: &GiftTransaction, utxo: &GiftUtxo) -> Result<WeightedUtxo, Error> {
+    let chain_position = match utxo.confirmation_height {
+        Some(height) => ChainPosition::Confirmed {
+            anchor: ConfirmationBlockTime {
+                block_id: BlockId {
+                    height,
+                    hash: BlockHash::all_zeros(),
+                },
+                confirmation_time: 0,
+            },
+            transitively: None,
+        },
+        None => ChainPosition::Unconfirmed { last_seen: None },
+    };
+
+    Ok(WeightedUtxo {
+        satisfaction_weight: gift_tx.input_weight(utxo)?.weight() - INPUT_LENGTH_PREFIX_WEIGHT,
+        utxo: Utxo::Local(LocalOutput {
+            outpoint: utxo.outpoint,
+            txout: TxOut {
+                value: utxo.amount,
+                script_pubkey: utxo.script_pubkey.clone(),
+            },
+            keychain: KeychainKind::External,
+            is_spent: false,
+            derivation_index: utxo.index,
+            chain_position,
+        }),
+    })
+}
+
+fn insufficient_funds(e: InsufficientFunds) -> Error {
+    Error::CoinSelectionError(format!(
+        "UTXOs total {} but the gift and fee need {}",
+        e.available, e.needed
+    ))
+}
@@ -0,0 +1,357 @@
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use bitcoin::{Network, Psbt, Transaction};
+use musig2::secp::Point;
+use musig2::{AggNonce, BinaryEncoding, KeyAggContext, PartialSignature, PubNonce, SecNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use crate::keys::{from_point, to_point};
+use crate::storage;
+use crate::transaction::{self, GiftTransaction, MuSig2Signer};
+use crate::Error;
+
+/// Format version written to session and nonce files.
+pub const COOP_FORMAT_VERSION: u32 = 2;
+
+/// A cooperative MuSig2 keypath spend, passed between giver and receiver.
+///
+/// The session is a small JSON file around the spending PSBT. The PSBT
+/// lists both participants and, as the exchange progresses, collects each
+/// participant's public nonce and partial signature in the BIP-373 fields,
+/// so any BIP-373 wallet can take part. It contains nothing secret and can
+/// travel over any channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoopSession {
+    pub version: u32,
+    /// Random identifier binding nonce files to this session.
+    pub session_id: String,
+    pub network: Network,
+    /// Spending PSBT with the BIP-373 MuSig2 fields, base64.
+    pub psbt: String,
+}
+
+/// A participant's secret nonce for one session, kept on their machine.
+///
+/// The secret nonce is removed from the file before the partial signature
+/// that uses it is produced, so a nonce can never sign twice. `counter`
+/// counts the nonces generated for the session and is mixed into each new
+/// one, so even a retry after a lost file never repeats a nonce.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NonceState {
+    pub version: u32,
+    pub session_id: String,
+    /// Sighash the nonce was generated for, hex.
+    pub message: String,
+    pub public_key: String,
+    pub counter: u64,
+    /// Public nonce published to the session, hex.
+    pub public_nonce: String,
+    /// Secret nonce, hex; `None` once it has been used.
+    pub secret_nonce: Option<String>,
+}
+
+impl CoopSession {
+    /// Starts a session that spends the gift output cooperatively.
+    ///
+    /// # Arguments
+    ///
+    /// * `gift_tx` - The gift being spent
+    /// * `psbt` - Keypath spending PSBT from `GiftTransaction::spend_cooperatively`
+    ///
+    /// # Returns
+    ///
+    /// A session with no nonces or signatures yet.
+    pub fn start(gift_tx: &GiftTransaction, psbt: &Psbt) -> Result<Self, Error> {
+        let mut id = [0u8; 32];
+        thread_rng().fill_bytes(&mut id);
+
+        let session = Self {
+            version: COOP_FORMAT_VERSION,
+            session_id: hex::encode(id),
+            network: gift_tx.keys().network,
+            psbt: psbt.to_string(),
+        };
+
+        // Refuse to start a session whose key aggregate does not pay the gift
+        session.key_agg_context()?;
+        Ok(session)
+    }
+
+    /// Reads a session file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let session: Self = read_json(path)?;
+        if session.version != COOP_FORMAT_VERSION {
+            return Err(Error::CoopError(format!(
+                "Unsupported session version {} in {}",
+                session.version,
+                path.display()
+            )));
+        }
+        Ok(session)
+    }
+
+    /// Writes the session file.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        write_json(path, self)
+    }
+
+    /// Returns the spending PSBT with the nonces and partial signatures
+    /// collected so far.
+    pub fn psbt(&self) -> Result<Psbt, Error> {
+        Psbt::from_str(&self.psbt).map_err(|e| Error::CoopError(format!("Invalid session PSBT: {}", e)))
+    }
+
+    /// Returns the BIP-341 keypath sighash both participants sign.
+    pub fn message(&self) -> Result<[u8; 32], Error> {
+        transaction::key_spend_sighash(&self.psbt()?, 0)
+    }
+
+    /// Builds the key aggregation context from the participants listed in
+    /// the PSBT, tweaked for the gift's taproot tree, and checks it matches
+    /// the output being spent.
+    pub fn key_agg_context(&self) -> Result<KeyAggContext, Error> {
+        let psbt = self.psbt()?;
+        if psbt.inputs.len() != 1 {
+            return Err(Error::CoopError("Session PSBT must spend exactly the gift output".to_string()));
+        }
+        if psbt.inputs[0].tap_merkle_root.is_none() {
+            return Err(Error::CoopError("Session PSBT input has no tap_merkle_root".to_string()));
+        }
+        transaction::key_path_context(&psbt.inputs[0]).map(|(ctx, _)| ctx)
+    }
+
+    /// Returns the participants' public keys in aggregation order.
+    pub fn participants(&self) -> Result<Vec<PublicKey>, Error> {
+        self.key_agg_context()?
+            .pubkeys()
+            .iter()
+            .map(|point| from_point(*point))
+            .collect()
+    }
+
+    /// Returns the public nonces published so far, by participant.
+    pub fn nonces(&self) -> Result<BTreeMap<PublicKey, PubNonce>, Error> {
+        let nonces = transaction::musig2_pub_nonces(&self.psbt()?.inputs[0])?;
+        Ok(nonces.into_iter().map(|(signer, nonce)| (signer.participant, nonce)).collect())
+    }
+
+    /// Returns the partial signatures added so far, by participant.
+    pub fn partial_signatures(&self) -> Result<BTreeMap<PublicKey, PartialSignature>, Error> {
+        let partials = transaction::musig2_partial_signatures(&self.psbt()?.inputs[0])?;
+        Ok(partials.into_iter().map(|(signer, partial)| (signer.participant, partial)).collect())
+    }
+
+    /// Generates a fresh nonce for the holder of `secret` and publishes its
+    /// public half to the session.
+    ///
+    /// Any partial signatures already in the session were made against the
+    /// old nonces and are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The participant's secret key at the gift index
+    /// * `previous` - The participant's existing nonce state, if any; one from
+    ///   another session is ignored
+    ///
+    /// # Returns
+    ///
+    /// The new nonce state, which must be saved before the session is shared.
+    pub fn generate_nonce(&mut self, secret: &SecretKey, previous: Option<&NonceState>) -> Result<NonceState, Error> {
+        let signer = self.signer(secret)?;
+        let public_key = signer.participant.to_string();
+        let message = self.message()?;
+        // A nonce file left by an earlier session is replaced; the session id
+        // goes into the nonce, so starting its counter over reuses nothing
+        let counter = match previous.filter(|state| state.session_id == self.session_id) {
+            Some(state) => {
+                state.check(self, &public_key)?;
+                state.counter + 1
+            }
+            None => 0,
+        };
+
+        let ctx = self.key_agg_context()?;
+        let mut seed = [0u8; 32];
+        thread_rng().fill_bytes(&mut seed);
+        let session_id = self.session_id.clone();
+        let counter_bytes = counter.to_be_bytes();
+        let aggregated: Point = ctx.aggregated_pubkey();
+        let secnonce = SecNonce::build(seed)
+            .with_seckey(to_scalar(secret)?)
+            .with_pubkey(to_point(&signer.participant)?)
+            .with_aggregated_pubkey(aggregated)
+            .with_message(&message)
+            .with_extra_input(&session_id)
+            .with_extra_input(&counter_bytes)
+            .build();
+        let public_nonce = secnonce.public_nonce();
+
+        let mut psbt = self.psbt()?;
+        transaction::set_musig2_pub_nonce(&mut psbt.inputs[0], signer, &public_nonce);
+        transaction::clear_musig2_partial_signatures(&mut psbt.inputs[0]);
+        self.psbt = psbt.to_string();
+
+        Ok(NonceState {
+            version: COOP_FORMAT_VERSION,
+            session_id: self.session_id.clone(),
+            message: hex::encode(message),
+            public_key,
+            counter,
+            public_nonce: hex::encode(public_nonce.to_bytes()),
+            secret_nonce: Some(hex::encode(secnonce.to_bytes())),
+        })
+    }
+
+    /// Returns the aggregate of both participants' public nonces.
+    pub fn aggregated_nonce(&self) -> Result<AggNonce, Error> {
+        let published = self.nonces()?;
+        let nonces = self
+            .participants()?
+            .iter()
+            .map(|key| {
+                published
+                    .get(key)
+                    .cloned()
+                    .ok_or_else(|| Error::CoopError(format!("Waiting for the nonce of {}", key)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AggNonce::sum(nonces))
+    }
+
+    /// Adds the partial signature of the holder of `secret`.
+    ///
+    /// `secnonce` must come from `NonceState::take_secret_nonce`, which
+    /// erases it from disk first.
+    pub fn sign(&mut self, secret: &SecretKey, secnonce: SecNonce) -> Result<(), Error> {
+        let signer = self.signer(secret)?;
+        let published = self.nonces()?.remove(&signer.participant).ok_or_else(|| {
+            Error::CoopError("This participant has not published a nonce".to_string())
+        })?;
+        if published != secnonce.public_nonce() {
+            return Err(Error::CoopError(
+                "Secret nonce does not match the nonce published in the session".to_string(),
+            ));
+        }
+
+        let ctx = self.key_agg_context()?;
+        let partial: PartialSignature = musig2::sign_partial(
+            &ctx,
+            to_scalar(secret)?,
+            secnonce,
+            &self.aggregated_nonce()?,
+            self.message()?,
+        )
+        .map_err(|e| Error::CoopError(format!("Failed to sign: {}", e)))?;
+
+        let mut psbt = self.psbt()?;
+        transaction::set_musig2_partial_signature(&mut psbt.inputs[0], signer, partial);
+        self.psbt = psbt.to_string();
+        Ok(())
+    }
+
+    /// Aggregates both partial signatures into the keypath signature and
+    /// returns the finalized transaction.
+    pub fn combine(&self) -> Result<Transaction, Error> {
+        let signed = self.partial_signatures()?;
+        for key in self.participants()? {
+            if !signed.contains_key(&key) {
+                return Err(Error::CoopError(format!("Waiting for the partial signature of {}", key)));
+            }
+        }
+
+        GiftTransaction::finalize(self.psbt()?)
+    }
+
+    /// Returns the BIP-373 signer for `secret`, or an error if the key is
+    /// not one of the participants.
+    fn signer(&self, secret: &SecretKey) -> Result<MuSig2Signer, Error> {
+        let public_key = secret.public_key(&Secp256k1::signing_only());
+        if !self.participants()?.contains(&public_key) {
+            return Err(Error::CoopError(format!(
+                "Key {} is not a participant in this gift; check the key and --index",
+                public_key
+            )));
+        }
+
+        let aggregate = from_point(self.key_agg_context()?.aggregated_pubkey_untweaked())?;
+        Ok(MuSig2Signer::key_path(public_key, aggregate))
+    }
+}
+
+impl NonceState {
+    /// Reads a nonce file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        read_json(path)
+    }
+
+    /// Writes the nonce file, readable only by its owner where supported.
+    ///
+    /// The file is replaced through `storage::write_owner_only`, so the
+    /// secret nonce is never readable by others, not even briefly.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::CoopError(format!("Failed to encode {}: {}", path.display(), e)))?;
+
+        storage::write_owner_only(path, text.as_bytes())
+            .map_err(|e| Error::CoopError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// Takes the secret nonce out of the nonce file at `path`.
+    ///
+    /// The file is rewritten without the secret nonce before it is
+    /// returned, so it cannot be used again even if signing fails.
+    ///
+    /// # Returns
+    ///
+    /// The secret nonce or a CoopError if it belongs to another session,
+    /// participant or transaction, or has already been used.
+    pub fn take_secret_nonce(path: &Path, session: &CoopSession, secret: &SecretKey) -> Result<SecNonce, Error> {
+        let mut state = Self::load(path)?;
+        state.check(session, &session.signer(secret)?.participant.to_string())?;
+        if state.message != hex::encode(session.message()?) {
+            return Err(Error::CoopError(
+                "The session transaction changed since this nonce was made; run coop nonce again".to_string(),
+            ));
+        }
+
+        let secnonce = state.secret_nonce.take().ok_or_else(|| {
+            Error::CoopError("This nonce has already been used; run coop nonce again".to_string())
+        })?;
+        state.save(path)?;
+
+        let bytes = hex::decode(secnonce).map_err(|e| Error::CoopError(format!("Invalid secret nonce: {}", e)))?;
+        SecNonce::from_bytes(&bytes).map_err(|e| Error::CoopError(format!("Invalid secret nonce: {}", e)))
+    }
+
+    /// Checks this state belongs to `session` and `public_key`.
+    fn check(&self, session: &CoopSession, public_key: &str) -> Result<(), Error> {
+        if self.session_id != session.session_id {
+            return Err(Error::CoopError("Nonce file belongs to a different session".to_string()));
+        }
+        if self.public_key != public_key {
+            return Err(Error::CoopError("Nonce file belongs to the other participant".to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn to_scalar(secret: &SecretKey) -> Result<musig2::secp::Scalar, Error> {
+    musig2::secp::Scalar::from_slice(&secret.secret_bytes())
+        .map_err(|e| Error::CoopError(format!("Invalid secret key: {}", e)))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::CoopError(format!("Failed to read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&text).map_err(|e| Error::CoopError(format!("Invalid file {}: {}", path.display(), e)))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::CoopError(format!("Failed to encode {}: {}", path.display(), e)))?;
+    fs::write(path, text).map_err(|e| Error::CoopError(format!("Failed to write {}: {}", path.display(), e)))
+}
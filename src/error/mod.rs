@@ -9,8 +9,44 @@ pub enum Error {
     #[error("Script error: {0}")]
     ScriptError(String),
     
+    #[error("Timelock error: {0}")]
+    TimelockError(String),
+
     #[error("Transaction error: {0}")]
     TransactionError(String),
+
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
+    #[error("Mainnet safety check failed: {0}")]
+    SafetyError(String),
+
+    #[error("Cooperative signing error: {0}")]
+    CoopError(String),
+
+    #[error("Fee error: {0}")]
+    FeeError(String),
+
+    #[error("Coin selection error: {0}")]
+    CoinSelectionError(String),
+
+    #[error("Batch error: {0}")]
+    BatchError(String),
+
+    #[error("Vesting error: {0}")]
+    VestingError(String),
+
+    #[error("Chain backend error: {0}")]
+    BackendError(String),
+
+    #[error("Gift registry error: {0}")]
+    RegistryError(String),
+
+    #[error("Gift backup error: {0}")]
+    BackupError(String),
+
+    #[error("Recovery error: {0}")]
+    RecoveryError(String),
     
     #[error(transparent)]
     BdkError(#[from] BdkError),
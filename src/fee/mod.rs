@@ -0,0 +1,204 @@
+use bitcoin::transaction::{predict_weight, InputWeightPrediction};
+use bitcoin::{Amount, FeeRate, Weight};
+use std::fmt;
+use std::str::FromStr;
+use crate::keys::GiftKeys;
+use crate::script::{GiftScript, LeafRole, SpendPath};
+use crate::Error;
+
+/// Length of a P2TR output script, the destination assumed for estimates.
+pub const P2TR_SCRIPT_LEN: usize = 34;
+
+/// Smallest P2TR output relayed by default nodes.
+pub const P2TR_DUST_LIMIT: Amount = Amount::from_sat(330);
+
+/// Fee rate (100 sat/vB) that past fee spikes have reached. A gift whose
+/// break-even rate is lower may be uneconomical to claim for long stretches.
+pub const HIGH_FEE_RATE: FeeRate = FeeRate::from_sat_per_kwu(25_000);
+
+/// What it costs to claim a gift through one spending path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimCost {
+    pub path: SpendPath,
+    /// Upper bound on the witness weight, from miniscript.
+    pub satisfaction_weight: Weight,
+    /// Exact weight of the claiming transaction to a P2TR address.
+    pub weight: Weight,
+}
+
+impl ClaimCost {
+    /// Returns the fee of the claiming transaction at `fee_rate`.
+    pub fn fee(&self, fee_rate: FeeRate) -> Result<Amount, Error> {
+        fee_at(fee_rate, self.weight)
+    }
+
+    /// Returns the highest fee rate at which claiming a gift of `amount`
+    /// still leaves more than dust, or `None` if the gift is dust already.
+    pub fn break_even_fee_rate(&self, amount: Amount) -> Option<FeeRate> {
+        let spendable = amount.checked_sub(P2TR_DUST_LIMIT).filter(|spendable| *spendable > Amount::ZERO)?;
+        Some(FeeRate::from_sat_per_kwu(spendable.to_sat() * 1000 / self.weight.to_wu()))
+    }
+}
+
+/// Returns the claim cost of every spending path of the gift, keypath first.
+pub fn claim_costs(keys: &GiftKeys, script: &GiftScript) -> Result<Vec<ClaimCost>, Error> {
+    script
+        .satisfaction_weights(keys)?
+        .into_iter()
+        .map(|(path, satisfaction_weight)| {
+            Ok(ClaimCost {
+                path,
+                satisfaction_weight,
+                weight: spend_weight(keys, script, path, P2TR_SCRIPT_LEN)?,
+            })
+        })
+        .collect()
+}
+
+/// Checks a gift of `amount` is worth claiming on its own through the
+/// receiver leaf, the path the receiver can always use.
+///
+/// # Returns
+///
+/// A warning for each problem found: a dust-sized gift, or one that
+/// becomes uneconomical to claim below `HIGH_FEE_RATE`.
+pub fn viability_warnings(costs: &[ClaimCost], amount: Amount) -> Vec<String> {
+    let Some(receiver) = costs.iter().find(|cost| cost.path == SpendPath::Leaf(LeafRole::Receiver)) else {
+        return Vec::new();
+    };
+
+    match receiver.break_even_fee_rate(amount) {
+        None => vec![format!(
+            "A gift of {} sat is at or below the {} sat dust limit and cannot be claimed",
+            amount.to_sat(),
+            P2TR_DUST_LIMIT.to_sat()
+        )],
+        Some(rate) if rate < HIGH_FEE_RATE => vec![format!(
+            "Above {} sat/vB, claiming a gift of {} sat through the receiver leaf leaves only dust; \
+             fees have passed {} sat/vB before, so the gift may be uneconomical to claim for long stretches",
+            format_fee_rate(rate),
+            amount.to_sat(),
+            format_fee_rate(HIGH_FEE_RATE)
+        )],
+        Some(_) => Vec::new(),
+    }
+}
+
+/// Who bears the fee of redeeming the gift.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeeMode {
+    /// The gift output holds the stated amount and the receiver pays the
+    /// redemption fee out of it.
+    #[default]
+    AmountSent,
+    /// The giver adds the redemption fee to the gift output, so the receiver
+    /// is left with the stated amount after redeeming.
+    AmountReceived,
+}
+
+impl FeeMode {
+    /// Returns the value of the gift output for a gift of `amount`.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - Amount the giver means to give
+    /// * `redemption_fee` - Expected fee of the receiver's redemption
+    pub fn gift_output(self, amount: Amount, redemption_fee: Amount) -> Result<Amount, Error> {
+        match self {
+            FeeMode::AmountSent => Ok(amount),
+            FeeMode::AmountReceived => amount
+                .checked_add(redemption_fee)
+                .ok_or_else(|| Error::FeeError("Gift amount overflows".to_string())),
+        }
+    }
+}
+
+impl fmt::Display for FeeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeMode::AmountSent => write!(f, "sent"),
+            FeeMode::AmountReceived => write!(f, "received"),
+        }
+    }
+}
+
+impl FromStr for FeeMode {
+    type Err = Error;
+
+    /// Parses `sent` or `received`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "sent" => Ok(FeeMode::AmountSent),
+            "received" => Ok(FeeMode::AmountReceived),
+            _ => Err(Error::FeeError(format!(
+                "Unknown fee mode '{}' (expected 'sent' or 'received')",
+                s
+            ))),
+        }
+    }
+}
+
+/// Returns the witness that spends a gift output through `path`.
+///
+/// Keypath spends carry one 64-byte signature. Leaf spends carry the leaf
+/// key's signature, the leaf script and its control block, whose size grows
+/// with the leaf's depth in the tree.
+///
+/// # Returns
+///
+/// The witness prediction or a ScriptError if the gift has no such leaf.
+pub fn spend_witness(keys: &GiftKeys, script: &GiftScript, path: SpendPath) -> Result<InputWeightPrediction, Error> {
+    let role = match path {
+        SpendPath::KeyPath => return Ok(InputWeightPrediction::P2TR_KEY_DEFAULT_SIGHASH),
+        SpendPath::Leaf(role) => role,
+    };
+
+    let leaf = script
+        .leaves(keys)?
+        .into_iter()
+        .find(|leaf| leaf.role == role)
+        .ok_or_else(|| Error::ScriptError(format!("Gift has no {} leaf", role)))?;
+    let (_, spend_info) = script.create_taproot_tree(keys)?;
+    let control_block = leaf.control_block(&spend_info)?;
+
+    Ok(InputWeightPrediction::new(0, [64, leaf.script.len(), control_block.size()]))
+}
+
+/// Returns the weight of a transaction spending the gift output through
+/// `path` to a single output with a script of `destination_len` bytes.
+pub fn spend_weight(keys: &GiftKeys, script: &GiftScript, path: SpendPath, destination_len: usize) -> Result<Weight, Error> {
+    Ok(predict_weight([spend_witness(keys, script, path)?], [destination_len]))
+}
+
+/// Returns the fee of redeeming the gift through `path` to a P2TR address.
+pub fn redemption_fee(keys: &GiftKeys, script: &GiftScript, path: SpendPath, fee_rate: FeeRate) -> Result<Amount, Error> {
+    fee_at(fee_rate, spend_weight(keys, script, path, P2TR_SCRIPT_LEN)?)
+}
+
+/// Returns the weight of a transaction spending inputs with the given
+/// witnesses into outputs with the given script lengths.
+pub fn transaction_weight(inputs: &[InputWeightPrediction], output_script_lens: &[usize]) -> Weight {
+    predict_weight(inputs.iter().copied(), output_script_lens.iter().copied())
+}
+
+/// Returns the fee of a transaction of `weight` at `fee_rate`.
+pub fn fee_at(fee_rate: FeeRate, weight: Weight) -> Result<Amount, Error> {
+    fee_rate
+        .fee_wu(weight)
+        .ok_or_else(|| Error::FeeError("Fee overflows".to_string()))
+}
+
+/// Returns the virtual size in vbytes of a transaction of `weight`.
+pub fn vsize(weight: Weight) -> u64 {
+    weight.to_vbytes_ceil()
+}
+
+/// Formats a fee rate in sat/vB, with a decimal only where needed.
+pub fn format_fee_rate(fee_rate: FeeRate) -> String {
+    let sat_per_kwu = fee_rate.to_sat_per_kwu();
+    if sat_per_kwu.is_multiple_of(250) {
+        (sat_per_kwu / 250).to_string()
+    } else {
+        format!("{:.2}", sat_per_kwu as f64 / 250.0)
+    }
+}
@@ -1,22 +1,153 @@
-use bitcoin::XOnlyPublicKey;
-use bitcoin::secp256k1::PublicKey;
+use bitcoin::{Network, XOnlyPublicKey};
+use bitcoin::secp256k1::{PublicKey, SecretKey};
 use musig2::KeyAggContext;
-use miniscript::descriptor::DescriptorPublicKey;
+use musig2::secp::Point;
+use bitcoin::bip32;
+use bitcoin::secp256k1::Secp256k1;
+use miniscript::descriptor::{DefiniteDescriptorKey, DescriptorPublicKey, DescriptorSecretKey, SinglePub, SinglePubKey, Wildcard};
 use std::str::FromStr;
+use crate::network::check_key_network;
 use crate::Error;
 
+/// Where a participant's public key comes from.
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    /// A raw secp256k1 public key.
+    Raw(PublicKey),
+    /// A descriptor key, usually an xpub/tpub with origin and derivation path.
+    Descriptor(DescriptorPublicKey),
+}
+
+impl KeySource {
+    /// Resolves this source to a definite descriptor key at a derivation index.
+    ///
+    /// Wildcard (`/*`) descriptor keys are replaced with the child at `index`;
+    /// raw keys and keys without a wildcard ignore the index.
+    pub fn at_index(&self, index: u32) -> Result<DefiniteDescriptorKey, Error> {
+        let key = match self {
+            KeySource::Raw(key) => DescriptorPublicKey::Single(SinglePub {
+                origin: None,
+                key: SinglePubKey::FullKey(bitcoin::PublicKey::new(*key)),
+            }),
+            KeySource::Descriptor(key) => key.clone(),
+        };
+
+        key.at_derivation_index(index)
+            .map_err(|e| Error::KeyError(format!("Failed to derive key at index {}: {}", index, e)))
+    }
+
+    /// Returns the full public key for this source at a derivation index.
+    ///
+    /// Bare x-only descriptor keys are lifted to their even-parity point, as
+    /// BIP-340 does.
+    pub fn public_key_at(&self, index: u32) -> Result<PublicKey, Error> {
+        self.at_index(index)?
+            .derive_public_key(&Secp256k1::verification_only())
+            .map(|key| key.inner)
+            .map_err(|e| Error::KeyError(format!("Failed to derive public key: {}", e)))
+    }
+
+    /// Whether this key was supplied with a `[fingerprint/path]` origin.
+    pub fn has_origin(&self) -> bool {
+        match self {
+            KeySource::Raw(_) => false,
+            KeySource::Descriptor(DescriptorPublicKey::Single(single)) => single.origin.is_some(),
+            KeySource::Descriptor(DescriptorPublicKey::XPub(xpub)) => xpub.origin.is_some(),
+            KeySource::Descriptor(DescriptorPublicKey::MultiXPub(xpub)) => xpub.origin.is_some(),
+        }
+    }
+
+    /// Returns the BIP-32 origin (master fingerprint and full path) of the
+    /// key at a derivation index, so signing wallets can locate it.
+    ///
+    /// Raw keys carry no origin and return `None`.
+    pub fn key_origin_at(&self, index: u32) -> Result<Option<bip32::KeySource>, Error> {
+        if let KeySource::Raw(_) = self {
+            return Ok(None);
+        }
+
+        let definite = self.at_index(index)?;
+        Ok(definite
+            .full_derivation_path()
+            .map(|path| (definite.master_fingerprint(), path)))
+    }
+}
+
+impl From<PublicKey> for KeySource {
+    fn from(key: PublicKey) -> Self {
+        KeySource::Raw(key)
+    }
+}
+
+impl From<DescriptorPublicKey> for KeySource {
+    fn from(key: DescriptorPublicKey) -> Self {
+        KeySource::Descriptor(key)
+    }
+}
+
 /// GiftKeys holds the public keys for the giver and receiver.
+///
+/// `index` selects the child key for wildcard descriptors, so each gift
+/// between the same pair of xpubs gets its own keys and address. `network`
+/// defaults to regtest; use `with_network` to change it.
 #[derive(Debug, Clone)]
 pub struct GiftKeys {
-    pub giver: DescriptorPublicKey,
-    pub receiver: DescriptorPublicKey,
+    pub giver: KeySource,
+    pub receiver: KeySource,
+    pub index: u32,
+    pub network: Network,
 }
 
 impl GiftKeys {
     /// Create a new GiftKeys instance from raw public keys.
     pub fn new(giver: PublicKey, receiver: PublicKey) -> Self {
-        // Not implemented directly - we're using descriptor-based keys
-        unimplemented!("Direct PublicKey initialization is no longer supported. Use from_descriptor_strings instead.")
+        Self::from_keys(giver, receiver)
+    }
+
+    /// Create a new GiftKeys instance from any pair of key sources.
+    pub fn from_keys(giver: impl Into<KeySource>, receiver: impl Into<KeySource>) -> Self {
+        Self {
+            giver: giver.into(),
+            receiver: receiver.into(),
+            index: 0,
+            network: Network::Regtest,
+        }
+    }
+
+    /// Returns a copy of these keys bound to `network`.
+    ///
+    /// # Returns
+    ///
+    /// GiftKeys for the network or Error if an extended key was encoded for
+    /// a different network (an xpub on a test network or a tpub on mainnet).
+    pub fn with_network(&self, network: Network) -> Result<Self, Error> {
+        for (role, key) in [("Giver", &self.giver), ("Receiver", &self.receiver)] {
+            if let KeySource::Descriptor(key) = key {
+                check_key_network(role, key, network)?;
+            }
+        }
+
+        Ok(Self { network, ..self.clone() })
+    }
+
+    /// Returns a copy of these keys that derives the gift at `index`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Unhardened child index substituted for the `/*` wildcard
+    ///
+    /// # Returns
+    ///
+    /// GiftKeys at the new index or Error if the index is hardened.
+    pub fn at_index(&self, index: u32) -> Result<Self, Error> {
+        if index >= (1 << 31) {
+            return Err(Error::KeyError(format!(
+                "Gift index {} is out of range (must be below 2^31)",
+                index
+            )));
+        }
+
+        Ok(Self { index, ..self.clone() })
     }
 
     /// Creates a new GiftKeys from two descriptor strings.
@@ -36,33 +167,94 @@ impl GiftKeys {
         let receiver = DescriptorPublicKey::from_str(receiver_desc)
             .map_err(|e| Error::KeyError(format!("Invalid receiver descriptor: {}", e)))?;
         
-        Ok(Self { giver, receiver })
+        Ok(Self::from_keys(giver, receiver))
     }
     
     /// Returns the x-only public key for the giver.
-    ///
-    /// # Returns
-    /// 
-    /// XOnlyPublicKey for the giver or Error if the key is not an XPub.
     pub fn giver_x_only_pub(&self) -> Result<XOnlyPublicKey, Error> {
-        match &self.giver {
-            DescriptorPublicKey::XPub(xpub) => Ok(xpub.xkey.to_x_only_pub()),
-            _ => Err(Error::KeyError("Giver key is not an XPub type".to_string()))
-        }
+        Ok(self.giver_pub()?.x_only_public_key().0)
     }
     
     /// Returns the x-only public key for the receiver.
+    pub fn receiver_x_only_pub(&self) -> Result<XOnlyPublicKey, Error> {
+        Ok(self.receiver_pub()?.x_only_public_key().0)
+    }
+
+    /// Returns the full (parity-carrying) public key for the giver.
+    ///
+    /// MuSig2 key aggregation needs the 33-byte key, not the x-only form.
+    pub fn giver_pub(&self) -> Result<PublicKey, Error> {
+        self.giver.public_key_at(self.index)
+    }
+
+    /// Returns the full (parity-carrying) public key for the receiver.
+    pub fn receiver_pub(&self) -> Result<PublicKey, Error> {
+        self.receiver.public_key_at(self.index)
+    }
+
+    /// Returns the giver key as a definite descriptor key at the current
+    /// index, keeping its origin for use in output descriptors.
+    pub fn giver_descriptor_key(&self) -> Result<DescriptorPublicKey, Error> {
+        Ok(self.giver.at_index(self.index)?.into_descriptor_public_key())
+    }
+
+    /// Returns the receiver key as a definite descriptor key at the current
+    /// index, keeping its origin for use in output descriptors.
+    pub fn receiver_descriptor_key(&self) -> Result<DescriptorPublicKey, Error> {
+        Ok(self.receiver.at_index(self.index)?.into_descriptor_public_key())
+    }
+
+    /// Returns the giver key's origin at the current index, if known.
+    pub fn giver_key_origin(&self) -> Result<Option<bip32::KeySource>, Error> {
+        self.giver.key_origin_at(self.index)
+    }
+
+    /// Returns the receiver key's origin at the current index, if known.
+    pub fn receiver_key_origin(&self) -> Result<Option<bip32::KeySource>, Error> {
+        self.receiver.key_origin_at(self.index)
+    }
+
+    /// Builds the BIP-327 key aggregation context for giver and receiver.
+    ///
+    /// Keys are sorted with KeySort first, so the aggregate does not depend on
+    /// which party is listed as giver and which as receiver.
     ///
     /// # Returns
-    /// 
-    /// XOnlyPublicKey for the receiver or Error if the key is not an XPub.
-    pub fn receiver_x_only_pub(&self) -> Result<XOnlyPublicKey, Error> {
-        match &self.receiver {
-            DescriptorPublicKey::XPub(xpub) => Ok(xpub.xkey.to_x_only_pub()),
-            _ => Err(Error::KeyError("Receiver key is not an XPub type".to_string()))
-        }
+    ///
+    /// KeyAggContext over both keys or Error if aggregation fails.
+    pub fn key_agg_context(&self) -> Result<KeyAggContext, Error> {
+        let points = self
+            .musig2_participants()?
+            .iter()
+            .map(to_point)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        KeyAggContext::new(points)
+            .map_err(|e| Error::KeyError(format!("MuSig2 key aggregation failed: {}", e)))
     }
-    
+
+    /// Returns giver and receiver keys in KeySort order, the order they are
+    /// aggregated in and listed in BIP-373 PSBT fields.
+    pub fn musig2_participants(&self) -> Result<Vec<PublicKey>, Error> {
+        let mut keys = vec![self.giver_pub()?, self.receiver_pub()?];
+        keys.sort_by_key(|key| key.serialize());
+        Ok(keys)
+    }
+
+    /// Returns the full (compressed) MuSig2 aggregate before any taproot
+    /// tweak, as BIP-373 uses to identify the aggregate.
+    pub fn aggregate_musig2_pubkey(&self) -> Result<PublicKey, Error> {
+        from_point(self.key_agg_context()?.aggregated_pubkey_untweaked())
+    }
+
+    /// Returns the x-only MuSig2 aggregate of the giver and receiver keys.
+    ///
+    /// This is the untweaked taproot internal key. The result is
+    /// deterministic: KeySort makes it independent of argument order.
+    pub fn aggregate_musig2_key(&self) -> Result<XOnlyPublicKey, Error> {
+        point_to_x_only(self.key_agg_context()?.aggregated_pubkey())
+    }
+
     /// For backwards compatibility: create from descriptors
     pub fn from_descriptors(giver_desc: &str, receiver_desc: &str) -> Result<Self, Error> {
         Self::from_descriptor_strings(giver_desc, receiver_desc)
@@ -87,15 +279,78 @@ impl GiftKeys {
     }
 }
 
+/// Parses a participant's signing key and derives it at a gift index.
+///
+/// Accepts a WIF private key, or an extended private key in the same form as
+/// the public descriptors, e.g. `[73c5da0a/86'/1'/0']tprv.../0/*`, whose
+/// wildcard is replaced with `index`.
+///
+/// # Returns
+///
+/// The secret key or a KeyError if it cannot be parsed or derived.
+pub fn parse_secret_key(spec: &str, index: u32) -> Result<SecretKey, Error> {
+    let spec = spec.trim();
+    if let Ok(key) = bitcoin::PrivateKey::from_wif(spec) {
+        return Ok(key.inner);
+    }
+
+    let key = DescriptorSecretKey::from_str(spec)
+        .map_err(|e| Error::KeyError(format!("Invalid secret key (expected WIF or an xprv/tprv descriptor): {}", e)))?;
+
+    match key {
+        DescriptorSecretKey::Single(single) => Ok(single.key.inner),
+        DescriptorSecretKey::XPrv(xprv) => {
+            let child = match xprv.wildcard {
+                Wildcard::None => None,
+                Wildcard::Unhardened => Some(bip32::ChildNumber::from_normal_idx(index)),
+                Wildcard::Hardened => Some(bip32::ChildNumber::from_hardened_idx(index)),
+            };
+            let path = match child {
+                Some(child) => xprv.derivation_path.child(
+                    child.map_err(|e| Error::KeyError(format!("Invalid derivation index {}: {}", index, e)))?,
+                ),
+                None => xprv.derivation_path.clone(),
+            };
+
+            xprv.xkey
+                .derive_priv(&Secp256k1::new(), &path)
+                .map(|derived| derived.private_key)
+                .map_err(|e| Error::KeyError(format!("Failed to derive secret key: {}", e)))
+        }
+        DescriptorSecretKey::MultiXPrv(_) => Err(Error::KeyError(
+            "Multipath secret keys are not supported; give a single derivation path".to_string(),
+        )),
+    }
+}
+
+/// Converts a bitcoin secp256k1 key into a musig2 point.
+///
+/// The musig2 crate links a newer secp256k1 than rust-bitcoin, so keys cross
+/// over through their compressed serialization.
+pub(crate) fn to_point(key: &PublicKey) -> Result<Point, Error> {
+    Point::from_slice(&key.serialize())
+        .map_err(|e| Error::KeyError(format!("Invalid public key for MuSig2: {}", e)))
+}
+
+/// Converts a musig2 point back into a bitcoin public key.
+pub(crate) fn from_point(point: Point) -> Result<PublicKey, Error> {
+    PublicKey::from_slice(&point.serialize())
+        .map_err(|e| Error::KeyError(format!("Invalid aggregated key: {}", e)))
+}
+
+/// Converts a musig2 point back into a bitcoin x-only key.
+pub(crate) fn point_to_x_only(point: Point) -> Result<XOnlyPublicKey, Error> {
+    XOnlyPublicKey::from_slice(&point.serialize_xonly())
+        .map_err(|e| Error::KeyError(format!("Invalid aggregated key: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_harness::fixtures::{GIVER_DESC, GIVER_KEY, RECEIVER_DESC, RECEIVER_KEY};
 
     #[test]
     fn parse_descriptors() -> Result<(), ()> {
-        const GIVER_DESC: &str = "[73c5da0a/86'/1'/0']tpubDCvNAJkUmvjcXrTzyui9M7ehe1EXGkUmF12jTuJ9JxiAmg3tuVgocse3x5zx87WeydqwJWftYkyRQ4d7wF2F5Gs8AdzhJHVXAnMYG9QzmQ6/0/*";
-        const RECEIVER_DESC: &str = "[143df5a6/86'/1'/1']tpubDCvNAJkUmvjcbaAF57Yp5v53rMMxVo34KYLMjmj6xcdo9r2rf3CkZoGHswZTtA2H6pXJsavhRpeqkwnDs6bSLsHbdycfLJN3N5J4nQP1Kuc/0/*";
-        
         let gift_keys = GiftKeys::from_descriptor_strings(GIVER_DESC, RECEIVER_DESC)
             .map_err(|_| ())?;
         
@@ -109,4 +364,15 @@ mod tests {
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn aggregation_is_order_independent() {
+        let pk1 = PublicKey::from_str(GIVER_KEY).unwrap();
+        let pk2 = PublicKey::from_str(RECEIVER_KEY).unwrap();
+
+        let forward = GiftKeys::new(pk1, pk2).aggregate_musig2_key().unwrap();
+        let reverse = GiftKeys::new(pk2, pk1).aggregate_musig2_key().unwrap();
+
+        assert_eq!(forward, reverse);
+    }
+}
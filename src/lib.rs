@@ -1,10 +1,37 @@
+pub mod backend;
+pub mod backup;
+pub mod batch;
+pub mod coin_selection;
+pub mod coop;
 pub mod error;
+pub mod fee;
 pub mod keys;
+pub mod network;
+pub mod recover;
+pub mod registry;
+pub mod safety;
 pub mod script;
+pub mod status;
+pub mod storage;
 pub mod test_harness;
+pub mod timelock;
+pub mod transaction;
+pub mod vesting;
 
 // Re-export key types for easy access
-pub use keys::GiftKeys;
-pub use script::GiftScript;
+pub use backend::{ChainBackend, ChainUtxo};
+pub use backup::GiftBackup;
+pub use batch::{BatchGift, BatchPlan};
+pub use coin_selection::{CoinSelection, SelectionOptions};
+pub use coop::{CoopSession, NonceState};
+pub use fee::FeeMode;
+pub use keys::{GiftKeys, KeySource};
+pub use recover::RecoverySearch;
+pub use registry::{GiftRecord, GiftRegistry};
+pub use script::{GiftLeaf, GiftScript, LeafRole, SpendPath};
+pub use status::{GiftStatus, WatchedGift};
 pub use test_harness::TestHarness;
+pub use timelock::Timelock;
+pub use transaction::{GiftTransaction, GiftUtxo};
+pub use vesting::VestingSchedule;
 pub use error::Error;
\ No newline at end of file
@@ -1,6 +1,24 @@
-use clap::{Parser, Subcommand};
-use bitcoin::base58;
-use mallowbtc::{GiftKeys, GiftScript, Error};
+use clap::{Args, Parser, Subcommand};
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::{Address, Amount, FeeRate, Network, NetworkKind, OutPoint, Psbt, Transaction};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use mallowbtc::backend::{self, BitcoindBackend, ElectrumBackend, EsploraBackend, DEFAULT_FEE_TARGET, DEFAULT_SCAN_INDICES};
+use mallowbtc::batch;
+use mallowbtc::coin_selection::{select_batch_utxos, select_utxos, CoinSelection, SelectionOptions};
+use mallowbtc::fee::{self, FeeMode};
+use mallowbtc::keys::parse_secret_key;
+use mallowbtc::network::{bech32_hrp, network_for_address, network_name, parse_network};
+use mallowbtc::safety::{check_mainnet_gift, confirmation_matches, CONFIRMATION_PHRASE};
+use mallowbtc::recover::{self, RecoverySearch};
+use mallowbtc::registry::Redemption;
+use mallowbtc::timelock::{format_duration, format_timestamp};
+use mallowbtc::transaction::parse_fee_rate;
+use mallowbtc::vesting::{self, VestingSchedule};
+use mallowbtc::{BatchPlan, ChainBackend, CoopSession, NonceState, GiftKeys, GiftBackup, GiftLeaf, GiftRecord, GiftRegistry, GiftScript, GiftStatus, GiftTransaction, GiftUtxo, Error, LeafRole, SpendPath, Timelock, WatchedGift};
 
 /// Mallow Bitcoin - Timelocked Bitcoin Gift Service
 #[derive(Parser, Debug)]
@@ -21,146 +39,1917 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Create a new timelocked bitcoin gift
-    Create {
-        /// The giver's extended public key with fingerprint and path
-        #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
-        giver_tpub: Option<String>,
+    Create(CreateArgs),
+    /// Build an unsigned PSBT that funds a gift from the giver's UTXOs
+    Fund(FundArgs),
+    /// Build an unsigned PSBT that spends a gift through the receiver's timelock leaf
+    Redeem(RedeemArgs),
+    /// Build one unsigned PSBT that funds every gift listed in a manifest
+    Batch(BatchArgs),
+    /// Build one unsigned PSBT that splits a gift into tranches unlocking one after another
+    Vest(VestArgs),
+    /// Finalize a signed PSBT into a broadcastable transaction
+    Finalize(FinalizeArgs),
+    /// Show a gift's funding, the blocks left before each leaf unlocks, and how it was spent
+    Status(StatusArgs),
+    /// List the gifts recorded in the gift registry
+    List(RegistryArgs),
+    /// Show everything recorded about one gift, with its spending information
+    Show(GiftIdArgs),
+    /// Remove a gift from the registry (the gift itself is unaffected)
+    Forget(GiftIdArgs),
+    /// Write a recorded gift to a checksummed backup file
+    Export(ExportArgs),
+    /// Check a gift backup file rebuilds its address, then record the gift
+    Import(ImportArgs),
+    /// Find a gift's index, timelocks and layout from its address and the two descriptors
+    Recover(RecoverArgs),
+    /// Spend a gift cooperatively with a MuSig2 signature from giver and receiver
+    #[command(subcommand)]
+    Coop(CoopCommands),
+}
+
+/// The steps of a cooperative spend. Each step reads and updates a session
+/// file that the two participants pass back and forth.
+#[derive(Subcommand, Debug)]
+enum CoopCommands {
+    /// Create a session file for a cooperative spend of a gift output
+    Start(CoopStartArgs),
+    /// Add your public nonce to the session (keeps the secret nonce in a local file)
+    Nonce(CoopSignerArgs),
+    /// Add your partial signature to the session once both nonces are in
+    Sign(CoopSignerArgs),
+    /// Combine both partial signatures into the final transaction
+    Combine(CoopCombineArgs),
+}
+
+#[derive(Args, Debug)]
+struct CoopStartArgs {
+    #[command(flatten)]
+    gift: GiftArgs,
+
+    /// The funded gift output
+    #[arg(long, help = "Gift output as txid:vout:amount_sat")]
+    gift_utxo: String,
+
+    /// Address the funds are sent to
+    #[arg(long, help = "Destination address agreed by giver and receiver")]
+    destination: String,
+
+    /// Fee rate for the spending transaction
+    #[arg(long, value_parser = parse_fee_rate, help = "Fee rate in sat/vB, e.g. 2 or 1.5")]
+    fee_rate: FeeRate,
+
+    /// Session file to create
+    #[arg(long, default_value = "coop-session.json", help = "Path of the session file to write")]
+    session: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct CoopSessionArgs {
+    /// Session file shared between giver and receiver
+    #[arg(long, default_value = "coop-session.json", help = "Path of the session file")]
+    session: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct CoopCombineArgs {
+    #[command(flatten)]
+    session: CoopSessionArgs,
+
+    /// Publish the combined transaction through the chain backend
+    #[arg(long, help = "Broadcast the transaction through the chain backend")]
+    broadcast: bool,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+}
+
+#[derive(Args, Debug)]
+struct CoopSignerArgs {
+    #[command(flatten)]
+    session: CoopSessionArgs,
+
+    /// Your secret key; read from standard input when omitted
+    #[arg(long, help = "WIF key or [fingerprint/path]tprv.../0/* descriptor; omit to read it from stdin")]
+    secret_key: Option<String>,
+
+    /// Derivation index of the gift
+    #[arg(long, default_value_t = 0, help = "Child index substituted for /* in the secret key descriptor")]
+    index: u32,
+
+    /// Local file holding your secret nonce; never share it
+    #[arg(long, help = "Defaults to the session path with .<key>.nonce.json appended")]
+    nonce_file: Option<PathBuf>,
+}
+
+/// The keys and spending conditions that define a gift.
+#[derive(Args, Debug)]
+struct GiftArgs {
+    /// The giver's extended public key with fingerprint and path
+    #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
+    giver_tpub: Option<String>,
+
+    /// The receiver's extended public key with fingerprint and path
+    #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
+    receiver_tpub: Option<String>,
+
+    #[command(flatten)]
+    timelock: TimelockArgs,
+
+    /// Optional giver clawback leaf, spendable by the giver alone after a longer timelock
+    #[arg(long, help = "Clawback timelock in the same unit as the gift: 105120, 380d, 2035-01-01 or height:1000000")]
+    clawback: Option<Timelock>,
+
+    /// Depth of each tapscript leaf, receiver leaf first
+    #[arg(long, value_delimiter = ',', help = "Comma-separated leaf depths, e.g. 1,1 (defaults to a balanced tree)")]
+    leaf_depths: Option<Vec<u8>>,
+
+    /// Derivation index for this gift; use a fresh index for every gift
+    #[arg(long, default_value_t = 0, help = "Child index substituted for /* in both descriptors")]
+    index: u32,
+
+    /// Network the gift lives on
+    #[arg(long, default_value = "regtest", value_parser = parse_network, help = "mainnet, testnet, signet or regtest")]
+    network: Network,
+}
+
+impl GiftArgs {
+    /// Whether both keys and a timelock were given.
+    fn is_complete(&self) -> bool {
+        self.giver_tpub.is_some() && self.receiver_tpub.is_some() && self.timelock.resolve().is_some()
+    }
+
+    /// Builds the gift's keys, derived at its index, and its script.
+    fn build(&self) -> Result<(GiftKeys, GiftScript), Error> {
+        let (Some(giver_tpub), Some(receiver_tpub), Some(timelock)) =
+            (&self.giver_tpub, &self.receiver_tpub, self.timelock.resolve())
+        else {
+            return Err(Error::KeyError(
+                "A gift needs --giver-tpub, --receiver-tpub and a timelock".to_string(),
+            ));
+        };
+
+        let gift_keys = GiftKeys::from_descriptor_strings(giver_tpub, receiver_tpub)?
+            .at_index(self.index)?
+            .with_network(self.network)?;
+
+        let mut script = GiftScript::from_timelock(timelock?).with_network(self.network);
+        if let Some(clawback) = self.clawback {
+            script = script.with_clawback(clawback);
+        }
+        if let Some(depths) = &self.leaf_depths {
+            script = script.with_leaf_depths(depths.clone());
+        }
+
+        Ok((gift_keys, script))
+    }
+}
+
+#[derive(Args, Debug)]
+struct StatusArgs {
+    #[command(flatten)]
+    gift: GiftArgs,
+
+    /// The gift's watch-only descriptor, instead of its keys and timelock
+    #[arg(long, conflicts_with_all = ["giver_tpub", "receiver_tpub"], help = "tr() descriptor printed by create or batch; --index fills in any /*")]
+    descriptor: Option<String>,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct CreateArgs {
+    #[command(flatten)]
+    gift: GiftArgs,
+
+    /// Intended gift amount, to check it is worth claiming
+    #[arg(long, help = "Intended gift amount in satoshis; prints the break-even fee rate and viability warnings")]
+    amount: Option<u64>,
+
+    /// Skip the typed confirmation for mainnet gifts (the automatic checks still run)
+    #[arg(long)]
+    i_understand_mainnet: bool,
+
+    /// Name to list the gift under in the registry
+    #[arg(long, help = "Label for the gift registry, e.g. \"Alice 18th birthday\"")]
+    label: Option<String>,
+
+    /// Don't record the gift in the registry
+    #[arg(long, conflicts_with = "label")]
+    no_save: bool,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+/// Where the gift registry lives.
+#[derive(Args, Debug)]
+struct RegistryArgs {
+    /// Gift registry file
+    #[arg(long, help = "Gift registry file (default: mallowbtc/gifts.json in the XDG data directory)")]
+    registry: Option<PathBuf>,
+}
+
+impl RegistryArgs {
+    /// Reads the registry and returns it with its path.
+    fn load(&self) -> Result<(PathBuf, GiftRegistry), Error> {
+        let path = match &self.registry {
+            Some(path) => path.clone(),
+            None => GiftRegistry::default_path()?,
+        };
+        let registry = GiftRegistry::load(&path)?;
+        Ok((path, registry))
+    }
+
+    /// Applies `change` to the recorded gift paying `address`, if there is one.
+    ///
+    /// The registry is bookkeeping, so failing to update it is reported
+    /// without failing the command that produced the update.
+    fn update(&self, address: &str, change: impl FnOnce(&mut GiftRecord)) {
+        let result = self.load().and_then(|(path, mut registry)| {
+            let Some(record) = registry.find_by_address_mut(address) else {
+                return Ok(());
+            };
+            change(record);
+            let id = record.id;
+            registry.save(&path)?;
+            println!("\nUpdated gift #{} in the registry", id);
+            Ok(())
+        });
+        if let Err(e) = result {
+            println!("Warning: could not update the gift registry: {}", e);
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct ExportArgs {
+    /// Registry id of the gift, as shown by `list`
+    id: u32,
+
+    /// Where to write the backup
+    #[arg(long, help = "Backup file to write (default: gift-<id>.json)")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// The backup file written by `export`
+    file: PathBuf,
+
+    /// Only verify the backup, without recording the gift
+    #[arg(long)]
+    no_save: bool,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct RecoverArgs {
+    /// The gift's address
+    #[arg(long, help = "Gift address, e.g. bcrt1p...")]
+    address: String,
+
+    /// The giver's extended public key with fingerprint and path
+    #[arg(long, help = "Giver descriptor: [fingerprint/path]tpub.../0/*")]
+    giver: String,
+
+    /// The receiver's extended public key with fingerprint and path
+    #[arg(long, help = "Receiver descriptor: [fingerprint/path]tpub.../0/*")]
+    receiver: String,
+
+    /// Number of derivation indices to search
+    #[arg(long, default_value_t = DEFAULT_SCAN_INDICES, help = "Search gift indices 0 to N - 1")]
+    scan_indices: u32,
+
+    /// Receiver timelocks to try
+    #[arg(long, help = "Comma-separated timelocks or block ranges, e.g. 52560,180d,1000-2000,height:900000 (default: whole days and multiples of 1000 blocks, and whole days as durations)")]
+    timelocks: Option<String>,
+
+    /// Clawback timelocks to try
+    #[arg(long, help = "Comma-separated clawback timelocks or block ranges, or 'none' to skip gifts with a clawback leaf (default: every 30 days and quarter year)")]
+    clawbacks: Option<String>,
+
+    /// Network the gift lives on
+    #[arg(long, value_parser = parse_network, help = "mainnet, testnet, signet or regtest (default: taken from the address; tb1 addresses are taken as testnet)")]
+    network: Option<Network>,
+
+    /// Name to list the gift under in the registry
+    #[arg(long, help = "Label for the gift registry")]
+    label: Option<String>,
+
+    /// Don't record the recovered gift in the registry
+    #[arg(long, conflicts_with = "label")]
+    no_save: bool,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct GiftIdArgs {
+    /// Registry id of the gift, as shown by `list`
+    id: u32,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+/// Where chain data comes from when a command looks it up itself.
+#[derive(Args, Debug)]
+struct BackendArgs {
+    /// Bitcoin Core JSON-RPC endpoint
+    #[arg(long, help = "Bitcoin Core RPC URL, e.g. http://127.0.0.1:18443")]
+    rpc_url: Option<String>,
+
+    /// RPC user name
+    #[arg(long, requires = "rpc_url", help = "RPC user (rpcuser in bitcoin.conf)")]
+    rpc_user: Option<String>,
+
+    /// RPC password
+    #[arg(long, requires = "rpc_user", help = "RPC password (rpcpassword in bitcoin.conf)")]
+    rpc_password: Option<String>,
+
+    /// Cookie file for RPC authentication
+    #[arg(long, requires = "rpc_url", conflicts_with = "rpc_user", help = "Path to the node's .cookie file")]
+    rpc_cookie: Option<PathBuf>,
+
+    /// Esplora REST API
+    #[arg(long, conflicts_with_all = ["rpc_url", "electrum_url"], help = "Esplora API URL, e.g. https://blockstream.info/testnet/api")]
+    esplora_url: Option<String>,
+
+    /// Electrum server
+    #[arg(long, conflicts_with = "rpc_url", help = "Electrum server as tcp://host:port, e.g. tcp://127.0.0.1:50001")]
+    electrum_url: Option<String>,
+}
+
+impl BackendArgs {
+    /// Connects to the backend given on the command line, if any.
+    fn connect(&self) -> Result<Option<Box<dyn ChainBackend>>, Error> {
+        if let Some(url) = &self.esplora_url {
+            return Ok(Some(Box::new(EsploraBackend::new(url))));
+        }
+        if let Some(url) = &self.electrum_url {
+            return Ok(Some(Box::new(ElectrumBackend::new(url)?)));
+        }
+        let Some(url) = &self.rpc_url else {
+            return Ok(None);
+        };
+
+        let mut backend = BitcoindBackend::new(url);
+        if let Some(cookie) = &self.rpc_cookie {
+            backend = backend.with_cookie_file(cookie)?;
+        } else if let Some(user) = &self.rpc_user {
+            backend = backend.with_user_pass(user, self.rpc_password.as_deref().unwrap_or_default());
+        }
+
+        Ok(Some(Box::new(backend)))
+    }
+}
+
+/// Returns the fee rate given, or else the backend's estimate.
+fn resolve_fee_rate(given: Option<FeeRate>, backend: Option<&dyn ChainBackend>) -> Result<FeeRate, Error> {
+    match (given, backend) {
+        (Some(fee_rate), _) => Ok(fee_rate),
+        (None, Some(backend)) => {
+            let fee_rate = backend.estimate_fee_rate(DEFAULT_FEE_TARGET)?;
+            println!(
+                "Estimated fee rate: {} sat/vB to confirm within {} blocks",
+                fee::format_fee_rate(fee_rate),
+                DEFAULT_FEE_TARGET
+            );
+            Ok(fee_rate)
+        }
+        (None, None) => Err(Error::TransactionError(
+            "Give --fee-rate, or a chain backend to estimate it".to_string(),
+        )),
+    }
+}
+
+/// The giver UTXOs a funding transaction may spend and how they are chosen.
+#[derive(Args, Debug)]
+struct UtxoArgs {
+    /// Giver UTXO available to spend; repeat for each one
+    #[arg(long = "utxo", help = "Giver UTXO as txid:vout:amount_sat:index[:height], where index is its child of the giver descriptor and height the block it confirmed in")]
+    utxos: Vec<String>,
+
+    /// Number of giver descriptor indices searched when a backend looks up the UTXOs
+    #[arg(long, default_value_t = DEFAULT_SCAN_INDICES, help = "Without --utxo, search giver indices 0 to N-1 through the chain backend")]
+    scan_indices: u32,
+
+    /// How the UTXOs to spend are chosen
+    #[arg(long, default_value_t = CoinSelection::All, help = "Coin selection: all (spend every --utxo), bnb (changeless branch and bound), largest-first or oldest-first")]
+    coin_selection: CoinSelection,
+
+    /// UTXO that must be spent; repeat for each one
+    #[arg(long = "include-utxo", help = "Outpoint (txid:vout) of a --utxo that must be spent")]
+    include_utxos: Vec<String>,
+
+    /// UTXO that must not be spent; repeat for each one
+    #[arg(long = "exclude-utxo", help = "Outpoint (txid:vout) of a --utxo that must not be spent")]
+    exclude_utxos: Vec<String>,
+
+    /// Only spend UTXOs that share one output script
+    #[arg(long, help = "Privacy mode: never spend UTXOs of different giver addresses together")]
+    privacy: bool,
+}
+
+impl UtxoArgs {
+    /// Parses every --utxo into a giver UTXO, with the output script of its
+    /// child of the giver descriptor that `gift_tx` uses. Without --utxo,
+    /// looks the giver's UTXOs up through the backend.
+    fn load(&self, gift_tx: &GiftTransaction, backend: Option<&dyn ChainBackend>) -> Result<Vec<GiftUtxo>, Error> {
+        if self.utxos.is_empty() {
+            let Some(backend) = backend else {
+                return Err(Error::TransactionError(
+                    "Give --utxo, or a chain backend to look the giver's UTXOs up".to_string(),
+                ));
+            };
+            return backend::giver_utxos(backend, gift_tx, 0..self.scan_indices);
+        }
+
+        let mut utxos = Vec::with_capacity(self.utxos.len());
+        for spec in &self.utxos {
+            let (outpoint, amount, index, height) = parse_utxo_spec(spec, true)?;
+            let index = index.expect("index is required");
+            let utxo = GiftUtxo::new(outpoint, amount, gift_tx.giver_script_pubkey(index)?, index);
+            utxos.push(match height {
+                Some(height) => utxo.with_confirmation_height(height),
+                None => utxo,
+            });
+        }
+
+        Ok(utxos)
+    }
+
+    /// The coin selection settings given on the command line.
+    fn options(&self) -> Result<SelectionOptions, Error> {
+        Ok(SelectionOptions {
+            strategy: self.coin_selection,
+            include: parse_outpoints(&self.include_utxos)?,
+            exclude: parse_outpoints(&self.exclude_utxos)?,
+            privacy: self.privacy,
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+struct FundArgs {
+    #[command(flatten)]
+    gift: GiftArgs,
+
+    #[command(flatten)]
+    utxos: UtxoArgs,
+
+    #[command(flatten)]
+    backend: BackendArgs,
 
-        /// The receiver's extended public key with fingerprint and path
-        #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
-        receiver_tpub: Option<String>,
+    /// Amount given, in satoshis
+    #[arg(long, help = "Gift amount in satoshis")]
+    amount: u64,
 
-        /// Timelock period in blocks (approximately 52560 blocks = 1 year)
-        #[arg(long, help = "Number of blocks for timelock (52560 ≈ 1 year)")]
-        timelock: Option<u32>,
-    },
+    /// Fee rate for the funding transaction
+    #[arg(long, value_parser = parse_fee_rate, help = "Fee rate in sat/vB, e.g. 2 or 1.5 (default: the chain backend's estimate)")]
+    fee_rate: Option<FeeRate>,
+
+    /// Who pays the fee of redeeming the gift
+    #[arg(long, default_value_t = FeeMode::AmountSent, help = "'sent': the gift output holds --amount and the receiver pays the redemption fee; 'received': the giver adds the redemption fee so the receiver nets --amount")]
+    fee_mode: FeeMode,
+
+    /// Fee rate the redemption is expected to pay, for --fee-mode=received
+    #[arg(long, value_parser = parse_fee_rate, help = "Expected redemption fee rate in sat/vB (default: --fee-rate)")]
+    redeem_fee_rate: Option<FeeRate>,
+
+    /// Giver descriptor index that receives the change
+    #[arg(long, help = "Unused child index of the giver descriptor for the change output")]
+    change_index: u32,
+
+    /// Also write the PSBT to this file (binary BIP-174)
+    #[arg(long, help = "Path to write the binary PSBT to, e.g. gift.psbt")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct BatchArgs {
+    /// The giver's extended public key with fingerprint and path, shared by every gift
+    #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
+    giver_tpub: String,
+
+    /// Manifest of the gifts, one per row
+    #[arg(long, help = "CSV with label,receiver,amount,timelock[,index] columns, or a .json array of the same fields")]
+    manifest: PathBuf,
+
+    /// Derivation index of the first gift; later rows take the following indices
+    #[arg(long, default_value_t = 0, help = "Child index of the first gift, for rows without an index column")]
+    index: u32,
+
+    /// Network the gifts live on
+    #[arg(long, default_value = "regtest", value_parser = parse_network, help = "mainnet, testnet, signet or regtest")]
+    network: Network,
+
+    #[command(flatten)]
+    utxos: UtxoArgs,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Fee rate for the funding transaction
+    #[arg(long, value_parser = parse_fee_rate, help = "Fee rate in sat/vB, e.g. 2 or 1.5 (default: the chain backend's estimate)")]
+    fee_rate: Option<FeeRate>,
+
+    /// Giver descriptor index that receives the change
+    #[arg(long, help = "Unused child index of the giver descriptor for the change output")]
+    change_index: u32,
+
+    /// Also write the PSBT to this file (binary BIP-174)
+    #[arg(long, help = "Path to write the binary PSBT to, e.g. gifts.psbt")]
+    output: Option<PathBuf>,
+
+    /// Where to write the report of every gift
+    #[arg(long, help = "Path of the JSON report (default: the manifest path with .report.json)")]
+    report: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct VestArgs {
+    /// The giver's extended public key with fingerprint and path
+    #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
+    giver_tpub: String,
+
+    /// The receiver's extended public key with fingerprint and path
+    #[arg(long, help = "Extended public key with fingerprint and path: [fingerprint/path]tpub...")]
+    receiver_tpub: String,
+
+    /// Amount vested over the whole schedule, in satoshis
+    #[arg(long, help = "Total amount in satoshis, split across the tranches")]
+    total: u64,
+
+    /// Explicit tranches, each a timelock with an optional percentage share
+    #[arg(long, conflicts_with_all = ["tranches", "every", "yearly_from"], help = "Comma-separated tranches as timelock[:percent], e.g. 2027-01-01:40,2028-01-01:60")]
+    schedule: Option<String>,
+
+    /// Number of equal tranches, for --every or --yearly-from
+    #[arg(long, help = "Number of equal tranches")]
+    tranches: Option<u32>,
+
+    /// Relative timelock between tranches
+    #[arg(long, requires = "tranches", conflicts_with = "yearly_from", help = "Relative step between unlocks: blocks (13140) or a duration (90d)")]
+    every: Option<Timelock>,
+
+    /// Unlock date of the first tranche, with one more tranche each year after
+    #[arg(long, requires = "tranches", help = "First unlock date as YYYY-MM-DD; later tranches unlock on the same day of the following years")]
+    yearly_from: Option<String>,
+
+    /// Derivation index of the first tranche; later tranches take the following indices
+    #[arg(long, default_value_t = 0, help = "Child index of the first tranche")]
+    index: u32,
+
+    /// Network the gifts live on
+    #[arg(long, default_value = "regtest", value_parser = parse_network, help = "mainnet, testnet, signet or regtest")]
+    network: Network,
+
+    #[command(flatten)]
+    utxos: UtxoArgs,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Fee rate for the funding transaction
+    #[arg(long, value_parser = parse_fee_rate, help = "Fee rate in sat/vB, e.g. 2 or 1.5 (default: the chain backend's estimate)")]
+    fee_rate: Option<FeeRate>,
+
+    /// Giver descriptor index that receives the change
+    #[arg(long, help = "Unused child index of the giver descriptor for the change output")]
+    change_index: u32,
+
+    /// Also write the PSBT to this file (binary BIP-174)
+    #[arg(long, help = "Path to write the binary PSBT to, e.g. vesting.psbt")]
+    output: Option<PathBuf>,
+
+    /// Where to write the report of every tranche
+    #[arg(long, help = "Path of the JSON report listing each tranche's address, descriptor and control blocks")]
+    report: Option<PathBuf>,
+}
+
+impl VestArgs {
+    /// Builds the vesting schedule from whichever schedule flags were given.
+    fn schedule(&self) -> Result<VestingSchedule, Error> {
+        let total = Amount::from_sat(self.total);
+        match (&self.schedule, self.tranches, self.every, &self.yearly_from) {
+            (Some(spec), _, _, _) => VestingSchedule::parse(total, spec),
+            (None, Some(count), Some(step), _) => VestingSchedule::every(total, count, step),
+            (None, Some(count), None, Some(first)) => VestingSchedule::yearly(total, count, first),
+            _ => Err(Error::VestingError(
+                "Give --schedule, or --tranches with --every or --yearly-from".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct RedeemArgs {
+    #[command(flatten)]
+    gift: GiftArgs,
+
+    /// The funded gift output
+    #[arg(long, help = "Gift output as txid:vout:amount_sat (default: looked up through the chain backend)")]
+    gift_utxo: Option<String>,
+
+    /// Address the redeemed funds are sent to
+    #[arg(long, help = "Destination address, usually from the receiver's wallet")]
+    destination: String,
+
+    /// Fee rate for the redemption transaction
+    #[arg(long, value_parser = parse_fee_rate, help = "Fee rate in sat/vB, e.g. 2 or 1.5 (default: the chain backend's estimate)")]
+    fee_rate: Option<FeeRate>,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Also write the PSBT to this file (binary BIP-174)
+    #[arg(long, help = "Path to write the binary PSBT to, e.g. redeem.psbt")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    registry: RegistryArgs,
+}
+
+#[derive(Args, Debug)]
+struct FinalizeArgs {
+    /// The signed PSBT, as base64 or a path to a PSBT file
+    #[arg(long, help = "Signed PSBT as base64, or a path to a binary or base64 PSBT file")]
+    psbt: String,
+
+    /// Publish the finalized transaction through the chain backend
+    #[arg(long, help = "Broadcast the transaction through the chain backend")]
+    broadcast: bool,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+}
+
+/// Mutually exclusive ways of specifying the receiver's timelock.
+#[derive(Args, Debug)]
+struct TimelockArgs {
+    /// Timelock period in blocks (approximately 52560 blocks = 1 year)
+    #[arg(long, conflicts_with_all = ["lock_for", "unlock_date", "unlock_height"], help = "Number of blocks for timelock (52560 ≈ 1 year, at most 65535 ≈ 15 months)")]
+    timelock: Option<u32>,
+
+    /// Relative timelock as a duration, enforced in 512-second units
+    #[arg(long, conflicts_with_all = ["unlock_date", "unlock_height"], help = "Duration such as 180d, 12h or 2w (at most about 388 days)")]
+    lock_for: Option<String>,
+
+    /// Absolute unlock date (midnight UTC), enforced with OP_CHECKLOCKTIMEVERIFY
+    #[arg(long, conflicts_with = "unlock_height", help = "Unlock date as YYYY-MM-DD, e.g. 2030-01-01")]
+    unlock_date: Option<String>,
+
+    /// Absolute unlock block height, enforced with OP_CHECKLOCKTIMEVERIFY
+    #[arg(long, help = "Block height at which the receiver can spend")]
+    unlock_height: Option<u32>,
+}
+
+impl TimelockArgs {
+    /// Picks the timelock from whichever flag was given, if any.
+    fn resolve(&self) -> Option<Result<Timelock, Error>> {
+        if let Some(blocks) = self.timelock {
+            Some(Timelock::blocks(blocks))
+        } else if let Some(duration) = &self.lock_for {
+            Some(Timelock::lock_for(duration))
+        } else if let Some(date) = &self.unlock_date {
+            Some(Timelock::unlock_date(date))
+        } else {
+            self.unlock_height.map(Timelock::unlock_height)
+        }
+    }
 }
 
 fn show_create_requirements() {
     println!("\nTo create a timelocked bitcoin gift, you'll need:");
-    println!("1. Giver's Extended Public Key (tpub)");
+    println!("1. Giver's Extended Public Key (tpub, or xpub with --network mainnet)");
     println!("   - This is a watch-only key from your wallet");
     println!("   - Format: [fingerprint/derivation_path]tpub...");
     println!("   - Example: [73c5da0a/86'/1'/0']tpubD...");
-    println!("");
-    println!("2. Receiver's Extended Public Key (tpub)");
+    println!();
+    println!("2. Receiver's Extended Public Key (tpub, or xpub with --network mainnet)");
     println!("   - This is also a watch-only key from their wallet");
     println!("   - Format: [fingerprint/derivation_path]tpub...");
     println!("   - Example: [143df5a6/86'/1'/1']tpubD...");
-    println!("");
+    println!();
     println!("3. Timelock Period");
     println!("   - How long until the receiver can spend unilaterally");
     println!("   - Specified in blocks (52560 blocks ≈ 1 year)");
-    println!("");
+    println!("   - At most 65535 blocks (about 15 months), the BIP-68 limit");
+    println!("   - Or a duration with --lock-for=180d (512-second units, at most about 388 days)");
+    println!("   - Or a fixed date with --unlock-date=YYYY-MM-DD, or a block with --unlock-height");
+    println!();
+    println!("4. Gift Index (optional, defaults to 0)");
+    println!("   - Child index used for the /* in both descriptors");
+    println!("   - Use a new index for every gift so each gets its own address");
+    println!();
+    println!("5. Giver Clawback (optional)");
+    println!("   - --clawback lets the giver recover an unclaimed gift after a longer timelock");
+    println!("   - Same unit as the gift timelock, e.g. --timelock=52560 --clawback=65000");
+    println!();
     println!("Need help getting these? Visit: https://docs.mallowbtc.org/setup-guide");
     println!("(Tip: Most wallet software can export extended public keys with fingerprints. Look for 'Export xpub' or similar options)");
-    println!("");
+    println!();
     println!("Once you have the requirements, run:");
     println!("  mallowbtc create --giver-tpub=\"[FINGERPRINT/PATH]TPUB\" --receiver-tpub=\"[FINGERPRINT/PATH]TPUB\" --timelock=52560");
 }
 
-fn create_gift(giver_pk: &str, receiver_pk: &str, timelock: u32) -> Result<(), Error> {
-    // Create gift keys from descriptors
-    let gift_keys = GiftKeys::from_descriptor_strings(giver_pk, receiver_pk)?;
+/// Shows what is about to be locked on mainnet and asks for the confirmation phrase.
+fn confirm_mainnet(gift_keys: &GiftKeys, script: &GiftScript) -> Result<(), Error> {
+    println!("\nMAINNET GIFT - REAL FUNDS");
+    println!("=========================");
+    println!("Giver Public Key: {}", gift_keys.giver_pub()?);
+    println!("Receiver Public Key: {}", gift_keys.receiver_pub()?);
+    println!("Timelock Period: {}", script.timelock());
+    if let Some(clawback) = script.clawback() {
+        println!("Giver Clawback After: {}", clawback);
+    }
+    println!();
+    println!("Check both keys against your wallets. A wrong key cannot be fixed after funding.");
+    print!("Type '{}' to continue: ", CONFIRMATION_PHRASE);
+    io::stdout().flush().map_err(|e| Error::SafetyError(format!("Failed to prompt: {}", e)))?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| Error::SafetyError(format!("Failed to read confirmation: {}", e)))?;
+
+    if !confirmation_matches(&answer) {
+        return Err(Error::SafetyError("Mainnet gift was not confirmed".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Describes a timelock with the opcode that enforces it.
+fn timelock_description(timelock: &Timelock) -> String {
+    match timelock {
+        Timelock::Blocks(_) | Timelock::Seconds(_) => {
+            format!("{} after funding (relative, OP_CHECKSEQUENCEVERIFY)", timelock)
+        }
+        _ => format!("{} (absolute, OP_CHECKLOCKTIMEVERIFY)", timelock),
+    }
+}
 
-    if timelock < 1 {
-        return Err(Error::KeyError("Timelock must be greater than 0".to_string()));
+/// Prints how to spend through one tapscript leaf once its timelock passes.
+fn print_leaf_spend_instructions(leaf: &GiftLeaf) -> Result<(), Error> {
+    let (signer, title) = match leaf.role {
+        LeafRole::Receiver => ("Receiver", "To Spend After Timelock"),
+        LeafRole::Clawback => ("Giver", "To Claw Back an Unclaimed Gift"),
+    };
+    let timelock = leaf.timelock;
+
+    println!("\n{}:", title);
+    println!("{}", "-".repeat(title.len() + 1));
+    println!("1. Create a transaction spending from this address");
+    if timelock.is_absolute() {
+        println!("2. Set the transaction's nLockTime to at least: {}", timelock.to_lock_time()?.to_consensus_u32());
+        println!("   and the input's nSequence to: {} (non-final, so nLockTime is enforced)", timelock.to_sequence()?.to_consensus_u32());
+        if let Timelock::UnlockTime(_) = timelock {
+            println!("   Dates are checked against median time past, which trails real time by about an hour.");
+        }
+    } else {
+        println!("2. Set the transaction's nSequence to at least: {}", timelock.to_sequence()?.to_consensus_u32());
+        if let Some(effective) = timelock.effective_seconds()? {
+            println!("   (bit 22 set: the lock counts 512-second units of median time past)");
+            println!(
+                "   Rounding: {} requested, rounded up to {} x 512 s = {} so the lock never ends early",
+                timelock,
+                effective / Timelock::SECONDS_PER_UNIT,
+                format_duration(effective)
+            );
+        }
     }
+    println!("3. Witness stack should be (in order):");
+    println!("   - {}'s signature (schnorr, 64 bytes)", signer);
+    println!("   - {} leaf script (shown above)", leaf.role);
+    println!("   - {} leaf control block (shown above)", leaf.role);
+
+    Ok(())
+}
 
-    // Create script with timelock
-    let script = GiftScript::new(timelock);
+fn create_gift(args: &CreateArgs) -> Result<(), Error> {
+    let network = args.gift.network;
 
-    // Create the timelock script for later reference
-    let timelock_script = script.create_timelock_script(gift_keys.receiver_x_only_pub()?)?;
-    
-    // Get the taproot output and spend info
-    let (taproot_script, spend_info) = script.create_taproot_tree(&gift_keys)?;
+    // Create gift keys at this gift's index and the script with any clawback leaf
+    let (gift_keys, script) = args.gift.build()?;
+
+    // Mainnet gifts lock real funds, so they go through the safety gate first
+    if NetworkKind::from(network).is_mainnet() {
+        check_mainnet_gift(&gift_keys, &script)?;
+        if !args.i_understand_mainnet {
+            confirm_mainnet(&gift_keys, &script)?;
+        }
+    }
 
-    // Create address from script
-    let address = bitcoin::Address::from_script(&taproot_script, bitcoin::Network::Regtest)
-        .map_err(|e| Error::ScriptError(format!("Failed to create address: {}", e)))?;
-    
-    // Get control block information
-    let merkle_root = spend_info.merkle_root();
-    // Create control block with the leaf version
-    let leaf_version = bitcoin::taproot::LeafVersion::from_consensus(0xc0)
-        .map_err(|e| Error::ScriptError(format!("Invalid leaf version: {:?}", e)))?;
-    let control_block = spend_info.control_block(&(timelock_script.clone(), leaf_version))
-        .ok_or_else(|| Error::ScriptError("Failed to create control block".to_string()))?;
-    
     // Display the results
     println!("\nGift Created Successfully!");
     println!("===========================");
-    println!("");
+    print_gift_details(&gift_keys, &script, args.amount.map(Amount::from_sat))?;
+
+    println!();
+    println!("Use a new --index for every gift between the same two wallets.");
+    println!("Share this address with the giver to receive the gift amount.");
+    println!("Keep your wallet's private keys secure - they'll be needed to spend the funds.");
+
+    if !args.no_save {
+        save_gift(args, &script)?;
+    }
+
+    Ok(())
+}
+
+/// Prints a gift's address, keys, leaves, claim costs, descriptor and
+/// spending instructions.
+fn print_gift_details(gift_keys: &GiftKeys, script: &GiftScript, amount: Option<Amount>) -> Result<(), Error> {
+    let network = script.network();
+    let timelock = script.timelock();
+
+    // Get every leaf and the taproot output and spend info
+    let leaves = script.leaves(gift_keys)?;
+    let (taproot_script, spend_info) = script.create_taproot_tree(gift_keys)?;
+
+    // Create address from script
+    let address = bitcoin::Address::from_script(&taproot_script, network)
+        .map_err(|e| Error::ScriptError(format!("Failed to create address: {}", e)))?;
+
+    println!();
     println!("Deposit Address: {}", address);
-    println!("Timelock Period: {} blocks", timelock);
+    println!("Network: {} (bech32m prefix: {}1p)", network_name(network), bech32_hrp(network));
+    println!("Timelock: {}", timelock_description(&timelock));
+    if let Some(clawback) = script.clawback() {
+        println!("Giver Clawback: {}", timelock_description(&clawback));
+    }
+    println!("Derivation Index: {}", gift_keys.index);
     
     // Key information
     println!("\nSpending Information:");
     println!("---------------------");
-    println!("Internal Key (Giver): {}", gift_keys.giver_x_only_pub()?);
-    println!("Receiver Public Key: {}", gift_keys.receiver_x_only_pub()?);
+    println!("Key Path Mode: MuSig2 aggregate of giver and receiver (BIP-327)");
+    println!("Internal Key (MuSig2): {}", spend_info.internal_key());
+    println!("Giver Public Key: {}", gift_keys.giver_pub()?);
+    println!("Receiver Public Key: {}", gift_keys.receiver_pub()?);
+    if let Some((fingerprint, path)) = gift_keys.giver_key_origin()? {
+        println!("Giver Key Origin: [{}/{}]", fingerprint, path);
+    }
+    if let Some((fingerprint, path)) = gift_keys.receiver_key_origin()? {
+        println!("Receiver Key Origin: [{}/{}]", fingerprint, path);
+    }
     
     // Script information
     println!("\nTaproot Script Information:");
     println!("-------------------------");
-    println!("Timelock Script (hex): {}", hex::encode(timelock_script.as_bytes()));
-    println!("Script ASM: {}", timelock_script);
-    println!("Merkle Root: {:?}", merkle_root);
-    
-    // Control block information
-    println!("\nTapscript Spending Details:");
-    println!("--------------------------");
-    println!("Control Block (hex): {}", hex::encode(control_block.serialize()));
+    println!("Merkle Root: {:?}", spend_info.merkle_root());
     println!("Leaf Version: 0xc0 (Tapscript)");
-    println!("Script position in tree: 0");
+    for (position, leaf) in leaves.iter().enumerate() {
+        println!("\nLeaf {} ({}, depth {}):", position, leaf.role, leaf.depth);
+        println!("  Script (hex): {}", hex::encode(leaf.script.as_bytes()));
+        println!("  Script ASM: {}", leaf.script);
+        println!("  Control Block (hex): {}", hex::encode(leaf.control_block(&spend_info)?.serialize()));
+    }
+
+    print_claim_costs(gift_keys, script, amount)?;
     
-    // Spending instructions
-    println!("\nTo Spend After Timelock:");
+    // Descriptor for watch-only import
+    let descriptor = script.descriptor(gift_keys)?;
+    println!("\nWatch-only Descriptor:");
     println!("----------------------");
-    println!("1. Create a transaction spending from this address");
-    println!("2. Set the transaction's nSequence to at least: {}", timelock);
-    println!("3. Witness stack should be (in order):");
-    println!("   - Receiver's signature (schnorr, 64 bytes)");
-    println!("   - Timelock script (shown above)");
-    println!("   - Control block (shown above)");
+    println!("{}", descriptor);
+    println!("Import it as a watch-only wallet in Sparrow (File > New Wallet > Edit descriptor)");
+    println!("or in Bitcoin Core with importdescriptors and \"timestamp\": \"now\".");
+    
+    // Spending instructions
+    for leaf in &leaves {
+        print_leaf_spend_instructions(leaf)?;
+    }
     
     // Additional information
     println!("\nKey Usage Information:");
     println!("--------------------");
     println!("This address uses a Taproot output that enables:");
     println!("1. Cooperative spending between giver and receiver (using MuSig2)");
-    println!("2. Receiver-only spending after {} blocks", timelock);
-    println!("");
-    println!("Share this address with the giver to receive the gift amount.");
-    println!("Keep your wallet's private keys secure - they'll be needed to spend the funds.");
+    println!("2. Receiver-only spending once the timelock passes: {}", timelock_description(&timelock));
+    if let Some(clawback) = script.clawback() {
+        println!("3. Giver-only recovery of an unclaimed gift: {}", timelock_description(&clawback));
+    }
+    println!();
+    println!("To spend together at any time, use `mallowbtc coop start` with these gift parameters.");
+    println!("Once the timelock passes, run `mallowbtc redeem` with these gift parameters");
+    println!("to build the receiver's spending PSBT instead of assembling the witness by hand.");
 
     Ok(())
 }
 
+/// Records a newly created gift in the registry.
+fn save_gift(args: &CreateArgs, script: &GiftScript) -> Result<(), Error> {
+    let (Some(giver), Some(receiver)) = (&args.gift.giver_tpub, &args.gift.receiver_tpub) else {
+        return Ok(());
+    };
+    let record = GiftRecord::new(giver, receiver, args.gift.index, script)?.with_label(args.label.clone());
+    let (path, mut registry) = args.registry.load()?;
+    let id = registry.add(record);
+    registry.save(&path)?;
+
+    println!();
+    println!("Saved as gift #{} in {}", id, path.display());
+    println!("See it again with `mallowbtc show {}`, or skip saving with --no-save.", id);
+
+    Ok(())
+}
+
+/// Parses a `txid:vout:amount_sat[:index]` UTXO spec; the index is
+/// required when `with_index` is set and rejected otherwise.
+fn parse_utxo_spec(spec: &str, with_index: bool) -> Result<(OutPoint, Amount, Option<u32>, Option<u32>), Error> {
+    let invalid = |reason: &str| Error::TransactionError(format!("Invalid UTXO '{}': {}", spec, reason));
+
+    let parts: Vec<&str> = spec.split(':').collect();
+    let (txid, vout, amount, index, height) = match (parts.as_slice(), with_index) {
+        (&[txid, vout, amount, index], true) => (txid, vout, amount, Some(index), None),
+        (&[txid, vout, amount, index, height], true) => (txid, vout, amount, Some(index), Some(height)),
+        (&[txid, vout, amount], false) => (txid, vout, amount, None, None),
+        (_, true) => return Err(invalid("expected txid:vout:amount_sat:index[:height]")),
+        (_, false) => return Err(invalid("expected txid:vout:amount_sat")),
+    };
+
+    let outpoint = OutPoint::from_str(&format!("{}:{}", txid, vout)).map_err(|e| invalid(&e.to_string()))?;
+    let amount = amount
+        .parse()
+        .map(Amount::from_sat)
+        .map_err(|_| invalid("amount must be a whole number of satoshis"))?;
+    let index = index
+        .map(|index| index.parse().map_err(|_| invalid("index must be a derivation index")))
+        .transpose()?;
+    let height = height
+        .map(|height| height.parse().map_err(|_| invalid("height must be a block height")))
+        .transpose()?;
+
+    Ok((outpoint, amount, index, height))
+}
+
+fn fund_gift(args: &FundArgs) -> Result<(), Error> {
+    let (gift_keys, script) = args.gift.build()?;
+
+    // An unsigned PSBT moves nothing yet, but its gift output must still be sound
+    if NetworkKind::from(args.gift.network).is_mainnet() {
+        check_mainnet_gift(&gift_keys, &script)?;
+    }
+
+    let address = script.create_address(&gift_keys)?;
+    let gift_tx = GiftTransaction::new(gift_keys, script);
+
+    let backend = args.backend.connect()?;
+    let fee_rate = resolve_fee_rate(args.fee_rate, backend.as_deref())?;
+    let utxos = args.utxos.load(&gift_tx, backend.as_deref())?;
+
+    let redeem_fee_rate = args.redeem_fee_rate.unwrap_or(fee_rate);
+    let redemption_fee = fee::redemption_fee(
+        gift_tx.keys(),
+        gift_tx.script(),
+        SpendPath::Leaf(LeafRole::Receiver),
+        redeem_fee_rate,
+    )?;
+    let gift_amount = args.fee_mode.gift_output(Amount::from_sat(args.amount), redemption_fee)?;
+
+    let options = args.utxos.options()?;
+    let available = utxos.len();
+    let utxos = select_utxos(&gift_tx, &utxos, gift_amount, fee_rate, args.change_index, &options)?;
+    let psbt = gift_tx.fund(&utxos, gift_amount, fee_rate, args.change_index)?;
+    let fee = psbt
+        .fee()
+        .map_err(|e| Error::TransactionError(format!("Failed to compute fee: {}", e)))?;
+
+    println!("\nFunding PSBT Created");
+    println!("====================");
+    println!();
+    println!("Gift Address: {}", address);
+    println!("Gift Amount: {} sat", gift_amount.to_sat());
+    match args.fee_mode {
+        FeeMode::AmountSent => println!(
+            "Receiver Gets: about {} sat after a {} sat redemption fee at {} sat/vB",
+            gift_amount.to_sat().saturating_sub(redemption_fee.to_sat()),
+            redemption_fee.to_sat(),
+            fee::format_fee_rate(redeem_fee_rate)
+        ),
+        FeeMode::AmountReceived => println!(
+            "Receiver Gets: {} sat; the gift includes a {} sat redemption fee at {} sat/vB",
+            args.amount,
+            redemption_fee.to_sat(),
+            fee::format_fee_rate(redeem_fee_rate)
+        ),
+    }
+    println!(
+        "Inputs: {} of {} available ({} sat, {} selection)",
+        utxos.len(),
+        available,
+        utxos.iter().map(|utxo| utxo.amount.to_sat()).sum::<u64>(),
+        options.strategy
+    );
+    match psbt.unsigned_tx.output.get(1) {
+        Some(change) => println!("Change: {} sat to giver index {}", change.value.to_sat(), args.change_index),
+        None => println!("Change: none (below the dust limit, added to the fee)"),
+    }
+    let change_index = (psbt.unsigned_tx.output.len() == 2).then_some(args.change_index);
+    let weight = gift_tx.funding_weight(&utxos, change_index)?;
+    println!("Fee: {} sat ({} vB)", fee.to_sat(), fee::vsize(weight));
+
+    output_psbt(&psbt, args.output.as_ref())?;
+    println!();
+    println!("Load it in Sparrow (File > Open Transaction) or Bitcoin Core (walletprocesspsbt),");
+    println!("check the gift address and amount, then sign and broadcast.");
+
+    // Signing leaves a segwit txid unchanged, so the gift output is already known
+    let txid = psbt.unsigned_tx.compute_txid();
+    if let Some(vout) = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .position(|output| output.script_pubkey == address.script_pubkey())
+    {
+        args.registry.update(&address.to_string(), |record| {
+            record.funding_txid = Some(txid.to_string());
+            record.funding_outpoint = Some(OutPoint::new(txid, vout as u32).to_string());
+        });
+    }
+
+    Ok(())
+}
+
+/// Funds every gift of a plan in one PSBT and prints its inputs, change and fee.
+fn fund_plan(
+    plan: &BatchPlan,
+    utxo_args: &UtxoArgs,
+    backend_args: &BackendArgs,
+    fee_rate: Option<FeeRate>,
+    change_index: u32,
+) -> Result<Psbt, Error> {
+    let backend = backend_args.connect()?;
+    let fee_rate = resolve_fee_rate(fee_rate, backend.as_deref())?;
+    let funder = &plan.gifts[0].transaction;
+    let utxos = utxo_args.load(funder, backend.as_deref())?;
+    let options = utxo_args.options()?;
+    let available = utxos.len();
+    let outputs = plan.outputs();
+    let utxos = select_batch_utxos(&outputs, &utxos, fee_rate, change_index, &options)?;
+    let psbt = GiftTransaction::fund_batch(&outputs, &utxos, fee_rate, change_index)?;
+    let fee = psbt
+        .fee()
+        .map_err(|e| Error::TransactionError(format!("Failed to compute fee: {}", e)))?;
+
+    println!(
+        "Inputs: {} of {} available ({} sat, {} selection)",
+        utxos.len(),
+        available,
+        utxos.iter().map(|utxo| utxo.amount.to_sat()).sum::<u64>(),
+        options.strategy
+    );
+    match psbt.unsigned_tx.output.get(plan.gifts.len()) {
+        Some(change) => println!("Change: {} sat to giver index {}", change.value.to_sat(), change_index),
+        None => println!("Change: none (below the dust limit, added to the fee)"),
+    }
+    let change_index = (psbt.unsigned_tx.output.len() > plan.gifts.len()).then_some(change_index);
+    let weight = GiftTransaction::batch_funding_weight(&outputs, &utxos, change_index)?;
+    println!("Fee: {} sat ({} vB)", fee.to_sat(), fee::vsize(weight));
+
+    Ok(psbt)
+}
+
+/// Rejects a plan with any gift that would not pass the mainnet checks alone.
+fn check_mainnet_plan(plan: &BatchPlan) -> Result<(), Error> {
+    if NetworkKind::from(plan.network).is_mainnet() {
+        for planned in &plan.gifts {
+            let transaction = &planned.transaction;
+            check_mainnet_gift(transaction.keys(), transaction.script())
+                .map_err(|e| Error::BatchError(format!("Gift '{}': {}", planned.gift.label, e)))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn batch_gifts(args: &BatchArgs) -> Result<(), Error> {
+    let manifest = batch::load_manifest(&args.manifest)?;
+    let plan = BatchPlan::new(&args.giver_tpub, manifest, args.index, args.network)?;
+
+    // Every output of the batch must pass the checks a single gift does
+    check_mainnet_plan(&plan)?;
+
+    println!("\nBatch Funding PSBT Created");
+    println!("==========================");
+    println!();
+    println!("{:<4} {:<16} {:>6} {:>12}  {:<24} Address", "Vout", "Label", "Index", "Amount", "Timelock");
+    for (vout, planned) in plan.gifts.iter().enumerate() {
+        println!(
+            "{:<4} {:<16} {:>6} {:>8} sat  {:<24} {}",
+            vout,
+            planned.gift.label,
+            planned.index,
+            planned.gift.amount.to_sat(),
+            planned.gift.timelock.to_string(),
+            planned.address
+        );
+    }
+    println!();
+    println!("Gifts: {} totalling {} sat", plan.gifts.len(), plan.total().to_sat());
+    let psbt = fund_plan(&plan, &args.utxos, &args.backend, args.fee_rate, args.change_index)?;
+
+    let report_path = args
+        .report
+        .clone()
+        .unwrap_or_else(|| args.manifest.with_extension("report.json"));
+    plan.report(&psbt)?.save(&report_path)?;
+    println!("Report: {} (address, descriptor and control blocks of every gift)", report_path.display());
+
+    output_psbt(&psbt, args.output.as_ref())?;
+    println!();
+    println!("Give each receiver their entry of the report, then sign and broadcast the PSBT.");
+
+    Ok(())
+}
+
+fn vest_gift(args: &VestArgs) -> Result<(), Error> {
+    let schedule = args.schedule()?;
+    let plan = schedule.plan(&args.giver_tpub, &args.receiver_tpub, args.index, args.network)?;
+
+    // Every tranche must pass the checks a single gift does
+    check_mainnet_plan(&plan)?;
+
+    println!("\nVesting Funding PSBT Created");
+    println!("============================");
+    println!();
+    println!("Vesting Schedule:");
+    println!("-----------------");
+    println!("{:<8} {:>12} {:>6} {:>14}  Receiver-Spendable From", "Tranche", "Amount", "Share", "Vested");
+    let mut vested = Amount::ZERO;
+    for (position, (tranche, planned)) in schedule.tranches.iter().zip(&plan.gifts).enumerate() {
+        vested += tranche.amount;
+        println!(
+            "{:<8} {:>8} sat {:>5.1}% {:>10} sat  {}",
+            position + 1,
+            tranche.amount.to_sat(),
+            tranche.amount.to_sat() as f64 * 100.0 / schedule.total.to_sat() as f64,
+            vested.to_sat(),
+            vesting::spendable_from(&tranche.timelock)
+        );
+        println!("{:<8} Address (index {}): {}", "", planned.index, planned.address);
+    }
+    println!();
+    println!("Tranches: {} totalling {} sat", plan.gifts.len(), plan.total().to_sat());
+    let psbt = fund_plan(&plan, &args.utxos, &args.backend, args.fee_rate, args.change_index)?;
+
+    if let Some(report_path) = &args.report {
+        plan.report(&psbt)?.save(report_path)?;
+        println!("Report: {} (address, descriptor and control blocks of every tranche)", report_path.display());
+    }
+
+    output_psbt(&psbt, args.output.as_ref())?;
+    println!();
+    println!("Relative tranches count from the block that confirms this transaction.");
+    println!("Before any unlock, giver and receiver can still spend a tranche together.");
+
+    Ok(())
+}
+
+/// Parses `txid:vout` outpoints given to --include-utxo and --exclude-utxo.
+fn parse_outpoints(specs: &[String]) -> Result<Vec<OutPoint>, Error> {
+    specs
+        .iter()
+        .map(|spec| {
+            OutPoint::from_str(spec)
+                .map_err(|e| Error::CoinSelectionError(format!("Invalid outpoint '{}': {}", spec, e)))
+        })
+        .collect()
+}
+
+/// Writes a PSBT to `path` if given and prints it as base64.
+fn output_psbt(psbt: &Psbt, path: Option<&PathBuf>) -> Result<(), Error> {
+    if let Some(path) = path {
+        fs::write(path, psbt.serialize())
+            .map_err(|e| Error::TransactionError(format!("Failed to write {}: {}", path.display(), e)))?;
+        println!("PSBT File: {}", path.display());
+    }
+
+    println!("\nPSBT (base64):");
+    println!("--------------");
+    println!("{}", psbt);
+
+    Ok(())
+}
+
+/// Reads a PSBT given as base64 or as a path to a binary or base64 file.
+fn read_psbt(input: &str) -> Result<Psbt, Error> {
+    let path = Path::new(input);
+    if !path.is_file() {
+        return Psbt::from_str(input.trim())
+            .map_err(|e| Error::TransactionError(format!("Invalid PSBT: {}", e)));
+    }
+
+    let bytes = fs::read(path)
+        .map_err(|e| Error::TransactionError(format!("Failed to read {}: {}", path.display(), e)))?;
+    Psbt::deserialize(&bytes)
+        .or_else(|_| Psbt::from_str(String::from_utf8_lossy(&bytes).trim()))
+        .map_err(|e| Error::TransactionError(format!("Invalid PSBT in {}: {}", path.display(), e)))
+}
+
+fn redeem_gift(args: &RedeemArgs) -> Result<(), Error> {
+    let (gift_keys, script) = args.gift.build()?;
+    let timelock = script.timelock();
+
+    let destination = Address::from_str(&args.destination)
+        .and_then(|address| address.require_network(args.gift.network))
+        .map_err(|e| Error::TransactionError(format!("Invalid destination address: {}", e)))?;
+
+    let address = script.create_address(&gift_keys)?;
+    let gift_tx = GiftTransaction::new(gift_keys, script);
+    let backend = args.backend.connect()?;
+    let (outpoint, amount, confirmation_height) = match (&args.gift_utxo, backend.as_deref()) {
+        (Some(spec), _) => {
+            let (outpoint, amount, _, _) = parse_utxo_spec(spec, false)?;
+            (outpoint, amount, None)
+        }
+        (None, Some(backend)) => {
+            let found = backend::gift_utxos(backend, &gift_tx)?;
+            let [utxo] = found.as_slice() else {
+                return Err(Error::TransactionError(format!(
+                    "Found {} unspent outputs paying the gift address; choose one with --gift-utxo",
+                    found.len()
+                )));
+            };
+            (utxo.outpoint, utxo.txout.value, utxo.confirmation_height)
+        }
+        (None, None) => {
+            return Err(Error::TransactionError(
+                "Give --gift-utxo, or a chain backend to look the gift up".to_string(),
+            ));
+        }
+    };
+    let fee_rate = resolve_fee_rate(args.fee_rate, backend.as_deref())?;
+    let psbt = gift_tx.redeem_after_timelock(outpoint, amount, destination.script_pubkey(), fee_rate)?;
+    let fee = psbt
+        .fee()
+        .map_err(|e| Error::TransactionError(format!("Failed to compute fee: {}", e)))?;
+
+    println!("\nRedemption PSBT Created");
+    println!("=======================");
+    println!();
+    println!("Gift Output: {} ({} sat)", outpoint, amount.to_sat());
+    println!("Destination: {} ({} sat)", destination, psbt.unsigned_tx.output[0].value.to_sat());
+    let weight = fee::spend_weight(
+        gift_tx.keys(),
+        gift_tx.script(),
+        SpendPath::Leaf(LeafRole::Receiver),
+        destination.script_pubkey().len(),
+    )?;
+    println!("Fee: {} sat ({} vB)", fee.to_sat(), fee::vsize(weight));
+    println!("Spendable: {}", timelock_description(&timelock));
+    if let Some(backend) = backend.as_deref() {
+        match timelock.blocks_remaining(confirmation_height, backend.tip_height()?) {
+            Some(0) => println!("Timelock Status: passed, the redemption can be mined now"),
+            Some(blocks) => println!("Timelock Status: {} more blocks before the redemption can be mined", blocks),
+            None => println!("Timelock Status: unknown, check the gift's confirmation and the chain's median time"),
+        }
+    }
+    println!("nSequence: {}", psbt.unsigned_tx.input[0].sequence.to_consensus_u32());
+    println!("nLockTime: {}", psbt.unsigned_tx.lock_time.to_consensus_u32());
+
+    output_psbt(&psbt, args.output.as_ref())?;
+    println!();
+    println!("Sign it with the receiver's wallet, then run:");
+    println!("  mallowbtc finalize --psbt=<SIGNED PSBT>");
+    println!("The network rejects the transaction until the timelock has passed.");
+
+    let txid = psbt.unsigned_tx.compute_txid().to_string();
+    args.registry.update(&address.to_string(), |record| {
+        record.redemption = Redemption::Pending {
+            txid,
+            path: SpendPath::Leaf(LeafRole::Receiver).to_string(),
+        };
+    });
+
+    Ok(())
+}
+
+fn finalize_psbt(args: &FinalizeArgs) -> Result<(), Error> {
+    let tx = GiftTransaction::finalize(read_psbt(&args.psbt)?)?;
+
+    println!("\nTransaction Finalized");
+    println!("=====================");
+    println!();
+    println!("Txid: {}", tx.compute_txid());
+    println!("Virtual Size: {} vB", tx.vsize());
+    println!("\nRaw Transaction (hex):");
+    println!("----------------------");
+    println!("{}", serialize_hex(&tx));
+
+    publish(&tx, args.broadcast, &args.backend)
+}
+
+/// Broadcasts a finished transaction if asked to, or says how to.
+fn publish(tx: &Transaction, broadcast: bool, backend_args: &BackendArgs) -> Result<(), Error> {
+    println!();
+    if !broadcast {
+        println!("Broadcast it with your wallet or bitcoin-cli sendrawtransaction, or pass --broadcast.");
+        return Ok(());
+    }
+
+    let backend = backend_args.connect()?.ok_or_else(|| {
+        Error::TransactionError("--broadcast needs a chain backend: --rpc-url, --esplora-url or --electrum-url".to_string())
+    })?;
+    println!("Broadcast: {}", backend.broadcast(tx)?);
+
+    Ok(())
+}
+
+/// Reports a gift's outputs, their unlock countdowns and how they were
+/// spent, and records the funding and any spend in the registry.
+fn gift_status(args: &StatusArgs) -> Result<(), Error> {
+    let gift = match &args.descriptor {
+        Some(descriptor) => WatchedGift::from_descriptor_str(descriptor, args.gift.index, args.gift.network)?,
+        None => {
+            let (keys, script) = args.gift.build()?;
+            WatchedGift::new(&keys, &script)?
+        }
+    };
+    let Some(backend) = args.backend.connect()? else {
+        return Err(Error::BackendError(
+            "status needs --rpc-url, --esplora-url or --electrum-url".to_string(),
+        ));
+    };
+    let status = GiftStatus::fetch(backend.as_ref(), &gift)?;
+
+    println!("\nGift Status");
+    println!("===========");
+    println!();
+    println!("Address: {}", gift.address);
+    println!("Chain Tip: block {}", status.tip_height);
+    for leaf in &gift.leaves {
+        println!("{} leaf: {}", capitalize(&leaf.role.to_string()), timelock_description(&leaf.timelock));
+    }
+
+    if status.outputs.is_empty() {
+        println!();
+        println!("Not funded yet: no transaction pays this address.");
+    }
+    for output in &status.outputs {
+        println!();
+        println!("--- {} ---", output.outpoint);
+        println!("Amount: {} sat", output.amount.to_sat());
+        match output.confirmation_height {
+            Some(height) => println!(
+                "Confirmed: block {} ({} confirmations)",
+                height,
+                status.tip_height.saturating_sub(height) + 1
+            ),
+            None => println!("Confirmed: no, waiting in the mempool"),
+        }
+        for (role, remaining) in &output.blocks_remaining {
+            let unlock = match remaining {
+                Some(0) => "spendable now".to_string(),
+                Some(blocks) => format!(
+                    "spendable in {} blocks (from block {})",
+                    blocks,
+                    status.tip_height + blocks + 1
+                ),
+                None if output.confirmation_height.is_none() => "counts down once the funding confirms".to_string(),
+                None => "depends on the chain's median time".to_string(),
+            };
+            println!("{} leaf: {}", capitalize(&role.to_string()), unlock);
+        }
+        match &output.spent {
+            Some(spend) => {
+                let path = spend.path.map_or("an unrecognized path".to_string(), |path| format!("the {}", path));
+                match spend.confirmation_height {
+                    Some(height) => println!("Spent: by {} in block {} via {}", spend.txid, height, path),
+                    None => println!("Spent: by {} (unconfirmed) via {}", spend.txid, path),
+                }
+            }
+            None => println!("Spent: no"),
+        }
+    }
+
+    if !status.complete_history {
+        println!();
+        println!("Note: Bitcoin Core has no address index, so only unspent outputs are shown.");
+        println!("Use --esplora-url or --electrum-url to see spent outputs and how they were spent.");
+    }
+
+    // An address funded more than once has several outputs; the registry
+    // tracks one, preferring a confirmed spend, then a pending one, then the
+    // output already recorded as the funding
+    if !status.outputs.is_empty() {
+        args.registry.update(&gift.address.to_string(), |record| {
+            let recorded = record.funding_outpoint.clone();
+            let output = status
+                .outputs
+                .iter()
+                .find(|output| output.spent.as_ref().is_some_and(|spend| spend.confirmation_height.is_some()))
+                .or_else(|| status.outputs.iter().find(|output| output.spent.is_some()))
+                .or_else(|| status.outputs.iter().find(|output| Some(output.outpoint.to_string()) == recorded))
+                .unwrap_or(&status.outputs[0]);
+
+            record.funding_txid = Some(output.outpoint.txid.to_string());
+            record.funding_outpoint = Some(output.outpoint.to_string());
+            if let Some(spend) = &output.spent {
+                let txid = spend.txid.to_string();
+                let path = spend.path.map_or("unrecognized path".to_string(), |path| path.to_string());
+                record.redemption = match spend.confirmation_height {
+                    Some(height) => Redemption::Spent { txid, path, height },
+                    None => Redemption::Pending { txid, path },
+                };
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Summarizes how far a recorded gift has got.
+fn gift_state(record: &GiftRecord) -> String {
+    match (&record.redemption, &record.funding_outpoint) {
+        (Redemption::Unspent, Some(_)) => "funded".to_string(),
+        (Redemption::Unspent, None) => "not funded".to_string(),
+        (Redemption::Pending { .. }, _) => "redemption pending".to_string(),
+        (Redemption::Spent { height, .. }, _) => format!("spent in block {}", height),
+    }
+}
+
+fn list_gifts(args: &RegistryArgs) -> Result<(), Error> {
+    let (path, registry) = args.load()?;
+
+    println!("\nRecorded Gifts");
+    println!("==============");
+    println!("Registry: {}", path.display());
+    println!();
+    if registry.gifts().is_empty() {
+        println!("No gifts recorded yet. `mallowbtc create` records each gift it creates.");
+        return Ok(());
+    }
+
+    println!("{:>4}  {:<24} {:<8} {:<28} State", "ID", "Label", "Network", "Timelock");
+    for record in registry.gifts() {
+        println!(
+            "{:>4}  {:<24} {:<8} {:<28} {}",
+            record.id,
+            record.display_label(),
+            record.network,
+            record.timelock.to_string(),
+            gift_state(record)
+        );
+        println!("      {}", record.address);
+    }
+
+    Ok(())
+}
+
+fn show_gift(args: &GiftIdArgs) -> Result<(), Error> {
+    let (_, registry) = args.registry.load()?;
+    let record = registry
+        .get(args.id)
+        .ok_or_else(|| Error::RegistryError(format!("No gift #{} in the registry", args.id)))?;
+    let (keys, script) = record.gift()?;
+    let (_, spend_info) = script.create_taproot_tree(&keys)?;
+
+    println!("\nGift #{}: {}", record.id, record.display_label());
+    println!("===========================");
+    println!();
+    let created = u32::try_from(record.created_at).map_or(record.created_at.to_string(), |time| {
+        format!("{} UTC", format_timestamp(time))
+    });
+    println!("Created: {}", created);
+    println!("Address: {}", record.address);
+    println!("Network: {}", record.network);
+    println!("Timelock: {}", timelock_description(&record.timelock));
+    if let Some(clawback) = &record.clawback {
+        println!("Giver Clawback: {}", timelock_description(clawback));
+    }
+    println!("Derivation Index: {}", record.index);
+    println!("Giver Descriptor: {}", record.giver);
+    println!("Receiver Descriptor: {}", record.receiver);
+    println!("Funding: {}", record.funding_outpoint.as_deref().unwrap_or("not recorded"));
+    println!("Redemption: {}", record.redemption);
+
+    println!("\nSpending Information:");
+    println!("---------------------");
+    println!("Internal Key (MuSig2): {}", spend_info.internal_key());
+    for (position, leaf) in script.leaves(&keys)?.iter().enumerate() {
+        println!("\nLeaf {} ({}, depth {}):", position, leaf.role, leaf.depth);
+        println!("  Script (hex): {}", hex::encode(leaf.script.as_bytes()));
+        println!("  Control Block (hex): {}", hex::encode(leaf.control_block(&spend_info)?.serialize()));
+    }
+    println!("\nWatch-only Descriptor:");
+    println!("----------------------");
+    println!("{}", record.descriptor);
+
+    Ok(())
+}
+
+fn export_gift(args: &ExportArgs) -> Result<(), Error> {
+    let (_, registry) = args.registry.load()?;
+    let record = registry
+        .get(args.id)
+        .ok_or_else(|| Error::RegistryError(format!("No gift #{} in the registry", args.id)))?;
+    let backup = GiftBackup::new(record)?;
+    let path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("gift-{}.json", args.id)));
+    backup.save(&path)?;
+
+    println!("\nGift Backup Written");
+    println!("===================");
+    println!();
+    println!("Gift: #{} {}", record.id, record.display_label());
+    println!("Address: {}", backup.gift.address);
+    println!("File: {}", path.display());
+    println!("Checksum: {}", backup.checksum);
+    println!();
+    println!("The backup holds public keys, scripts and control blocks, no private keys.");
+    println!("Give a copy to the receiver: with it and their wallet they can always claim the gift.");
+
+    Ok(())
+}
+
+fn import_gift(args: &ImportArgs) -> Result<(), Error> {
+    let backup = GiftBackup::load(&args.file)?;
+    let record = backup.verify()?;
+
+    println!("\nGift Backup Verified");
+    println!("====================");
+    println!();
+    println!("Label: {}", record.display_label());
+    println!("Address: {} (rebuilt from the keys and timelocks)", record.address);
+    println!("Network: {}", record.network);
+    println!("Timelock: {}", timelock_description(&record.timelock));
+    if let Some(clawback) = &record.clawback {
+        println!("Giver Clawback: {}", timelock_description(clawback));
+    }
+    println!("Derivation Index: {}", record.index);
+    println!("Funding: {}", record.funding_outpoint.as_deref().unwrap_or("not recorded"));
+
+    if !args.no_save {
+        let (path, mut registry) = args.registry.load()?;
+        let id = registry.add(record);
+        registry.save(&path)?;
+        println!();
+        println!("Recorded as gift #{} in {}", id, path.display());
+    }
+
+    Ok(())
+}
+
+fn recover_gift(args: &RecoverArgs) -> Result<(), Error> {
+    let unchecked = Address::from_str(&args.address)
+        .map_err(|e| Error::RecoveryError(format!("Invalid address: {}", e)))?;
+    let network = match args.network {
+        Some(network) => network,
+        None => network_for_address(&unchecked)?,
+    };
+    let address = unchecked
+        .require_network(network)
+        .map_err(|e| Error::RecoveryError(format!("Invalid address: {}", e)))?;
+
+    let mut search = RecoverySearch {
+        indices: 0..args.scan_indices,
+        ..RecoverySearch::default()
+    };
+    if let Some(timelocks) = &args.timelocks {
+        search.timelocks = recover::parse_candidates(timelocks)?;
+    }
+    match args.clawbacks.as_deref() {
+        Some("none") => search.clawbacks.clear(),
+        Some(clawbacks) => search.clawbacks = recover::parse_candidates(clawbacks)?,
+        None => {}
+    }
+
+    println!(
+        "Searching {} combinations of index, timelock and leaf layout...",
+        search.candidates()
+    );
+    let Some(recovered) = search.run(&args.giver, &args.receiver, &address, network)? else {
+        return Err(Error::RecoveryError(format!(
+            "No gift between these keys at indices 0 to {} with the timelocks tried pays {}. \
+             Check the descriptors, or widen --scan-indices, --timelocks or --clawbacks",
+            args.scan_indices.saturating_sub(1),
+            address
+        )));
+    };
+
+    println!("\nGift Recovered");
+    println!("==============");
+    print_gift_details(&recovered.keys, &recovered.script, None)?;
+
+    if !args.no_save {
+        let record = GiftRecord::new(&args.giver, &args.receiver, recovered.keys.index, &recovered.script)?
+            .with_label(args.label.clone());
+        let (path, mut registry) = args.registry.load()?;
+        let id = registry.add(record);
+        registry.save(&path)?;
+        println!();
+        println!("Saved as gift #{} in {}", id, path.display());
+    }
+
+    Ok(())
+}
+
+fn forget_gift(args: &GiftIdArgs) -> Result<(), Error> {
+    let (path, mut registry) = args.registry.load()?;
+    let record = registry
+        .forget(args.id)
+        .ok_or_else(|| Error::RegistryError(format!("No gift #{} in the registry", args.id)))?;
+    registry.save(&path)?;
+
+    println!("\nForgot gift #{}: {} at {}", record.id, record.display_label(), record.address);
+    println!("Any funds at the address are unaffected; keep the gift's details elsewhere if it is funded.");
+
+    Ok(())
+}
+
+/// Upper-cases the first letter of a label.
+fn capitalize(label: &str) -> String {
+    let mut chars = label.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Reads the signer's secret key from the flag or standard input.
+fn read_secret_key(args: &CoopSignerArgs) -> Result<SecretKey, Error> {
+    let spec = match &args.secret_key {
+        Some(spec) => spec.clone(),
+        None => {
+            print!("Secret key: ");
+            io::stdout().flush().map_err(|e| Error::KeyError(format!("Failed to prompt: {}", e)))?;
+            let mut line = String::new();
+            io::stdin()
+                .lock()
+                .read_line(&mut line)
+                .map_err(|e| Error::KeyError(format!("Failed to read secret key: {}", e)))?;
+            line
+        }
+    };
+
+    parse_secret_key(&spec, args.index)
+}
+
+/// Returns where the signer's nonce file lives.
+fn nonce_file(args: &CoopSignerArgs, secret: &SecretKey) -> PathBuf {
+    args.nonce_file.clone().unwrap_or_else(|| {
+        let key = secret.public_key(&Secp256k1::signing_only()).to_string();
+        let mut path = args.session.session.clone().into_os_string();
+        path.push(format!(".{}.nonce.json", &key[..16]));
+        PathBuf::from(path)
+    })
+}
+
+/// Prints the size and fee of claiming the gift through each spending path
+/// and, given the gift amount, its break-even fee rates and any warnings.
+fn print_claim_costs(keys: &GiftKeys, script: &GiftScript, amount: Option<Amount>) -> Result<(), Error> {
+    let costs = fee::claim_costs(keys, script)?;
+    let rates = [1, 10, 100].map(FeeRate::from_sat_per_vb_u32);
+
+    println!("\nClaim Costs (to a P2TR address):");
+    println!("--------------------------------");
+    for cost in &costs {
+        let fees = rates
+            .iter()
+            .map(|rate| Ok(format!("{} sat at {} sat/vB", cost.fee(*rate)?.to_sat(), fee::format_fee_rate(*rate))))
+            .collect::<Result<Vec<_>, Error>>()?;
+        println!(
+            "{}: {} vB (witness up to {} WU); {}",
+            cost.path,
+            fee::vsize(cost.weight),
+            cost.satisfaction_weight.to_wu(),
+            fees.join(", ")
+        );
+    }
+
+    let Some(amount) = amount else {
+        return Ok(());
+    };
+    for cost in &costs {
+        match cost.break_even_fee_rate(amount) {
+            Some(rate) => println!("Break-even via {}: {} sat/vB", cost.path, fee::format_fee_rate(rate)),
+            None => println!("Break-even via {}: none, the gift is dust", cost.path),
+        }
+    }
+    for warning in fee::viability_warnings(&costs, amount) {
+        println!("WARNING: {}", warning);
+    }
+
+    Ok(())
+}
+
+/// Prints what the session spends and how far the exchange has got.
+fn print_session(session: &CoopSession) -> Result<(), Error> {
+    let psbt = session.psbt()?;
+    let fee = psbt
+        .fee()
+        .map_err(|e| Error::TransactionError(format!("Failed to compute fee: {}", e)))?;
+
+    println!("\nCooperative Spend");
+    println!("=================");
+    println!("Session: {}", session.session_id);
+    println!("Spending: {}", psbt.unsigned_tx.input[0].previous_output);
+    for output in &psbt.unsigned_tx.output {
+        match Address::from_script(&output.script_pubkey, session.network) {
+            Ok(address) => println!("Pays: {} sat to {}", output.value.to_sat(), address),
+            Err(_) => println!("Pays: {} sat to script {}", output.value.to_sat(), output.script_pubkey),
+        }
+    }
+    println!("Fee: {} sat", fee.to_sat());
+    let nonces = session.nonces()?;
+    let partial_signatures = session.partial_signatures()?;
+    for key in session.participants()? {
+        let nonce = if nonces.contains_key(&key) { "nonce added" } else { "waiting for nonce" };
+        let signature = if partial_signatures.contains_key(&key) { ", signed" } else { "" };
+        println!("Participant {}: {}{}", key, nonce, signature);
+    }
+
+    Ok(())
+}
+
+fn coop_start(args: &CoopStartArgs) -> Result<(), Error> {
+    let (gift_keys, script) = args.gift.build()?;
+    let (outpoint, amount, _, _) = parse_utxo_spec(&args.gift_utxo, false)?;
+    let destination = Address::from_str(&args.destination)
+        .and_then(|address| address.require_network(args.gift.network))
+        .map_err(|e| Error::TransactionError(format!("Invalid destination address: {}", e)))?;
+
+    let gift_tx = GiftTransaction::new(gift_keys, script);
+    let psbt = gift_tx.spend_cooperatively(outpoint, amount, destination.script_pubkey(), args.fee_rate)?;
+    let session = CoopSession::start(&gift_tx, &psbt)?;
+    session.save(&args.session)?;
+
+    print_session(&session)?;
+    println!("\nSession written to {}", args.session.display());
+    println!("Next: each participant runs `mallowbtc coop nonce --session={}`", args.session.display());
+    println!("and passes the updated file on.");
+
+    Ok(())
+}
+
+fn coop_nonce(args: &CoopSignerArgs) -> Result<(), Error> {
+    let mut session = CoopSession::load(&args.session.session)?;
+    print_session(&session)?;
+
+    let secret = read_secret_key(args)?;
+    let path = nonce_file(args, &secret);
+    let previous = if path.exists() { Some(NonceState::load(&path)?) } else { None };
+
+    let state = session.generate_nonce(&secret, previous.as_ref())?;
+    // The secret nonce must be on disk before its public half is shared
+    state.save(&path)?;
+    session.save(&args.session.session)?;
+
+    println!("\nNonce added to {}", args.session.session.display());
+    println!("Secret nonce kept in {} - do not share or copy it", path.display());
+    println!("Next: once both nonces are in the session, each participant runs `mallowbtc coop sign`.");
+
+    Ok(())
+}
+
+fn coop_sign(args: &CoopSignerArgs) -> Result<(), Error> {
+    let mut session = CoopSession::load(&args.session.session)?;
+    print_session(&session)?;
+    session.aggregated_nonce()?;
+
+    let secret = read_secret_key(args)?;
+    let secnonce = NonceState::take_secret_nonce(&nonce_file(args, &secret), &session, &secret)?;
+    session.sign(&secret, secnonce)?;
+    session.save(&args.session.session)?;
+
+    println!("\nPartial signature added to {}", args.session.session.display());
+    println!("Your secret nonce has been used and erased; signing again needs a new `coop nonce` round.");
+    println!("Next: once both participants have signed, run `mallowbtc coop combine`.");
+
+    Ok(())
+}
+
+fn coop_combine(args: &CoopCombineArgs) -> Result<(), Error> {
+    let session = CoopSession::load(&args.session.session)?;
+    let tx = session.combine()?;
+
+    println!("\nCooperative Spend Finalized");
+    println!("===========================");
+    println!();
+    println!("Txid: {}", tx.compute_txid());
+    println!("Virtual Size: {} vB", tx.vsize());
+    println!("\nRaw Transaction (hex):");
+    println!("----------------------");
+    println!("{}", serialize_hex(&tx));
+
+    publish(&tx, args.broadcast, &args.backend)
+}
+
 fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Create { giver_tpub, receiver_tpub, timelock } => {
-            if giver_tpub.is_none() || receiver_tpub.is_none() || timelock.is_none() {
+        Commands::Create(args) => {
+            if !args.gift.is_complete() {
                 println!("Welcome to Mallow Bitcoin Gift Creation!");
                 println!("=====================================");
                 show_create_requirements();
                 return;
             }
 
-            match create_gift(
-                &giver_tpub.unwrap(),
-                &receiver_tpub.unwrap(),
-                timelock.unwrap()
-            ) {
-                Ok(()) => {},
-                Err(e) => {
-                    println!("\nError creating gift: {}", e);
-                    println!("Please check your inputs and try again.");
-                }
+            if let Err(e) = create_gift(&args) {
+                println!("\nError creating gift: {}", e);
+                println!("Please check your inputs and try again.");
+            }
+        }
+        Commands::Fund(args) => {
+            if let Err(e) = fund_gift(&args) {
+                println!("\nError funding gift: {}", e);
+                println!("Please check your inputs and try again.");
+            }
+        }
+        Commands::Redeem(args) => {
+            if let Err(e) = redeem_gift(&args) {
+                println!("\nError redeeming gift: {}", e);
+                println!("Please check your inputs and try again.");
+            }
+        }
+        Commands::Batch(args) => {
+            if let Err(e) = batch_gifts(&args) {
+                println!("\nError funding batch: {}", e);
+                println!("Please check your inputs and try again.");
+            }
+        }
+        Commands::Vest(args) => {
+            if let Err(e) = vest_gift(&args) {
+                println!("\nError funding vesting schedule: {}", e);
+                println!("Please check your inputs and try again.");
+            }
+        }
+        Commands::Finalize(args) => {
+            if let Err(e) = finalize_psbt(&args) {
+                println!("\nError finalizing PSBT: {}", e);
+            }
+        }
+        Commands::Status(args) => {
+            if let Err(e) = gift_status(&args) {
+                println!("\nError checking gift status: {}", e);
+                println!("Please check your inputs and try again.");
+            }
+        }
+        Commands::List(args) => {
+            if let Err(e) = list_gifts(&args) {
+                println!("\nError listing gifts: {}", e);
+            }
+        }
+        Commands::Show(args) => {
+            if let Err(e) = show_gift(&args) {
+                println!("\nError showing gift: {}", e);
+            }
+        }
+        Commands::Forget(args) => {
+            if let Err(e) = forget_gift(&args) {
+                println!("\nError forgetting gift: {}", e);
+            }
+        }
+        Commands::Export(args) => {
+            if let Err(e) = export_gift(&args) {
+                println!("\nError exporting gift: {}", e);
+            }
+        }
+        Commands::Import(args) => {
+            if let Err(e) = import_gift(&args) {
+                println!("\nError importing gift: {}", e);
+                println!("Do not fund or rely on a gift whose backup fails to verify.");
+            }
+        }
+        Commands::Recover(args) => {
+            if let Err(e) = recover_gift(&args) {
+                println!("\nError recovering gift: {}", e);
+            }
+        }
+        Commands::Coop(command) => {
+            let result = match &command {
+                CoopCommands::Start(args) => coop_start(args),
+                CoopCommands::Nonce(args) => coop_nonce(args),
+                CoopCommands::Sign(args) => coop_sign(args),
+                CoopCommands::Combine(args) => coop_combine(args),
+            };
+            if let Err(e) = result {
+                println!("\nError in cooperative spend: {}", e);
             }
         }
     }
-}
\ No newline at end of file
+}
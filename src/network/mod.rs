@@ -0,0 +1,95 @@
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Network, NetworkKind};
+use miniscript::descriptor::DescriptorPublicKey;
+use crate::Error;
+
+/// Parses a network name as accepted on the command line.
+///
+/// # Arguments
+///
+/// * `name` - One of `mainnet`, `testnet`, `signet` or `regtest`
+///
+/// # Returns
+///
+/// The matching Network or an Error naming the accepted values.
+pub fn parse_network(name: &str) -> Result<Network, Error> {
+    match name.to_ascii_lowercase().as_str() {
+        "mainnet" | "bitcoin" => Ok(Network::Bitcoin),
+        "testnet" => Ok(Network::Testnet),
+        "signet" => Ok(Network::Signet),
+        "regtest" => Ok(Network::Regtest),
+        other => Err(Error::NetworkError(format!(
+            "Unknown network '{}' (expected mainnet, testnet, signet or regtest)",
+            other
+        ))),
+    }
+}
+
+/// Returns the user-facing name of a network, the inverse of `parse_network`.
+pub fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "mainnet",
+        Network::Testnet | Network::Testnet4 => "testnet",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+    }
+}
+
+/// Returns the bech32m human-readable part used for P2TR addresses.
+pub fn bech32_hrp(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "bc",
+        Network::Testnet | Network::Testnet4 | Network::Signet => "tb",
+        Network::Regtest => "bcrt",
+    }
+}
+
+/// Infers the network an address was encoded for from its prefix.
+///
+/// Testnet and signet share the `tb` prefix, so their addresses cannot be
+/// told apart; both resolve to testnet, which derives the same keys and
+/// scripts, so signet is never inferred. Callers that need to record signet
+/// must ask for it explicitly, as `recover --network signet` does.
+///
+/// # Arguments
+///
+/// * `address` - The address as parsed, before any network check
+///
+/// # Returns
+///
+/// The first network the address is valid for, or an Error if none match.
+pub fn network_for_address(address: &Address<NetworkUnchecked>) -> Result<Network, Error> {
+    [Network::Bitcoin, Network::Testnet, Network::Regtest]
+        .into_iter()
+        .find(|network| address.is_valid_for_network(*network))
+        .ok_or_else(|| Error::NetworkError(format!(
+            "Unknown network for address {}",
+            address.clone().assume_checked()
+        )))
+}
+
+/// Checks that an extended key was encoded for the given network.
+///
+/// xpubs are only accepted on mainnet and tpubs only on the test networks.
+/// Single keys carry no network and always pass. `role` names the key in the
+/// error message, e.g. "Giver".
+pub fn check_key_network(role: &str, key: &DescriptorPublicKey, network: Network) -> Result<(), Error> {
+    let kind = match key {
+        DescriptorPublicKey::XPub(xpub) => xpub.xkey.network,
+        DescriptorPublicKey::MultiXPub(xpub) => xpub.xkey.network,
+        DescriptorPublicKey::Single(_) => return Ok(()),
+    };
+
+    match (kind, NetworkKind::from(network)) {
+        (NetworkKind::Main, NetworkKind::Test) => Err(Error::NetworkError(format!(
+            "{} key is a mainnet xpub and cannot be used on {}",
+            role,
+            network_name(network)
+        ))),
+        (NetworkKind::Test, NetworkKind::Main) => Err(Error::NetworkError(format!(
+            "{} key is a testnet tpub and cannot be used on mainnet",
+            role
+        ))),
+        _ => Ok(()),
+    }
+}
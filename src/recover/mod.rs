@@ -0,0 +1,236 @@
+use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::taproot::{LeafVersion, TapLeafHash, TapNodeHash};
+use bitcoin::{Address, Network, ScriptBuf};
+use miniscript::{translate_hash_fail, Miniscript, Tap, TranslateErr, TranslatePk, Translator};
+use std::ops::Range;
+use crate::backend::DEFAULT_SCAN_INDICES;
+use crate::keys::GiftKeys;
+use crate::script::GiftScript;
+use crate::timelock::Timelock;
+use crate::Error;
+
+/// Where a recovery search looks for a gift's index, timelocks and layout.
+#[derive(Debug, Clone)]
+pub struct RecoverySearch {
+    /// Derivation indices to try.
+    pub indices: Range<u32>,
+    /// Receiver timelocks to try.
+    pub timelocks: Vec<Timelock>,
+    /// Clawback timelocks to try with each receiver timelock of the same
+    /// unit, for gifts with a giver clawback leaf.
+    pub clawbacks: Vec<Timelock>,
+}
+
+impl Default for RecoverySearch {
+    fn default() -> Self {
+        Self {
+            indices: 0..DEFAULT_SCAN_INDICES,
+            timelocks: default_timelocks(),
+            clawbacks: default_clawbacks(),
+        }
+    }
+}
+
+/// A gift found by a recovery search.
+pub struct RecoveredGift {
+    /// The gift's keys, derived at its index.
+    pub keys: GiftKeys,
+    pub script: GiftScript,
+}
+
+impl RecoverySearch {
+    /// Returns the number of index, timelock and layout combinations searched.
+    pub fn candidates(&self) -> usize {
+        let layouts: usize = self
+            .timelocks
+            .iter()
+            .map(|timelock| 1 + self.clawbacks.iter().filter(|clawback| fits_clawback(timelock, clawback)).count())
+            .sum();
+        self.indices.len() * layouts
+    }
+
+    /// Searches for the gift between two descriptors that pays `address`.
+    ///
+    /// Every index is tried with a lone receiver leaf first, the most common
+    /// layout, then with every pair of receiver and clawback leaves. Each
+    /// timelock's leaf policy is compiled once and only its key changes
+    /// between indices, so each layout costs one taproot tweak.
+    ///
+    /// # Arguments
+    ///
+    /// * `giver` - The giver's descriptor, ending in `/*`
+    /// * `receiver` - The receiver's descriptor, ending in `/*`
+    /// * `address` - The gift's address
+    /// * `network` - Network the address and keys belong to
+    ///
+    /// # Returns
+    ///
+    /// The gift's keys and script, `None` if no candidate produces the
+    /// address, or a RecoveryError if the address is not a taproot address.
+    pub fn run(
+        &self,
+        giver: &str,
+        receiver: &str,
+        address: &Address,
+        network: Network,
+    ) -> Result<Option<RecoveredGift>, Error> {
+        let target = address.script_pubkey();
+        if !target.is_p2tr() {
+            return Err(Error::RecoveryError(format!("{} is not a taproot address", address)));
+        }
+
+        let secp = Secp256k1::verification_only();
+        let descriptors = GiftKeys::from_descriptor_strings(giver, receiver)?.with_network(network)?;
+        let matches = |internal_key, root: TapNodeHash| ScriptBuf::new_p2tr(&secp, internal_key, Some(root)) == target;
+        let receiver_templates = templates(&self.timelocks)?;
+
+        // A lone receiver leaf at every index first
+        let mut searched = Vec::with_capacity(self.indices.len());
+        for index in self.indices.clone() {
+            let keys = descriptors.at_index(index)?;
+            let internal_key = keys.aggregate_musig2_key()?;
+            let receiver_leaves = leaf_hashes(&receiver_templates, keys.receiver_x_only_pub()?)?;
+
+            for (timelock, leaf) in self.timelocks.iter().zip(&receiver_leaves) {
+                if matches(internal_key, TapNodeHash::from(*leaf)) {
+                    return found(keys, GiftScript::from_timelock(*timelock), network, &target);
+                }
+            }
+            searched.push((keys, internal_key, receiver_leaves));
+        }
+
+        // Then a receiver and a clawback leaf side by side
+        let clawback_templates = templates(&self.clawbacks)?;
+        for (keys, internal_key, receiver_leaves) in searched {
+            let clawback_leaves = leaf_hashes(&clawback_templates, keys.giver_x_only_pub()?)?;
+
+            for (timelock, receiver_leaf) in self.timelocks.iter().zip(&receiver_leaves) {
+                for (clawback, clawback_leaf) in self.clawbacks.iter().zip(&clawback_leaves) {
+                    if !fits_clawback(timelock, clawback) {
+                        continue;
+                    }
+                    let root = TapNodeHash::from_node_hashes((*receiver_leaf).into(), (*clawback_leaf).into());
+                    if matches(internal_key, root) {
+                        let script = GiftScript::from_timelock(*timelock).with_clawback(*clawback);
+                        return found(keys, script, network, &target);
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Puts a party's key in place of the placeholder a leaf was compiled with.
+struct LeafKey(XOnlyPublicKey);
+
+impl Translator<String, XOnlyPublicKey, Error> for LeafKey {
+    fn pk(&mut self, _placeholder: &String) -> Result<XOnlyPublicKey, Error> {
+        Ok(self.0)
+    }
+
+    translate_hash_fail!(String, XOnlyPublicKey, Error);
+}
+
+/// Compiles the leaf of each timelock for a placeholder key.
+fn templates(timelocks: &[Timelock]) -> Result<Vec<Miniscript<String, Tap>>, Error> {
+    timelocks
+        .iter()
+        .map(|timelock| {
+            GiftScript::leaf_policy("key".to_string(), timelock)?
+                .compile::<Tap>()
+                .map_err(|e| Error::ScriptError(format!("Failed to compile gift policy: {}", e)))
+        })
+        .collect()
+}
+
+/// Returns the leaf hash of each template with `key` filled in.
+fn leaf_hashes(templates: &[Miniscript<String, Tap>], key: XOnlyPublicKey) -> Result<Vec<TapLeafHash>, Error> {
+    templates
+        .iter()
+        .map(|template| {
+            let leaf = template.translate_pk(&mut LeafKey(key)).map_err(|e| match e {
+                TranslateErr::TranslatorErr(e) => e,
+                TranslateErr::OuterError(e) => Error::ScriptError(format!("Failed to place leaf key: {}", e)),
+            })?;
+            Ok(TapLeafHash::from_script(&leaf.encode(), LeafVersion::TapScript))
+        })
+        .collect()
+}
+
+/// Whether `clawback` can follow `timelock` in a gift.
+fn fits_clawback(timelock: &Timelock, clawback: &Timelock) -> bool {
+    match (timelock.script_value(), clawback.script_value()) {
+        (Ok(timelock_value), Ok(clawback_value)) => timelock.is_same_unit(clawback) && clawback_value > timelock_value,
+        _ => false,
+    }
+}
+
+/// Rebuilds a matched gift the regular way and checks it pays `target`.
+fn found(keys: GiftKeys, script: GiftScript, network: Network, target: &ScriptBuf) -> Result<Option<RecoveredGift>, Error> {
+    let script = script.with_network(network);
+    if script.create_address(&keys)?.script_pubkey() != *target {
+        return Err(Error::RecoveryError(
+            "A candidate matched the output key but not the rebuilt gift address".to_string(),
+        ));
+    }
+
+    Ok(Some(RecoveredGift { keys, script }))
+}
+
+/// Receiver timelocks searched by default: every whole number of days and
+/// every multiple of 1000 in blocks, and every whole number of days as a
+/// duration.
+pub fn default_timelocks() -> Vec<Timelock> {
+    let blocks = (1..=Timelock::MAX_BLOCKS)
+        .filter(|blocks| blocks % 144 == 0 || blocks % 1000 == 0)
+        .map(Timelock::Blocks);
+    let days = (1..=Timelock::MAX_SECONDS / 86_400).map(|days| Timelock::Seconds(days * 86_400));
+    blocks.chain(days).collect()
+}
+
+/// Clawback timelocks searched by default: every 30 days and every quarter
+/// year in blocks, and every 30 days and one year as durations. Each is
+/// paired with every receiver timelock, so this list is kept short.
+pub fn default_clawbacks() -> Vec<Timelock> {
+    let blocks = (1..=Timelock::MAX_BLOCKS)
+        .filter(|blocks| blocks % 4320 == 0 || blocks % 13_140 == 0)
+        .map(Timelock::Blocks);
+    let days = (1..=Timelock::MAX_SECONDS / 86_400)
+        .filter(|days| days % 30 == 0 || *days == 365)
+        .map(|days| Timelock::Seconds(days * 86_400));
+    blocks.chain(days).collect()
+}
+
+/// Parses a comma-separated list of candidate timelocks.
+///
+/// Each entry is a timelock in the notation `--clawback` takes (`52560`,
+/// `180d`, `2030-01-01`, `height:900000`) or an inclusive range of blocks,
+/// `1000-2000`.
+pub fn parse_candidates(spec: &str) -> Result<Vec<Timelock>, Error> {
+    let mut candidates = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let range = entry
+            .split_once('-')
+            .and_then(|(start, end)| Some((start.parse::<u32>().ok()?, end.parse::<u32>().ok()?)));
+        match range {
+            Some((start, end)) if start <= end && end <= Timelock::MAX_BLOCKS => {
+                candidates.extend((start.max(1)..=end).map(Timelock::Blocks));
+            }
+            Some(_) => {
+                return Err(Error::RecoveryError(format!(
+                    "Invalid block range '{}': it must run upwards and end by {}",
+                    entry,
+                    Timelock::MAX_BLOCKS
+                )));
+            }
+            None => candidates.push(entry.parse()?),
+        }
+    }
+
+    if candidates.is_empty() {
+        return Err(Error::RecoveryError("No candidate timelocks given".to_string()));
+    }
+    Ok(candidates)
+}
@@ -0,0 +1,271 @@
+use bitcoin::Network;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::keys::GiftKeys;
+use crate::network::{network_name, parse_network};
+use crate::script::GiftScript;
+use crate::storage;
+use crate::timelock::Timelock;
+use crate::Error;
+
+/// Version of the registry file written by this build. Files with a newer
+/// version are refused rather than rewritten without the fields they add.
+pub const REGISTRY_VERSION: u32 = 1;
+
+/// How far a gift has got towards being spent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Redemption {
+    /// No spend is known.
+    #[default]
+    Unspent,
+    /// A spending transaction was built or seen in the mempool.
+    Pending { txid: String, path: String },
+    /// A spending transaction confirmed.
+    Spent { txid: String, path: String, height: u32 },
+}
+
+impl std::fmt::Display for Redemption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Redemption::Unspent => write!(f, "unspent"),
+            Redemption::Pending { txid, path } => write!(f, "pending via the {} in {}", path, txid),
+            Redemption::Spent { txid, path, height } => {
+                write!(f, "spent via the {} in {} (block {})", path, txid, height)
+            }
+        }
+    }
+}
+
+/// Everything needed to rebuild a gift, plus what is known of its funding
+/// and redemption.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GiftRecord {
+    pub id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Unix time the gift was recorded.
+    pub created_at: u64,
+    pub network: String,
+    /// The giver's descriptor as given, before derivation at `index`.
+    pub giver: String,
+    /// The receiver's descriptor as given, before derivation at `index`.
+    pub receiver: String,
+    pub index: u32,
+    pub timelock: Timelock,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clawback: Option<Timelock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf_depths: Option<Vec<u8>>,
+    pub address: String,
+    /// Watch-only `tr()` descriptor with checksum.
+    pub descriptor: String,
+    /// Txid of the funding transaction, once one is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_txid: Option<String>,
+    /// Gift output in the funding transaction, `txid:vout`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_outpoint: Option<String>,
+    #[serde(default)]
+    pub redemption: Redemption,
+}
+
+impl GiftRecord {
+    /// Records the gift made of two descriptors at `index` and `script`.
+    ///
+    /// # Arguments
+    ///
+    /// * `giver` - The giver's descriptor, as given to `create`
+    /// * `receiver` - The receiver's descriptor, as given to `create`
+    /// * `index` - The gift's derivation index
+    /// * `script` - The gift's script, with its network, clawback and leaf depths
+    ///
+    /// # Returns
+    ///
+    /// A record with id 0, which [`GiftRegistry::add`] replaces.
+    pub fn new(giver: &str, receiver: &str, index: u32, script: &GiftScript) -> Result<Self, Error> {
+        let keys = derive_keys(giver, receiver, index, script.network())?;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        Ok(Self {
+            id: 0,
+            label: None,
+            created_at,
+            network: network_name(script.network()).to_string(),
+            giver: giver.to_string(),
+            receiver: receiver.to_string(),
+            index,
+            timelock: script.timelock(),
+            clawback: script.clawback(),
+            leaf_depths: script.leaf_depths().map(<[u8]>::to_vec),
+            address: script.create_address(&keys)?.to_string(),
+            descriptor: script.descriptor(&keys)?.to_string(),
+            funding_txid: None,
+            funding_outpoint: None,
+            redemption: Redemption::Unspent,
+        })
+    }
+
+    /// Sets the gift's label.
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub fn network(&self) -> Result<Network, Error> {
+        parse_network(&self.network)
+    }
+
+    /// Rebuilds the gift's keys, derived at its index, and its script.
+    pub fn gift(&self) -> Result<(GiftKeys, GiftScript), Error> {
+        let network = self.network()?;
+        let keys = derive_keys(&self.giver, &self.receiver, self.index, network)?;
+        let script = gift_script(network, self.timelock, self.clawback, self.leaf_depths.as_deref());
+        Ok((keys, script))
+    }
+
+    /// Records a gift from the parameters a record stores, such as those
+    /// read back from a backup, rebuilding its script as `gift` does.
+    pub fn rebuild(
+        giver: &str,
+        receiver: &str,
+        index: u32,
+        network: Network,
+        timelock: Timelock,
+        clawback: Option<Timelock>,
+        leaf_depths: Option<&[u8]>,
+    ) -> Result<Self, Error> {
+        Self::new(giver, receiver, index, &gift_script(network, timelock, clawback, leaf_depths))
+    }
+
+    /// The label, or a placeholder for unlabelled gifts.
+    pub fn display_label(&self) -> &str {
+        self.label.as_deref().unwrap_or("(no label)")
+    }
+}
+
+fn gift_script(network: Network, timelock: Timelock, clawback: Option<Timelock>, leaf_depths: Option<&[u8]>) -> GiftScript {
+    let mut script = GiftScript::from_timelock(timelock).with_network(network);
+    if let Some(clawback) = clawback {
+        script = script.with_clawback(clawback);
+    }
+    if let Some(depths) = leaf_depths {
+        script = script.with_leaf_depths(depths.to_vec());
+    }
+    script
+}
+
+fn derive_keys(giver: &str, receiver: &str, index: u32, network: Network) -> Result<GiftKeys, Error> {
+    GiftKeys::from_descriptor_strings(giver, receiver)?
+        .at_index(index)?
+        .with_network(network)
+}
+
+/// The local store of created gifts, kept as a versioned JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftRegistry {
+    version: u32,
+    next_id: u32,
+    gifts: Vec<GiftRecord>,
+}
+
+impl Default for GiftRegistry {
+    fn default() -> Self {
+        Self {
+            version: REGISTRY_VERSION,
+            next_id: 1,
+            gifts: Vec::new(),
+        }
+    }
+}
+
+impl GiftRegistry {
+    /// Returns `mallowbtc/gifts.json` under the user's data directory
+    /// (`$XDG_DATA_HOME`, or `~/.local/share` on Linux).
+    pub fn default_path() -> Result<PathBuf, Error> {
+        dirs::data_dir()
+            .map(|dir| dir.join("mallowbtc").join("gifts.json"))
+            .ok_or_else(|| Error::RegistryError("No data directory found; pass --registry".to_string()))
+    }
+
+    /// Reads the registry at `path`, or starts an empty one if there is no file yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::RegistryError(format!("Failed to read {}: {}", path.display(), e))),
+        };
+
+        let registry: Self = serde_json::from_str(&text)
+            .map_err(|e| Error::RegistryError(format!("Invalid registry {}: {}", path.display(), e)))?;
+        if registry.version > REGISTRY_VERSION {
+            return Err(Error::RegistryError(format!(
+                "{} is registry version {}, newer than the version {} this build understands",
+                path.display(),
+                registry.version,
+                REGISTRY_VERSION
+            )));
+        }
+
+        Ok(registry)
+    }
+
+    /// Writes the registry, readable only by its owner where supported.
+    ///
+    /// The file is replaced through `storage::write_owner_only`, so an
+    /// interrupted write leaves the previous registry intact.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| Error::RegistryError(format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::RegistryError(format!("Failed to encode registry: {}", e)))?;
+        storage::write_owner_only(path, text.as_bytes())
+            .map_err(|e| Error::RegistryError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// Adds a gift and returns its id.
+    ///
+    /// A gift already recorded under the same address keeps its id and
+    /// state; only a new label replaces the old one.
+    pub fn add(&mut self, mut record: GiftRecord) -> u32 {
+        if let Some(existing) = self.gifts.iter_mut().find(|gift| gift.address == record.address) {
+            if record.label.is_some() {
+                existing.label = record.label;
+            }
+            return existing.id;
+        }
+
+        record.id = self.next_id;
+        self.next_id += 1;
+        self.gifts.push(record);
+        self.next_id - 1
+    }
+
+    /// Every recorded gift, oldest first.
+    pub fn gifts(&self) -> &[GiftRecord] {
+        &self.gifts
+    }
+
+    pub fn get(&self, id: u32) -> Option<&GiftRecord> {
+        self.gifts.iter().find(|gift| gift.id == id)
+    }
+
+    /// Finds the gift paying to `address`, to record its funding or spend.
+    pub fn find_by_address_mut(&mut self, address: &str) -> Option<&mut GiftRecord> {
+        self.gifts.iter_mut().find(|gift| gift.address == address)
+    }
+
+    /// Removes a gift and returns it. Ids are never reused.
+    pub fn forget(&mut self, id: u32) -> Option<GiftRecord> {
+        let position = self.gifts.iter().position(|gift| gift.id == id)?;
+        Some(self.gifts.remove(position))
+    }
+}
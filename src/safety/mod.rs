@@ -0,0 +1,144 @@
+use crate::keys::GiftKeys;
+use crate::script::GiftScript;
+use crate::timelock::Timelock;
+use crate::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Shortest mainnet timelock accepted without complaint (about one day).
+pub const MIN_MAINNET_TIMELOCK_BLOCKS: u32 = 144;
+
+/// Shortest mainnet relative duration accepted without complaint (one day).
+pub const MIN_MAINNET_TIMELOCK_SECONDS: u32 = 86_400;
+
+/// Longest block timelock BIP-68 can express (about 15 months).
+pub const MAX_MAINNET_TIMELOCK_BLOCKS: u32 = Timelock::MAX_BLOCKS;
+
+/// Mainnet height mined in April 2024; lower unlock heights have already passed.
+pub const MIN_MAINNET_UNLOCK_HEIGHT: u32 = 840_000;
+
+/// Timestamp of block `MIN_MAINNET_UNLOCK_HEIGHT`, used to estimate the tip.
+const MIN_MAINNET_UNLOCK_HEIGHT_TIME: u64 = 1_713_571_767;
+
+/// Furthest ahead an absolute mainnet lock may unlock, in years.
+pub const MAX_MAINNET_UNLOCK_YEARS: u32 = 10;
+
+/// Blocks mined in a year at the target spacing of ten minutes.
+const BLOCKS_PER_YEAR: u32 = 52_560;
+
+/// Seconds in a year, counting a quarter leap day.
+const SECONDS_PER_YEAR: u64 = 31_557_600;
+
+/// Phrase the user must type before a mainnet gift is created.
+pub const CONFIRMATION_PHRASE: &str = "I UNDERSTAND";
+
+/// Runs the automatic checks a mainnet gift must pass before confirmation.
+///
+/// A mistyped or reused key on mainnet can lock real funds with nobody able
+/// to spend them, so this refuses anything that looks like an input mistake:
+///
+/// * both keys must carry a `[fingerprint/path]` origin, so the signing
+///   wallets can be identified;
+/// * giver and receiver keys must differ at the gift's index;
+/// * a relative timelock must lie between `MIN_MAINNET_TIMELOCK_BLOCKS` and
+///   `MAX_MAINNET_TIMELOCK_BLOCKS`, or last at least
+///   `MIN_MAINNET_TIMELOCK_SECONDS`;
+/// * an absolute timelock must not already have passed, nor unlock more
+///   than `MAX_MAINNET_UNLOCK_YEARS` from now.
+///
+/// The timelock checks apply to the receiver leaf and to any clawback leaf.
+///
+/// # Returns
+///
+/// Ok if every check passes, otherwise a SafetyError naming the first failure.
+pub fn check_mainnet_gift(keys: &GiftKeys, script: &GiftScript) -> Result<(), Error> {
+    if !keys.giver.has_origin() {
+        return Err(Error::SafetyError(
+            "Giver key has no [fingerprint/path] origin".to_string(),
+        ));
+    }
+
+    if !keys.receiver.has_origin() {
+        return Err(Error::SafetyError(
+            "Receiver key has no [fingerprint/path] origin".to_string(),
+        ));
+    }
+
+    if keys.giver_pub()? == keys.receiver_pub()? {
+        return Err(Error::SafetyError(
+            "Giver and receiver keys are identical".to_string(),
+        ));
+    }
+
+    check_mainnet_timelock(&script.timelock())?;
+    if let Some(clawback) = script.clawback() {
+        check_mainnet_timelock(&clawback).map_err(|e| match e {
+            Error::SafetyError(message) => Error::SafetyError(format!("Clawback leaf: {}", message)),
+            e => e,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Checks one leaf's timelock against the mainnet ranges.
+fn check_mainnet_timelock(timelock: &Timelock) -> Result<(), Error> {
+    match *timelock {
+        Timelock::Blocks(blocks) if !(MIN_MAINNET_TIMELOCK_BLOCKS..=MAX_MAINNET_TIMELOCK_BLOCKS).contains(&blocks) => {
+            Err(Error::SafetyError(format!(
+                "Timelock of {} blocks is outside the accepted range {}-{}",
+                blocks, MIN_MAINNET_TIMELOCK_BLOCKS, MAX_MAINNET_TIMELOCK_BLOCKS
+            )))
+        }
+        Timelock::Seconds(seconds) if seconds < MIN_MAINNET_TIMELOCK_SECONDS => Err(Error::SafetyError(format!(
+            "Timelock of {} is shorter than the accepted minimum of one day",
+            timelock
+        ))),
+        Timelock::UnlockHeight(height) if height < MIN_MAINNET_UNLOCK_HEIGHT => Err(Error::SafetyError(format!(
+            "Unlock height {} has already passed on mainnet",
+            height
+        ))),
+        Timelock::UnlockHeight(height) if height > max_mainnet_unlock_height() => Err(Error::SafetyError(format!(
+            "Unlock height {} is more than {} years past the current height of about {}",
+            height,
+            MAX_MAINNET_UNLOCK_YEARS,
+            estimated_mainnet_height()
+        ))),
+        Timelock::UnlockTime(timestamp) if u64::from(timestamp) <= now_unix() => Err(Error::SafetyError(format!(
+            "Unlock date {} is in the past",
+            timelock
+        ))),
+        Timelock::UnlockTime(timestamp)
+            if u64::from(timestamp) > now_unix() + u64::from(MAX_MAINNET_UNLOCK_YEARS) * SECONDS_PER_YEAR =>
+        {
+            Err(Error::SafetyError(format!(
+                "Unlock date {} is more than {} years away",
+                timelock, MAX_MAINNET_UNLOCK_YEARS
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Estimates the mainnet tip from the clock at one block per ten minutes.
+fn estimated_mainnet_height() -> u32 {
+    let elapsed = now_unix().saturating_sub(MIN_MAINNET_UNLOCK_HEIGHT_TIME);
+    MIN_MAINNET_UNLOCK_HEIGHT.saturating_add(u32::try_from(elapsed / 600).unwrap_or(u32::MAX))
+}
+
+fn max_mainnet_unlock_height() -> u32 {
+    estimated_mainnet_height().saturating_add(MAX_MAINNET_UNLOCK_YEARS * BLOCKS_PER_YEAR)
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Checks a typed confirmation against `CONFIRMATION_PHRASE`.
+///
+/// Surrounding whitespace is ignored; case is not.
+pub fn confirmation_matches(input: &str) -> bool {
+    input.trim() == CONFIRMATION_PHRASE
+}
@@ -1,55 +1,397 @@
-use bitcoin::{ScriptBuf, XOnlyPublicKey};
+use bitcoin::{ScriptBuf, VarInt, Weight, XOnlyPublicKey};
 use bitcoin::secp256k1::Secp256k1;
-use bitcoin::taproot::TaprootBuilder;
+use bitcoin::taproot::{ControlBlock, LeafVersion, TaprootBuilder, TaprootSpendInfo};
 use bitcoin::Address;
 use bitcoin::Network;
+use miniscript::descriptor::{DescriptorPublicKey, SinglePub, SinglePubKey, TapTree};
+use miniscript::policy::Concrete;
+use miniscript::{AbsLockTime, Descriptor, Miniscript, MiniscriptKey, RelLockTime, Tap};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use crate::keys::GiftKeys;
+use crate::network::network_name;
+use crate::timelock::Timelock;
 use crate::Error;
 
+/// Who can spend through a tapscript leaf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafRole {
+    /// The receiver alone, once the gift timelock has passed.
+    Receiver,
+    /// The giver alone, once the (longer) clawback timelock has passed.
+    Clawback,
+}
+
+impl std::fmt::Display for LeafRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeafRole::Receiver => write!(f, "receiver"),
+            LeafRole::Clawback => write!(f, "giver clawback"),
+        }
+    }
+}
+
+/// How a gift output can be spent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendPath {
+    /// The MuSig2 keypath, signed by giver and receiver together.
+    KeyPath,
+    /// A tapscript leaf, signed by the leaf's key alone.
+    Leaf(LeafRole),
+}
+
+impl std::fmt::Display for SpendPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpendPath::KeyPath => write!(f, "cooperative keypath"),
+            SpendPath::Leaf(role) => write!(f, "{} leaf", role),
+        }
+    }
+}
+
+/// One tapscript leaf of the gift's taproot tree.
+#[derive(Debug, Clone)]
+pub struct GiftLeaf {
+    pub role: LeafRole,
+    pub timelock: Timelock,
+    pub depth: u8,
+    pub script: ScriptBuf,
+}
+
+impl GiftLeaf {
+    /// Returns the control block proving this leaf's inclusion in the tree.
+    pub fn control_block(&self, spend_info: &TaprootSpendInfo) -> Result<ControlBlock, Error> {
+        spend_info
+            .control_block(&(self.script.clone(), LeafVersion::TapScript))
+            .ok_or_else(|| Error::ScriptError(format!("No control block for the {} leaf", self.role)))
+    }
+
+    /// Describes the leaf with its control block in `spend_info`.
+    pub fn describe(&self, spend_info: &TaprootSpendInfo) -> Result<LeafDescription, Error> {
+        Ok(LeafDescription {
+            role: self.role.to_string(),
+            depth: self.depth,
+            timelock: self.timelock,
+            script: hex::encode(self.script.as_bytes()),
+            control_block: hex::encode(self.control_block(spend_info)?.serialize()),
+        })
+    }
+}
+
+/// A leaf as batch reports and backups write it: what a receiver needs to
+/// spend through it without rebuilding the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafDescription {
+    pub role: String,
+    pub depth: u8,
+    pub timelock: Timelock,
+    /// Leaf script, hex.
+    pub script: String,
+    /// Control block, hex.
+    pub control_block: String,
+}
+
 pub struct GiftScript {
-    timelock_blocks: u32,
+    timelock: Timelock,
+    network: Network,
+    clawback: Option<Timelock>,
+    leaf_depths: Option<Vec<u8>>,
 }
 
 impl GiftScript {
+    /// Creates a gift script with a relative timelock of `timelock_blocks`.
+    ///
+    /// The value is checked against BIP-68 when the script is built.
     pub fn new(timelock_blocks: u32) -> Self {
-        GiftScript { timelock_blocks }
+        Self::from_timelock(Timelock::Blocks(timelock_blocks))
+    }
+
+    /// Creates a gift script with any supported timelock.
+    pub fn from_timelock(timelock: Timelock) -> Self {
+        GiftScript {
+            timelock,
+            network: Network::Regtest,
+            clawback: None,
+            leaf_depths: None,
+        }
+    }
+
+    pub fn timelock(&self) -> Timelock {
+        self.timelock
+    }
+
+    /// Sets the network addresses are created for (regtest by default).
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Adds a leaf that lets the giver recover unclaimed funds after `timelock`.
+    ///
+    /// The clawback must use the same unit as the gift timelock and expire
+    /// strictly later, so the receiver always gets the first chance to claim.
+    pub fn with_clawback(mut self, timelock: Timelock) -> Self {
+        self.clawback = Some(timelock);
+        self
+    }
+
+    pub fn clawback(&self) -> Option<Timelock> {
+        self.clawback
+    }
+
+    /// Sets the depth of each leaf in the taproot tree, receiver leaf first.
+    ///
+    /// Shallower leaves have shorter control blocks and are cheaper to spend.
+    /// The depths must describe a complete tree; by default a lone leaf sits
+    /// at depth 0 and two leaves at depth 1.
+    pub fn with_leaf_depths(mut self, depths: Vec<u8>) -> Self {
+        self.leaf_depths = Some(depths);
+        self
+    }
+
+    /// Returns the leaf depths set with `with_leaf_depths`, if any.
+    pub fn leaf_depths(&self) -> Option<&[u8]> {
+        self.leaf_depths.as_deref()
+    }
+
+    /// Returns the spending policy for `key` after `timelock`:
+    /// `and(pk(key),older(n))`, or `after(n)` for absolute timelocks.
+    pub fn leaf_policy<Pk: MiniscriptKey>(key: Pk, timelock: &Timelock) -> Result<Concrete<Pk>, Error> {
+        let lock = if timelock.is_absolute() {
+            AbsLockTime::from_consensus(timelock.to_lock_time()?.to_consensus_u32())
+                .map(Concrete::After)
+                .map_err(|e| Error::ScriptError(format!("Invalid absolute timelock: {}", e)))?
+        } else {
+            RelLockTime::try_from(timelock.to_sequence()?)
+                .map(Concrete::Older)
+                .map_err(|e| Error::ScriptError(format!("Invalid relative timelock: {}", e)))?
+        };
+
+        Ok(Concrete::And(vec![Arc::new(Concrete::Key(key)), Arc::new(lock)]))
+    }
+
+    /// Returns the receiver's spending policy for this gift.
+    pub fn receiver_policy<Pk: MiniscriptKey>(&self, receiver: Pk) -> Result<Concrete<Pk>, Error> {
+        Self::leaf_policy(receiver, &self.timelock)
+    }
+
+    /// Compiles a leaf policy into a tapscript miniscript.
+    fn compile_leaf<Pk: MiniscriptKey>(key: Pk, timelock: &Timelock) -> Result<Miniscript<Pk, Tap>, Error> {
+        Self::leaf_policy(key, timelock)?
+            .compile::<Tap>()
+            .map_err(|e| Error::ScriptError(format!("Failed to compile gift policy: {}", e)))
     }
 
     pub fn create_timelock_script(&self, receiver_key: XOnlyPublicKey) -> Result<ScriptBuf, Error> {
-        // Create a simple script that checks receiver's signature and timelock
-        let script = bitcoin::script::Builder::new()
-            .push_slice(&receiver_key.serialize())
-            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIGVERIFY)
-            .push_int(self.timelock_blocks as i64)
-            .push_opcode(bitcoin::opcodes::all::OP_CSV)
-            .into_script();
+        // Compiles to <receiver> OP_CHECKSIGVERIFY <n> OP_CSV (or OP_CLTV)
+        Ok(Self::compile_leaf(receiver_key, &self.timelock)?.encode())
+    }
+
+    /// Returns the roles and timelocks of the leaves, in tree order.
+    fn leaf_specs(&self) -> Result<Vec<(LeafRole, Timelock)>, Error> {
+        let mut specs = vec![(LeafRole::Receiver, self.timelock)];
 
-        Ok(script.into())
+        if let Some(clawback) = self.clawback {
+            if !self.timelock.is_same_unit(&clawback) {
+                return Err(Error::TimelockError(format!(
+                    "Clawback timelock ({}) must use the same unit as the gift timelock ({})",
+                    clawback, self.timelock
+                )));
+            }
+            if clawback.script_value()? <= self.timelock.script_value()? {
+                return Err(Error::TimelockError(format!(
+                    "Clawback timelock ({}) must expire after the gift timelock ({})",
+                    clawback, self.timelock
+                )));
+            }
+            specs.push((LeafRole::Clawback, clawback));
+        }
+
+        Ok(specs)
     }
 
-    pub fn create_taproot_tree(&self, keys: &GiftKeys) -> Result<(ScriptBuf, bitcoin::taproot::TaprootSpendInfo), Error> {
-        // Get the giver's key for the keypath
-        let internal_key = keys.giver_x_only_pub()?;
-        
-        // Get the receiver's x-only public key
-        let receiver_key = keys.receiver_x_only_pub()?;
-        
-        // Create the timelock script for the script path
-        let timelock_script = self.create_timelock_script(receiver_key)?;
+    /// Returns the leaf depths, checking one is given per leaf.
+    fn depths(&self, leaf_count: usize) -> Result<Vec<u8>, Error> {
+        match &self.leaf_depths {
+            Some(depths) if depths.len() != leaf_count => Err(Error::ScriptError(format!(
+                "Got {} leaf depths for {} leaves",
+                depths.len(),
+                leaf_count
+            ))),
+            Some(depths) => Ok(depths.clone()),
+            None if leaf_count == 1 => Ok(vec![0]),
+            None => Ok(vec![1; leaf_count]),
+        }
+    }
+
+    /// Returns every tapscript leaf of the gift, receiver leaf first.
+    pub fn leaves(&self, keys: &GiftKeys) -> Result<Vec<GiftLeaf>, Error> {
+        let specs = self.leaf_specs()?;
+        let depths = self.depths(specs.len())?;
+
+        specs
+            .into_iter()
+            .zip(depths)
+            .map(|((role, timelock), depth)| {
+                let key = match role {
+                    LeafRole::Receiver => keys.receiver_x_only_pub()?,
+                    LeafRole::Clawback => keys.giver_x_only_pub()?,
+                };
+                let script = Self::compile_leaf(key, &timelock)?.encode();
+                Ok(GiftLeaf { role, timelock, depth, script })
+            })
+            .collect()
+    }
+
+    /// Describes every leaf with its control block, receiver leaf first.
+    pub fn describe_leaves(&self, keys: &GiftKeys) -> Result<Vec<LeafDescription>, Error> {
+        let (_, spend_info) = self.create_taproot_tree(keys)?;
+        self.leaves(keys)?.iter().map(|leaf| leaf.describe(&spend_info)).collect()
+    }
+
+    /// Returns the gift as a `tr()` output descriptor.
+    ///
+    /// The internal key is the MuSig2 aggregate as a bare x-only key, and each
+    /// leaf is `and_v(v:pk(key),older(n))` with the party's key at the gift's
+    /// index and origin, so the descriptor can be imported as a watch-only
+    /// wallet. Its `Display` includes the checksum.
+    pub fn descriptor(&self, keys: &GiftKeys) -> Result<Descriptor<DescriptorPublicKey>, Error> {
+        self.check_network(keys)?;
+
+        let internal_key = DescriptorPublicKey::Single(SinglePub {
+            origin: None,
+            key: SinglePubKey::XOnly(keys.aggregate_musig2_key()?),
+        });
+
+        let specs = self.leaf_specs()?;
+        let depths = self.depths(specs.len())?;
+        let mut leaves = Vec::with_capacity(specs.len());
+        for ((role, timelock), depth) in specs.into_iter().zip(depths) {
+            let key = match role {
+                LeafRole::Receiver => keys.receiver_descriptor_key()?,
+                LeafRole::Clawback => keys.giver_descriptor_key()?,
+            };
+            leaves.push((depth, TapTree::Leaf(Arc::new(Self::compile_leaf(key, &timelock)?))));
+        }
+        let tree = combine_at_depths(leaves, TapTree::combine)?;
+
+        Descriptor::new_tr(internal_key, Some(tree))
+            .map_err(|e| Error::ScriptError(format!("Failed to build tr() descriptor: {}", e)))
+    }
+
+    /// Returns the largest witness that satisfies each spending path, keypath
+    /// first, then every leaf in tree order.
+    ///
+    /// Weights come from miniscript's `max_weight_to_satisfy`, which assumes
+    /// 65-byte signatures with an explicit sighash byte, and include the
+    /// leaf script and a control block for the leaf's depth. They are upper
+    /// bounds on what the spending input adds to a transaction's weight.
+    ///
+    /// # Returns
+    ///
+    /// Each path with its satisfaction weight, or a ScriptError if a leaf
+    /// cannot be satisfied.
+    pub fn satisfaction_weights(&self, keys: &GiftKeys) -> Result<Vec<(SpendPath, Weight)>, Error> {
+        let internal_key = keys.aggregate_musig2_key()?;
+        let max_weight = |tree: Option<TapTree<XOnlyPublicKey>>| {
+            Descriptor::new_tr(internal_key, tree)
+                .and_then(|descriptor| descriptor.max_weight_to_satisfy())
+                .map_err(|e| Error::ScriptError(format!("Failed to compute satisfaction weight: {}", e)))
+        };
+
+        let mut weights = vec![(SpendPath::KeyPath, max_weight(None)?)];
+        for leaf in self.leaves(keys)? {
+            let key = match leaf.role {
+                LeafRole::Receiver => keys.receiver_x_only_pub()?,
+                LeafRole::Clawback => keys.giver_x_only_pub()?,
+            };
+            let miniscript = Self::compile_leaf(key, &leaf.timelock)?;
+
+            // A lone leaf has a 33-byte control block; each level of depth
+            // adds a 32-byte hash
+            let lone = max_weight(Some(TapTree::Leaf(Arc::new(miniscript))))?;
+            let control_block = 33 + 32 * usize::from(leaf.depth);
+            let extra = VarInt::from(control_block).size() + control_block - (VarInt::from(33usize).size() + 33);
+            weights.push((SpendPath::Leaf(leaf.role), lone + Weight::from_wu(extra as u64)));
+        }
+
+        Ok(weights)
+    }
+
+    fn check_network(&self, keys: &GiftKeys) -> Result<(), Error> {
+        if keys.network != self.network {
+            return Err(Error::NetworkError(format!(
+                "Keys are for {} but the script is for {}",
+                network_name(keys.network),
+                network_name(self.network)
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn create_taproot_tree(&self, keys: &GiftKeys) -> Result<(ScriptBuf, TaprootSpendInfo), Error> {
+        self.check_network(keys)?;
+
+        // MuSig2 aggregate of giver and receiver for the cooperative keypath
+        let internal_key = keys.aggregate_musig2_key()?;
 
         // Initialize secp context
         let secp = Secp256k1::new();
 
-        // Build taproot tree with our script
-        let spend_info = TaprootBuilder::new()
-            .add_leaf(0, timelock_script.clone())
-            .map_err(|e| Error::ScriptError(format!("Failed to add script to tree: {:?}", e)))?
+        // Build taproot tree with every leaf at its depth
+        let mut builder = TaprootBuilder::new();
+        for leaf in self.leaves(keys)? {
+            builder = builder
+                .add_leaf(leaf.depth, leaf.script)
+                .map_err(|e| Error::ScriptError(format!("Failed to add script to tree: {:?}", e)))?;
+        }
+        let spend_info = builder
             .finalize(&secp, internal_key)
-            .map_err(|e| Error::ScriptError(format!("Failed to finalize taproot: {:?}", e)))?;
+            .map_err(|_| Error::ScriptError("Leaf depths do not form a complete taproot tree".to_string()))?;
 
         // Convert to P2TR address
-        let address = Address::p2tr(&secp, internal_key, spend_info.merkle_root(), Network::Regtest);
+        let address = Address::p2tr(&secp, internal_key, spend_info.merkle_root(), self.network);
         
         Ok((address.script_pubkey(), spend_info))
     }
-}
\ No newline at end of file
+
+    /// Returns the P2TR deposit address for the gift on this script's network.
+    pub fn create_address(&self, keys: &GiftKeys) -> Result<Address, Error> {
+        let (script_pubkey, _) = self.create_taproot_tree(keys)?;
+
+        Address::from_script(&script_pubkey, self.network)
+            .map_err(|e| Error::ScriptError(format!("Failed to create address: {}", e)))
+    }
+}
+
+/// Assembles nodes listed in depth-first order into one tree, merging
+/// siblings the same way `TaprootBuilder` does.
+fn combine_at_depths<T>(nodes: Vec<(u8, T)>, combine: impl Fn(T, T) -> T) -> Result<T, Error> {
+    let invalid = || Error::ScriptError("Leaf depths do not form a complete taproot tree".to_string());
+    let mut stack: Vec<(u8, T)> = Vec::new();
+
+    for (depth, node) in nodes {
+        let mut current = (depth, node);
+        while let Some((top_depth, _)) = stack.last() {
+            if *top_depth != current.0 {
+                break;
+            }
+            if current.0 == 0 {
+                return Err(invalid());
+            }
+            let (_, left) = stack.pop().expect("checked above");
+            current = (current.0 - 1, combine(left, current.1));
+        }
+        stack.push(current);
+    }
+
+    match (stack.pop(), stack.is_empty()) {
+        (Some((0, tree)), true) => Ok(tree),
+        _ => Err(invalid()),
+    }
+}
@@ -0,0 +1,241 @@
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::{absolute, relative, Address, Amount, Network, OutPoint, ScriptBuf, Txid, Witness};
+use miniscript::descriptor::{Descriptor, DescriptorPublicKey};
+use miniscript::Terminal;
+use std::str::FromStr;
+use crate::backend::ChainBackend;
+use crate::keys::GiftKeys;
+use crate::script::{GiftScript, LeafRole, SpendPath};
+use crate::timelock::Timelock;
+use crate::Error;
+
+/// A tapscript leaf of a watched gift.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedLeaf {
+    pub role: LeafRole,
+    pub script: ScriptBuf,
+    pub timelock: Timelock,
+}
+
+/// What it takes to follow a gift on chain: its address and its leaves.
+///
+/// It needs no giver key, so the watch-only descriptor that `create` and
+/// `batch` print is enough to build one.
+#[derive(Debug, Clone)]
+pub struct WatchedGift {
+    pub address: Address,
+    /// Leaves in tree order, receiver first.
+    pub leaves: Vec<WatchedLeaf>,
+}
+
+impl WatchedGift {
+    /// Watches the gift made of `keys` and `script`.
+    pub fn new(keys: &GiftKeys, script: &GiftScript) -> Result<Self, Error> {
+        Self::from_descriptor(&script.descriptor(keys)?, 0, script.network())
+    }
+
+    /// Watches the gift described by a `tr()` descriptor string.
+    pub fn from_descriptor_str(descriptor: &str, index: u32, network: Network) -> Result<Self, Error> {
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(descriptor.trim())
+            .map_err(|e| Error::ScriptError(format!("Invalid gift descriptor: {}", e)))?;
+        Self::from_descriptor(&descriptor, index, network)
+    }
+
+    /// Watches the gift described by a `tr()` descriptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `descriptor` - The gift's descriptor, with the receiver leaf first
+    /// * `index` - Index substituted for any `/*` in the descriptor's keys
+    /// * `network` - Network of the gift's address
+    ///
+    /// # Returns
+    ///
+    /// The watched gift, or a ScriptError if the descriptor is not a
+    /// taproot descriptor with one or two timelocked leaves.
+    pub fn from_descriptor(
+        descriptor: &Descriptor<DescriptorPublicKey>,
+        index: u32,
+        network: Network,
+    ) -> Result<Self, Error> {
+        let secp = Secp256k1::verification_only();
+        let derived = descriptor
+            .at_derivation_index(index)
+            .map_err(|e| Error::ScriptError(format!("Failed to derive descriptor at index {}: {}", index, e)))?
+            .derived_descriptor(&secp)
+            .map_err(|e| Error::ScriptError(format!("Failed to derive descriptor keys: {}", e)))?;
+        let address = derived
+            .address(network)
+            .map_err(|e| Error::ScriptError(format!("Failed to derive gift address: {}", e)))?;
+
+        let Descriptor::Tr(tr) = &derived else {
+            return Err(Error::ScriptError("A gift descriptor must be a tr() descriptor".to_string()));
+        };
+        let roles = [LeafRole::Receiver, LeafRole::Clawback];
+        let scripts: Vec<_> = tr.iter_scripts().collect();
+        if scripts.is_empty() || scripts.len() > roles.len() {
+            return Err(Error::ScriptError(format!(
+                "A gift descriptor has one or two leaves, this one has {}",
+                scripts.len()
+            )));
+        }
+
+        let leaves = scripts
+            .into_iter()
+            .zip(roles)
+            .map(|((_, miniscript), role)| {
+                let timelock = miniscript
+                    .iter()
+                    .find_map(|node| match &node.node {
+                        Terminal::Older(lock) => relative_timelock(lock.to_consensus_u32()),
+                        Terminal::After(lock) => absolute_timelock(lock.to_consensus_u32()),
+                        _ => None,
+                    })
+                    .ok_or_else(|| Error::ScriptError(format!("The {} leaf has no timelock", role)))?;
+                Ok(WatchedLeaf {
+                    role,
+                    script: miniscript.encode(),
+                    timelock,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self { address, leaves })
+    }
+
+    /// Tells which path a witness spending the gift used, or `None` if it
+    /// matches none of them.
+    pub fn spend_path(&self, witness: &Witness) -> Option<SpendPath> {
+        if witness.len() == 1 {
+            return Some(SpendPath::KeyPath);
+        }
+        let leaf_script = witness.taproot_leaf_script()?;
+        self.leaves
+            .iter()
+            .find(|leaf| leaf.script.as_script() == leaf_script.script)
+            .map(|leaf| SpendPath::Leaf(leaf.role))
+    }
+}
+
+fn relative_timelock(consensus: u32) -> Option<Timelock> {
+    match relative::LockTime::from_consensus(consensus).ok()? {
+        relative::LockTime::Blocks(blocks) => Some(Timelock::Blocks(u32::from(blocks.value()))),
+        relative::LockTime::Time(time) => Some(Timelock::Seconds(u32::from(time.value()) * Timelock::SECONDS_PER_UNIT)),
+    }
+}
+
+fn absolute_timelock(consensus: u32) -> Option<Timelock> {
+    match absolute::LockTime::from_consensus(consensus) {
+        absolute::LockTime::Blocks(height) => Some(Timelock::UnlockHeight(height.to_consensus_u32())),
+        absolute::LockTime::Seconds(time) => Some(Timelock::UnlockTime(time.to_consensus_u32())),
+    }
+}
+
+/// The transaction that spent a gift output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spend {
+    pub txid: Txid,
+    /// Block the spend confirmed in; `None` while it is in the mempool.
+    pub confirmation_height: Option<u32>,
+    /// Path the spend used, `None` if its witness matches none of the gift's.
+    pub path: Option<SpendPath>,
+}
+
+/// One output that paid a gift.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputStatus {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    /// Block the output confirmed in; `None` while it is in the mempool.
+    pub confirmation_height: Option<u32>,
+    /// Blocks until each leaf can be spent, in leaf order; `None` where the
+    /// count depends on time or on a confirmation that hasn't happened.
+    pub blocks_remaining: Vec<(LeafRole, Option<u32>)>,
+    /// How the output was spent, `None` while it is unspent.
+    pub spent: Option<Spend>,
+}
+
+/// A gift's state on chain.
+#[derive(Debug, Clone)]
+pub struct GiftStatus {
+    pub tip_height: u32,
+    pub outputs: Vec<OutputStatus>,
+    /// Whether spent outputs are included. Backends without a script index
+    /// only report unspent outputs.
+    pub complete_history: bool,
+}
+
+impl GiftStatus {
+    /// Looks a gift up through a chain backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Where to look
+    /// * `gift` - The gift to report on
+    ///
+    /// # Returns
+    ///
+    /// Every output that paid the gift address, in the order the backend
+    /// lists them, with its confirmation, unlock countdown and spend.
+    pub fn fetch(backend: &dyn ChainBackend, gift: &WatchedGift) -> Result<Self, Error> {
+        let tip_height = backend.tip_height()?;
+        let script_pubkey = gift.address.script_pubkey();
+        let countdown = |confirmation_height: Option<u32>| {
+            gift.leaves
+                .iter()
+                .map(|leaf| (leaf.role, leaf.timelock.blocks_remaining(confirmation_height, tip_height)))
+                .collect::<Vec<_>>()
+        };
+
+        let Some(history) = backend.script_history(&script_pubkey)? else {
+            let outputs = backend
+                .script_utxos(&script_pubkey)?
+                .into_iter()
+                .map(|utxo| OutputStatus {
+                    outpoint: utxo.outpoint,
+                    amount: utxo.txout.value,
+                    confirmation_height: utxo.confirmation_height,
+                    blocks_remaining: countdown(utxo.confirmation_height),
+                    spent: None,
+                })
+                .collect();
+            return Ok(Self {
+                tip_height,
+                outputs,
+                complete_history: false,
+            });
+        };
+
+        let mut outputs = Vec::new();
+        for (tx, confirmation_height) in &history {
+            let txid = tx.compute_txid();
+            for (vout, txout) in tx.output.iter().enumerate() {
+                if txout.script_pubkey != script_pubkey {
+                    continue;
+                }
+                let outpoint = OutPoint::new(txid, vout as u32);
+                let spent = history.iter().find_map(|(spender, height)| {
+                    let input = spender.input.iter().find(|input| input.previous_output == outpoint)?;
+                    Some(Spend {
+                        txid: spender.compute_txid(),
+                        confirmation_height: *height,
+                        path: gift.spend_path(&input.witness),
+                    })
+                });
+                outputs.push(OutputStatus {
+                    outpoint,
+                    amount: txout.value,
+                    confirmation_height: *confirmation_height,
+                    blocks_remaining: countdown(*confirmation_height),
+                    spent,
+                });
+            }
+        }
+
+        Ok(Self {
+            tip_height,
+            outputs,
+            complete_history: true,
+        })
+    }
+}
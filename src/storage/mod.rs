@@ -0,0 +1,49 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replaces the file at `path` with `contents`, readable only by its owner
+/// where supported.
+///
+/// The contents go to a temporary file next to `path`, created fresh with
+/// owner-only permissions and then renamed over `path`. The data is never
+/// readable by others, not even briefly, and an interrupted write leaves the
+/// previous file intact. A temporary file left by an earlier interrupted
+/// write is removed first rather than written through, so a link planted in
+/// its place is never followed.
+///
+/// # Arguments
+///
+/// * `path` - The file to write
+/// * `contents` - The bytes to write
+///
+/// # Returns
+///
+/// Ok once the file is in place, or the first I/O error.
+pub fn write_owner_only(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    match fs::remove_file(&temporary) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    fs::rename(&temporary, path)
+}
+
+/// Returns the temporary file `write_owner_only` uses for `path`.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,224 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::error::Error;
+
+/// What a fake server answers to one request.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// A JSON-RPC `result`, or an HTTP 200 body (a JSON string is sent as plain text).
+    Ok(Value),
+    /// A JSON-RPC error code and message, or an HTTP status and body.
+    Err(i64, String),
+}
+
+#[derive(Clone, Copy)]
+enum Protocol {
+    /// Esplora REST, replies keyed by request line, e.g. `GET /blocks/tip/height`.
+    Rest,
+    /// Bitcoin Core JSON-RPC over HTTP, replies keyed by method.
+    HttpJsonRpc,
+    /// Electrum newline-delimited JSON-RPC over TCP, replies keyed by method.
+    Electrum,
+}
+
+/// An in-process server on a local port that answers from canned replies,
+/// so backend clients can be tested without a network.
+///
+/// JSON-RPC replies are keyed by method, or by the method and its JSON
+/// params separated by a space when the reply depends on them; the more
+/// specific key wins. Requests without a reply get a 404 or an RPC error.
+/// Every request is recorded, in the same form as the keys.
+pub struct FakeServer {
+    address: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl FakeServer {
+    /// Serves Esplora REST requests.
+    pub fn esplora(replies: Vec<(&str, Reply)>) -> Result<Self, Error> {
+        Self::start(Protocol::Rest, replies)
+    }
+
+    /// Serves Bitcoin Core JSON-RPC requests.
+    pub fn bitcoind(replies: Vec<(&str, Reply)>) -> Result<Self, Error> {
+        Self::start(Protocol::HttpJsonRpc, replies)
+    }
+
+    /// Serves Electrum protocol requests.
+    pub fn electrum(replies: Vec<(&str, Reply)>) -> Result<Self, Error> {
+        Self::start(Protocol::Electrum, replies)
+    }
+
+    /// The server's URL for HTTP clients, `http://127.0.0.1:<port>`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// The server's `host:port`.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Every request received so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().expect("request log").clone()
+    }
+
+    fn start(protocol: Protocol, replies: Vec<(&str, Reply)>) -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .map_err(|e| Error::BackendError(format!("Failed to start fake server: {}", e)))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::BackendError(format!("Failed to start fake server: {}", e)))?;
+        let replies: Arc<HashMap<String, Reply>> =
+            Arc::new(replies.into_iter().map(|(key, reply)| (key.to_string(), reply)).collect());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let (log, stop) = (requests.clone(), shutdown.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let (replies, log) = (replies.clone(), log.clone());
+                thread::spawn(move || {
+                    // A client that hangs up mid-request is not the server's problem
+                    let _ = match protocol {
+                        Protocol::Electrum => serve_electrum(stream, &replies, &log),
+                        _ => serve_http(stream, protocol, &replies, &log),
+                    };
+                });
+            }
+        });
+
+        Ok(Self {
+            address,
+            requests,
+            shutdown,
+        })
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        // Wake the accept loop so it sees the flag and exits
+        self.shutdown.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+/// Finds the reply for a JSON-RPC call, preferring one keyed by its params.
+fn rpc_reply(replies: &HashMap<String, Reply>, method: &str, params: &Value) -> Option<(String, Reply)> {
+    let with_params = format!("{} {}", method, params);
+    replies
+        .get(&with_params)
+        .or_else(|| replies.get(method))
+        .map(|reply| (with_params, reply.clone()))
+}
+
+fn serve_http(
+    stream: TcpStream,
+    protocol: Protocol,
+    replies: &HashMap<String, Reply>,
+    log: &Mutex<Vec<String>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8_lossy(&body).to_string();
+
+    let (status, content) = match protocol {
+        Protocol::HttpJsonRpc => {
+            let call: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+            let rpc_method = call["method"].as_str().unwrap_or_default();
+            let params = call.get("params").cloned().unwrap_or(json!([]));
+            let (key, reply) = rpc_reply(replies, rpc_method, &params)
+                .unwrap_or_else(|| (format!("{} {}", rpc_method, params), method_not_found()));
+            log.lock().expect("request log").push(key);
+            // Bitcoin Core sends RPC errors with HTTP 500
+            let (status, response) = match reply {
+                Reply::Ok(result) => (200, json!({ "result": result, "error": null, "id": call["id"] })),
+                Reply::Err(code, message) => (
+                    500,
+                    json!({ "result": null, "error": { "code": code, "message": message }, "id": call["id"] }),
+                ),
+            };
+            (status, response.to_string())
+        }
+        _ => {
+            let key = format!("{} {}", method, path);
+            log.lock().expect("request log").push(if body.is_empty() { key.clone() } else { format!("{} {}", key, body) });
+            match replies.get(&key) {
+                Some(Reply::Ok(Value::String(text))) => (200, text.clone()),
+                Some(Reply::Ok(value)) => (200, value.to_string()),
+                Some(Reply::Err(status, text)) => (*status as u16, text.clone()),
+                None => (404, "Not Found".to_string()),
+            }
+        }
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {} Fake\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content.len(),
+        content
+    )?;
+    stream.flush()
+}
+
+fn serve_electrum(stream: TcpStream, replies: &HashMap<String, Reply>, log: &Mutex<Vec<String>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let call: Value = serde_json::from_str(&line).unwrap_or(Value::Null);
+        let method = call["method"].as_str().unwrap_or_default();
+        let params = call.get("params").cloned().unwrap_or(json!([]));
+        let (key, reply) =
+            rpc_reply(replies, method, &params).unwrap_or_else(|| (format!("{} {}", method, params), method_not_found()));
+        log.lock().expect("request log").push(key);
+
+        let response = match reply {
+            Reply::Ok(result) => json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }),
+            Reply::Err(code, message) => {
+                json!({ "jsonrpc": "2.0", "id": call["id"], "error": { "code": code, "message": message } })
+            }
+        };
+        let stream = reader.get_mut();
+        stream.write_all(format!("{}\n", response).as_bytes())?;
+        stream.flush()?;
+    }
+}
+
+fn method_not_found() -> Reply {
+    Reply::Err(-32601, "Method not found".to_string())
+}
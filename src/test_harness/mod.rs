@@ -7,11 +7,17 @@ use std::str::FromStr;
 use crate::keys::GiftKeys;
 use crate::error::Error;
 
+pub mod fake_server;
+pub mod fixtures;
+
+pub use fake_server::{FakeServer, Reply};
+
 #[derive(Clone)]
 pub struct TestHarness {
     pub giver_descriptor: Descriptor<DescriptorPublicKey>,
     pub receiver_descriptor: Descriptor<DescriptorPublicKey>,
     pub gift_keys: GiftKeys,
+    pub network: Network,
 }
 
 impl TestHarness {
@@ -27,13 +33,17 @@ impl TestHarness {
         let receiver_descriptor = Descriptor::<DescriptorPublicKey>::from_str(receiver_desc)
             .map_err(|e| Error::KeyError(format!("Failed to parse receiver descriptor: {}", e)))?;
 
-        // Create GiftKeys from descriptors
-        let gift_keys = GiftKeys::from_descriptors(giver_desc, receiver_desc)?;
+        // Create GiftKeys from the descriptors' internal keys
+        let gift_keys = GiftKeys::from_keys(
+            tr_internal_key(&giver_descriptor)?,
+            tr_internal_key(&receiver_descriptor)?,
+        );
             
         Ok(Self {
             giver_descriptor,
             receiver_descriptor,
             gift_keys,
+            network: Network::Regtest,
         })
     }
 
@@ -45,7 +55,15 @@ impl TestHarness {
             
         let script = derived.script_pubkey();
         
-        bitcoin::Address::from_script(&script, Network::Regtest)
+        bitcoin::Address::from_script(&script, self.network)
             .map_err(|e| Error::KeyError(format!("Failed to create address: {}", e)))
     }
-}
\ No newline at end of file
+}
+
+/// Extracts the internal key of a `tr()` descriptor.
+fn tr_internal_key(descriptor: &Descriptor<DescriptorPublicKey>) -> Result<DescriptorPublicKey, Error> {
+    match descriptor {
+        Descriptor::Tr(tr) => Ok(tr.internal_key().clone()),
+        _ => Err(Error::KeyError("Expected a tr() descriptor".to_string())),
+    }
+}
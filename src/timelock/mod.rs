@@ -0,0 +1,382 @@
+use bitcoin::opcodes::all::{OP_CLTV, OP_CSV};
+use bitcoin::opcodes::Opcode;
+use bitcoin::{absolute, relative};
+use bitcoin::Sequence;
+use serde::{Deserialize, Serialize};
+use crate::Error;
+
+/// Timelock guarding the receiver's script path.
+///
+/// Values are kept as the user asked for them and checked when they are
+/// encoded, so a gift can be described before it is validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timelock {
+    /// Relative lock of a number of blocks, enforced with OP_CHECKSEQUENCEVERIFY.
+    Blocks(u32),
+    /// Relative lock of a duration in seconds, enforced with
+    /// OP_CHECKSEQUENCEVERIFY in 512-second units (rounded up).
+    Seconds(u32),
+    /// Absolute block height, enforced with OP_CHECKLOCKTIMEVERIFY.
+    UnlockHeight(u32),
+    /// Absolute Unix timestamp, enforced with OP_CHECKLOCKTIMEVERIFY against
+    /// median time past.
+    UnlockTime(u32),
+}
+
+impl Timelock {
+    /// Largest relative block count BIP-68 can express (about 15 months).
+    pub const MAX_BLOCKS: u32 = 0xFFFF;
+
+    /// Granularity of time-based relative locks.
+    pub const SECONDS_PER_UNIT: u32 = 512;
+
+    /// Largest relative duration BIP-68 can express: 65535 units of 512
+    /// seconds (about 388 days).
+    pub const MAX_SECONDS: u32 = 0xFFFF * Self::SECONDS_PER_UNIT;
+
+    /// Creates a relative block timelock, rejecting values CSV can't express.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - Number of blocks after confirmation, 1 to `MAX_BLOCKS`
+    ///
+    /// # Returns
+    ///
+    /// The Timelock or a TimelockError explaining the valid range.
+    pub fn blocks(blocks: u32) -> Result<Self, Error> {
+        let timelock = Timelock::Blocks(blocks);
+        timelock.validate()?;
+        Ok(timelock)
+    }
+
+    /// Creates a relative timelock from a duration such as `180d`.
+    ///
+    /// Accepted suffixes are `s`, `m`, `h`, `d` and `w`. The duration is
+    /// rounded up to whole 512-second units so the lock never ends early.
+    pub fn lock_for(duration: &str) -> Result<Self, Error> {
+        let timelock = Timelock::Seconds(parse_duration(duration)?);
+        timelock.validate()?;
+        Ok(timelock)
+    }
+
+    /// Returns the encoded relative duration in seconds, after rounding up
+    /// to 512-second units. `None` for block-based or absolute locks.
+    pub fn effective_seconds(&self) -> Result<Option<u32>, Error> {
+        match self.relative_lock_time()? {
+            relative::LockTime::Time(time) => Ok(Some(u32::from(time.value()) * Self::SECONDS_PER_UNIT)),
+            relative::LockTime::Blocks(_) => Ok(None),
+        }
+    }
+
+    /// Creates an absolute timelock at a block height.
+    pub fn unlock_height(height: u32) -> Result<Self, Error> {
+        let timelock = Timelock::UnlockHeight(height);
+        timelock.validate()?;
+        Ok(timelock)
+    }
+
+    /// Creates an absolute timelock at midnight UTC on a `YYYY-MM-DD` date.
+    pub fn unlock_date(date: &str) -> Result<Self, Error> {
+        let timestamp = parse_date(date)?;
+        let timelock = Timelock::UnlockTime(timestamp);
+        timelock.validate()?;
+        Ok(timelock)
+    }
+
+    /// Returns how many more blocks must be mined before a spend through
+    /// this lock can be included in the next block.
+    ///
+    /// # Arguments
+    ///
+    /// * `confirmation_height` - Height of the block that confirmed the gift,
+    ///   needed for relative locks
+    /// * `tip_height` - Height of the current chain tip
+    ///
+    /// # Returns
+    ///
+    /// `Some(0)` once spendable, or `None` for time-based locks and for
+    /// relative locks on an unconfirmed gift, whose end blocks can't tell.
+    pub fn blocks_remaining(&self, confirmation_height: Option<u32>, tip_height: u32) -> Option<u32> {
+        // A transaction is mined at the earliest in the block after the tip
+        let next_block = tip_height.saturating_add(1);
+        let unlock_height = match *self {
+            Timelock::Blocks(blocks) => confirmation_height?.saturating_add(blocks),
+            Timelock::UnlockHeight(height) => height.saturating_add(1),
+            Timelock::Seconds(_) | Timelock::UnlockTime(_) => return None,
+        };
+
+        Some(unlock_height.saturating_sub(next_block))
+    }
+
+    /// Checks that the timelock fits its consensus encoding.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.is_absolute() {
+            self.absolute_lock_time().map(|_| ())
+        } else {
+            self.relative_lock_time().map(|_| ())
+        }
+    }
+
+    /// Whether the lock is absolute (CLTV) rather than relative (CSV).
+    pub fn is_absolute(&self) -> bool {
+        matches!(self, Timelock::UnlockHeight(_) | Timelock::UnlockTime(_))
+    }
+
+    /// Whether two timelocks are measured the same way (relative blocks,
+    /// relative time, absolute height or absolute time) and so comparable.
+    pub fn is_same_unit(&self, other: &Timelock) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Returns the BIP-68 relative lock time, with the type flag set for the unit.
+    pub fn relative_lock_time(&self) -> Result<relative::LockTime, Error> {
+        match *self {
+            Timelock::Blocks(0) => Err(Error::TimelockError(
+                "Timelock must be at least 1 block".to_string(),
+            )),
+            Timelock::Blocks(blocks) => u16::try_from(blocks)
+                .map(relative::LockTime::from_height)
+                .map_err(|_| {
+                    Error::TimelockError(format!(
+                        "Timelock of {} blocks exceeds the BIP-68 maximum of {} blocks (about 15 months); use an unlock date or height instead",
+                        blocks,
+                        Self::MAX_BLOCKS
+                    ))
+                }),
+            Timelock::Seconds(0) => Err(Error::TimelockError(
+                "Timelock duration must be at least 1 second".to_string(),
+            )),
+            Timelock::Seconds(seconds) => relative::LockTime::from_seconds_ceil(seconds).map_err(|_| {
+                Error::TimelockError(format!(
+                    "Timelock of {} seconds exceeds the BIP-68 maximum of {} seconds (about 388 days); use an unlock date instead",
+                    seconds,
+                    Self::MAX_SECONDS
+                ))
+            }),
+            _ => Err(Error::TimelockError(format!("{} is not a relative timelock", self))),
+        }
+    }
+
+    /// Returns the BIP-65 absolute lock time.
+    pub fn absolute_lock_time(&self) -> Result<absolute::LockTime, Error> {
+        match *self {
+            Timelock::UnlockHeight(0) => Err(Error::TimelockError(
+                "Unlock height must be at least 1".to_string(),
+            )),
+            Timelock::UnlockHeight(height) => absolute::LockTime::from_height(height).map_err(|_| {
+                Error::TimelockError(format!(
+                    "Unlock height {} is too large; heights must be below {}",
+                    height,
+                    absolute::LOCK_TIME_THRESHOLD
+                ))
+            }),
+            Timelock::UnlockTime(timestamp) => absolute::LockTime::from_time(timestamp).map_err(|_| {
+                Error::TimelockError(format!(
+                    "Unlock time {} is too early; timestamps start at {} (November 1985)",
+                    timestamp,
+                    absolute::LOCK_TIME_THRESHOLD
+                ))
+            }),
+            _ => Err(Error::TimelockError(format!("{} is not an absolute timelock", self))),
+        }
+    }
+
+    /// Returns the minimum nSequence a spending input must carry.
+    ///
+    /// Absolute locks only need a non-final sequence so nLockTime is enforced.
+    pub fn to_sequence(&self) -> Result<Sequence, Error> {
+        if self.is_absolute() {
+            self.absolute_lock_time()?;
+            Ok(Sequence::ENABLE_RBF_NO_LOCKTIME)
+        } else {
+            Ok(self.relative_lock_time()?.to_sequence())
+        }
+    }
+
+    /// Returns the nLockTime a spending transaction must carry.
+    ///
+    /// Relative locks don't constrain nLockTime and return zero.
+    pub fn to_lock_time(&self) -> Result<absolute::LockTime, Error> {
+        if self.is_absolute() {
+            self.absolute_lock_time()
+        } else {
+            self.relative_lock_time()?;
+            Ok(absolute::LockTime::ZERO)
+        }
+    }
+
+    /// Returns the number pushed before the timelock opcode.
+    ///
+    /// This is the consensus encoding of the lock, including the BIP-68 type
+    /// flag for relative locks, so it compares correctly against the spending
+    /// input's nSequence or the transaction's nLockTime.
+    pub fn script_value(&self) -> Result<i64, Error> {
+        if self.is_absolute() {
+            Ok(i64::from(self.absolute_lock_time()?.to_consensus_u32()))
+        } else {
+            Ok(i64::from(self.relative_lock_time()?.to_consensus_u32()))
+        }
+    }
+
+    /// Returns OP_CHECKLOCKTIMEVERIFY for absolute locks and
+    /// OP_CHECKSEQUENCEVERIFY for relative ones.
+    pub fn opcode(&self) -> Opcode {
+        if self.is_absolute() {
+            OP_CLTV
+        } else {
+            OP_CSV
+        }
+    }
+}
+
+impl std::fmt::Display for Timelock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timelock::Blocks(blocks) => write!(f, "{} blocks", blocks),
+            Timelock::Seconds(seconds) => write!(f, "{}", format_duration(*seconds)),
+            Timelock::UnlockHeight(height) => write!(f, "block height {}", height),
+            Timelock::UnlockTime(timestamp) => write!(f, "{} UTC", format_timestamp(*timestamp)),
+        }
+    }
+}
+
+/// Parses the compact timelock notation used for secondary timelocks and
+/// manifests: `52560` (blocks), `180d` (duration), `2030-01-01` (date) or
+/// `height:900000` (absolute height).
+impl std::str::FromStr for Timelock {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+
+        if let Some(height) = spec.strip_prefix("height:") {
+            let height = height
+                .parse()
+                .map_err(|_| Error::TimelockError(format!("Invalid unlock height '{}'", height)))?;
+            Timelock::unlock_height(height)
+        } else if spec.contains('-') {
+            Timelock::unlock_date(spec)
+        } else if let Ok(blocks) = spec.parse() {
+            Timelock::blocks(blocks)
+        } else {
+            Timelock::lock_for(spec)
+        }
+    }
+}
+
+/// Parses a duration like `180d`, `12h` or `3600s` into seconds.
+fn parse_duration(duration: &str) -> Result<u32, Error> {
+    let invalid = || {
+        Error::TimelockError(format!(
+            "Invalid duration '{}' (expected a number followed by s, m, h, d or w, e.g. 180d)",
+            duration
+        ))
+    };
+
+    let duration = duration.trim();
+    let (split, _) = duration.char_indices().last().ok_or_else(invalid)?;
+    let (amount, unit) = duration.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let multiplier: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+
+    amount
+        .checked_mul(multiplier)
+        .and_then(|seconds| u32::try_from(seconds).ok())
+        .ok_or_else(|| {
+            Error::TimelockError(format!(
+                "Timelock duration '{}' exceeds the BIP-68 maximum of {} seconds (about 388 days)",
+                duration,
+                Timelock::MAX_SECONDS
+            ))
+        })
+}
+
+/// Formats seconds in the largest unit that divides them evenly.
+pub fn format_duration(seconds: u32) -> String {
+    match seconds {
+        s if s % 86_400 == 0 => format!("{} days", s / 86_400),
+        s if s % 3600 == 0 => format!("{} hours", s / 3600),
+        s if s % 60 == 0 => format!("{} minutes", s / 60),
+        s => format!("{} seconds", s),
+    }
+}
+
+/// Parses `YYYY-MM-DD` into the Unix timestamp of midnight UTC on that day.
+fn parse_date(date: &str) -> Result<u32, Error> {
+    let invalid = || Error::TimelockError(format!("Invalid date '{}' (expected YYYY-MM-DD)", date));
+
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    let day: u32 = day.parse().map_err(|_| invalid())?;
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86_400;
+    u32::try_from(seconds)
+        .map_err(|_| Error::TimelockError(format!("Date '{}' is outside the range nLockTime can express", date)))
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM:SS`.
+pub fn format_timestamp(timestamp: u32) -> String {
+    let days = i64::from(timestamp / 86_400);
+    let seconds = timestamp % 86_400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
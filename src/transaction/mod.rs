@@ -1,10 +1,803 @@
+use bitcoin::absolute::LockTime;
+use bitcoin::bip32::{self, DerivationPath};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::transaction::{InputWeightPrediction, Version};
+use bitcoin::{Amount, CompressedPublicKey, FeeRate, OutPoint, Psbt, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Weight, Witness};
+use bitcoin::hashes::Hash;
+use bitcoin::key::TweakedPublicKey;
+use bitcoin::psbt::{self, raw};
+use bitcoin::secp256k1::{schnorr, PublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{self, LeafVersion, TapLeafHash};
+use miniscript::descriptor::DescriptorPublicKey;
+use miniscript::psbt::PsbtExt;
+use musig2::{AggNonce, BinaryEncoding, KeyAggContext, PartialSignature, PubNonce};
+use std::collections::{BTreeMap, HashSet};
+use crate::coin_selection::{self, SelectionOptions};
+use crate::fee::{self, fee_at};
+use crate::keys::{from_point, point_to_x_only, to_point, GiftKeys, KeySource};
+use crate::script::{GiftScript, LeafRole, SpendPath};
+use crate::timelock::Timelock;
 use crate::error::Error;
 
-// Placeholder for transaction functionality
-pub struct GiftTransaction;
+/// BIP-373 input field listing the participants of a MuSig2 aggregate key.
+pub const PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x1a;
+
+/// BIP-373 input field holding a participant's public nonce.
+pub const PSBT_IN_MUSIG2_PUB_NONCE: u8 = 0x1b;
+
+/// BIP-373 input field holding a participant's partial signature.
+pub const PSBT_IN_MUSIG2_PARTIAL_SIG: u8 = 0x1c;
+
+/// BIP-373 output field listing the participants of a MuSig2 aggregate key.
+pub const PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x08;
+
+/// Identifies a BIP-373 nonce or partial signature: whose it is, which
+/// aggregate key it signs for, and the tapleaf for script-path spends
+/// (`None` for the keypath).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MuSig2Signer {
+    pub participant: PublicKey,
+    pub aggregate: PublicKey,
+    pub leaf_hash: Option<TapLeafHash>,
+}
+
+impl MuSig2Signer {
+    /// Returns a signer for the keypath of `aggregate`.
+    pub fn key_path(participant: PublicKey, aggregate: PublicKey) -> Self {
+        Self {
+            participant,
+            aggregate,
+            leaf_hash: None,
+        }
+    }
+
+    fn to_key_data(self) -> Vec<u8> {
+        let mut data = self.participant.serialize().to_vec();
+        data.extend_from_slice(&self.aggregate.serialize());
+        if let Some(leaf_hash) = self.leaf_hash {
+            data.extend_from_slice(leaf_hash.as_byte_array());
+        }
+        data
+    }
+
+    fn from_key_data(data: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::TransactionError("Invalid BIP-373 MuSig2 key data".to_string());
+        if data.len() != 66 && data.len() != 98 {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            participant: PublicKey::from_slice(&data[..33]).map_err(|_| invalid())?,
+            aggregate: PublicKey::from_slice(&data[33..66]).map_err(|_| invalid())?,
+            leaf_hash: data
+                .get(66..)
+                .filter(|hash| !hash.is_empty())
+                .map(|hash| TapLeafHash::from_slice(hash).map_err(|_| invalid()))
+                .transpose()?,
+        })
+    }
+}
+
+/// A giver UTXO available to fund a gift.
+///
+/// `index` is the child of the giver's descriptor that controls the output,
+/// which is where its key origin in the PSBT comes from.
+#[derive(Debug, Clone)]
+pub struct GiftUtxo {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    pub script_pubkey: ScriptBuf,
+    pub index: u32,
+    /// Block the UTXO confirmed in, if known; used by oldest-first selection.
+    pub confirmation_height: Option<u32>,
+}
+
+impl GiftUtxo {
+    pub fn new(outpoint: OutPoint, amount: Amount, script_pubkey: ScriptBuf, index: u32) -> Self {
+        Self {
+            outpoint,
+            amount,
+            script_pubkey,
+            index,
+            confirmation_height: None,
+        }
+    }
+
+    /// Sets the block height the UTXO confirmed in.
+    pub fn with_confirmation_height(mut self, height: u32) -> Self {
+        self.confirmation_height = Some(height);
+        self
+    }
+}
+
+/// Builds the transactions that move funds into and out of a gift.
+pub struct GiftTransaction {
+    keys: GiftKeys,
+    script: GiftScript,
+}
 
 impl GiftTransaction {
-    pub fn new() -> Self {
-        Self
+    pub fn new(keys: GiftKeys, script: GiftScript) -> Self {
+        Self { keys, script }
+    }
+
+    pub fn keys(&self) -> &GiftKeys {
+        &self.keys
+    }
+
+    pub fn script(&self) -> &GiftScript {
+        &self.script
+    }
+
+    /// Returns the single-key P2TR script of the giver's wallet at `index`,
+    /// as a Sparrow or Core `tr()` wallet would derive it.
+    pub fn giver_script_pubkey(&self, index: u32) -> Result<ScriptBuf, Error> {
+        let key = self.keys.giver.public_key_at(index)?;
+        Ok(ScriptBuf::new_p2tr(&Secp256k1::verification_only(), key.x_only_public_key().0, None))
+    }
+
+    /// Builds an unsigned funding PSBT that pays `amount` to the gift address.
+    ///
+    /// Every UTXO in `utxos` is spent. Whatever is left after `amount` and the
+    /// fee goes back to the giver's descriptor at `change_index`, unless it
+    /// would be dust, in which case it is added to the fee.
+    ///
+    /// Each input carries its `witness_utxo` and the giver key origin
+    /// (`tap_key_origins` for P2TR, `bip32_derivation` for P2WPKH), and the
+    /// change output carries its origin too, so the giver's wallet can sign
+    /// the PSBT and recognise the change.
+    ///
+    /// # Arguments
+    ///
+    /// * `utxos` - Giver UTXOs to spend
+    /// * `amount` - Amount the gift address receives
+    /// * `fee_rate` - Fee rate for the funding transaction
+    /// * `change_index` - Giver descriptor index the change is sent to
+    ///
+    /// # Returns
+    ///
+    /// The unsigned PSBT or a TransactionError if the UTXOs do not belong to
+    /// the giver or cannot cover the amount and fee.
+    pub fn fund(&self, utxos: &[GiftUtxo], amount: Amount, fee_rate: FeeRate, change_index: u32) -> Result<Psbt, Error> {
+        Self::fund_batch(&[(self, amount)], utxos, fee_rate, change_index)
+    }
+
+    /// Builds one unsigned funding PSBT that pays several gifts from the same
+    /// giver, with one output per gift in the order given.
+    ///
+    /// Works like `fund`: every UTXO is spent, change goes to the giver's
+    /// descriptor at `change_index`, and each gift output carries its MuSig2
+    /// internal key and participants. The first gift's giver keys sign the
+    /// inputs and receive the change.
+    ///
+    /// # Returns
+    ///
+    /// The unsigned PSBT or a TransactionError if the gifts have different
+    /// givers or networks, or the UTXOs cannot cover them and the fee.
+    pub fn fund_batch(
+        gifts: &[(&GiftTransaction, Amount)],
+        utxos: &[GiftUtxo],
+        fee_rate: FeeRate,
+        change_index: u32,
+    ) -> Result<Psbt, Error> {
+        let funder = Self::funder(gifts)?;
+        if utxos.is_empty() {
+            return Err(Error::TransactionError("No UTXOs to fund the gift with".to_string()));
+        }
+
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = utxos.iter().find(|utxo| !seen.insert(utxo.outpoint)) {
+            return Err(Error::TransactionError(format!("UTXO {} is listed twice", duplicate.outpoint)));
+        }
+
+        let mut outputs = Vec::with_capacity(gifts.len() + 1);
+        for (gift, amount) in gifts {
+            let gift_script_pubkey = gift.script.create_address(&gift.keys)?.script_pubkey();
+            if *amount < gift_script_pubkey.minimal_non_dust() {
+                return Err(Error::TransactionError(format!(
+                    "Gift amount of {} is below the dust limit of {}",
+                    amount,
+                    gift_script_pubkey.minimal_non_dust()
+                )));
+            }
+            outputs.push(TxOut {
+                value: *amount,
+                script_pubkey: gift_script_pubkey,
+            });
+        }
+
+        let amount = gifts
+            .iter()
+            .try_fold(Amount::ZERO, |sum, (_, amount)| sum.checked_add(*amount))
+            .ok_or_else(|| Error::TransactionError("Gift amounts overflow".to_string()))?;
+        let total = utxos
+            .iter()
+            .try_fold(Amount::ZERO, |sum, utxo| sum.checked_add(utxo.amount))
+            .ok_or_else(|| Error::TransactionError("UTXO amounts overflow".to_string()))?;
+
+        let change_script_pubkey = funder.giver_script_pubkey(change_index)?;
+        let fee_with_change = fee_at(fee_rate, Self::batch_funding_weight(gifts, utxos, Some(change_index))?)?;
+        let fee_without_change = fee_at(fee_rate, Self::batch_funding_weight(gifts, utxos, None)?)?;
+
+        match total.checked_sub(amount + fee_with_change) {
+            Some(change) if change >= change_script_pubkey.minimal_non_dust() => outputs.push(TxOut {
+                value: change,
+                script_pubkey: change_script_pubkey,
+            }),
+            _ if total >= amount + fee_without_change => {}
+            _ => {
+                return Err(Error::TransactionError(format!(
+                    "UTXOs total {} but the gift needs {} plus a fee of {}",
+                    total, amount, fee_without_change
+                )))
+            }
+        }
+        let has_change = outputs.len() > gifts.len();
+
+        let unsigned_tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: utxos
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: outputs,
+        };
+
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx)
+            .map_err(|e| Error::TransactionError(format!("Failed to create PSBT: {}", e)))?;
+
+        for (input, utxo) in psbt.inputs.iter_mut().zip(utxos) {
+            input.witness_utxo = Some(TxOut {
+                value: utxo.amount,
+                script_pubkey: utxo.script_pubkey.clone(),
+            });
+
+            let key = funder.keys.giver.public_key_at(utxo.index)?;
+            let origin = key_origin(&funder.keys.giver, utxo.index)?;
+            if utxo.script_pubkey.is_p2tr() {
+                let (x_only, _) = key.x_only_public_key();
+                input.tap_internal_key = Some(x_only);
+                input.tap_key_origins.insert(x_only, (Vec::new(), origin));
+            } else {
+                input.bip32_derivation.insert(key, origin);
+            }
+        }
+
+        if has_change {
+            let key = funder.keys.giver.public_key_at(change_index)?;
+            let (x_only, _) = key.x_only_public_key();
+            let change = &mut psbt.outputs[gifts.len()];
+            change.tap_internal_key = Some(x_only);
+            change.tap_key_origins.insert(x_only, (Vec::new(), key_origin(&funder.keys.giver, change_index)?));
+        }
+
+        // Each gift output's internal key and participants let wallets
+        // recognise the MuSig2 keypath
+        for (output, (gift, _)) in psbt.outputs.iter_mut().zip(gifts) {
+            output.tap_internal_key = Some(gift.keys.aggregate_musig2_key()?);
+            output.unknown.insert(
+                raw::Key {
+                    type_value: PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS,
+                    key: gift.keys.aggregate_musig2_pubkey()?.serialize().to_vec(),
+                },
+                participants_value(&gift.keys.musig2_participants()?),
+            );
+        }
+
+        if let KeySource::Descriptor(DescriptorPublicKey::XPub(xpub)) = &funder.keys.giver {
+            if let Some(origin) = &xpub.origin {
+                psbt.xpub.insert(xpub.xkey, origin.clone());
+            }
+        }
+
+        Ok(psbt)
+    }
+
+    /// Builds an unsigned PSBT that funds the gift from UTXOs chosen among
+    /// `utxos` by `options`, then works like `fund`.
+    ///
+    /// # Returns
+    ///
+    /// The unsigned PSBT or a CoinSelectionError if no allowed set of UTXOs
+    /// covers the amount and fee.
+    pub fn fund_with_selection(
+        &self,
+        utxos: &[GiftUtxo],
+        amount: Amount,
+        fee_rate: FeeRate,
+        change_index: u32,
+        options: &SelectionOptions,
+    ) -> Result<Psbt, Error> {
+        let selected = coin_selection::select_utxos(self, utxos, amount, fee_rate, change_index, options)?;
+        self.fund(&selected, amount, fee_rate, change_index)
+    }
+
+    /// Returns the weight of the funding transaction spending `utxos` into
+    /// the gift output and, given a `change_index`, a change output.
+    ///
+    /// # Returns
+    ///
+    /// The weight or a TransactionError if a UTXO does not belong to the
+    /// giver.
+    pub fn funding_weight(&self, utxos: &[GiftUtxo], change_index: Option<u32>) -> Result<Weight, Error> {
+        Self::batch_funding_weight(&[(self, Amount::ZERO)], utxos, change_index)
+    }
+
+    /// Returns the weight of a batch funding transaction spending `utxos`
+    /// into one output per gift and, given a `change_index`, a change output.
+    pub fn batch_funding_weight(
+        gifts: &[(&GiftTransaction, Amount)],
+        utxos: &[GiftUtxo],
+        change_index: Option<u32>,
+    ) -> Result<Weight, Error> {
+        let funder = Self::funder(gifts)?;
+        let inputs = utxos
+            .iter()
+            .map(|utxo| funder.input_weight(utxo))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut output_script_lens = gifts
+            .iter()
+            .map(|(gift, _)| Ok(gift.script.create_address(&gift.keys)?.script_pubkey().len()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if let Some(index) = change_index {
+            output_script_lens.push(funder.giver_script_pubkey(index)?.len());
+        }
+
+        Ok(fee::transaction_weight(&inputs, &output_script_lens))
+    }
+
+    /// Returns the gift whose giver keys fund a batch, checking every gift
+    /// shares its giver and network.
+    fn funder<'a>(gifts: &[(&'a GiftTransaction, Amount)]) -> Result<&'a GiftTransaction, Error> {
+        let (funder, _) = gifts
+            .first()
+            .ok_or_else(|| Error::TransactionError("No gifts to fund".to_string()))?;
+
+        let giver_script = funder.giver_script_pubkey(0)?;
+        for (gift, _) in &gifts[1..] {
+            if gift.keys.network != funder.keys.network {
+                return Err(Error::TransactionError("Gifts in one funding transaction must share a network".to_string()));
+            }
+            if gift.giver_script_pubkey(0)? != giver_script {
+                return Err(Error::TransactionError("Gifts in one funding transaction must share a giver".to_string()));
+            }
+        }
+
+        Ok(funder)
+    }
+
+    /// Builds an unsigned PSBT that spends the gift through the receiver's
+    /// timelock leaf, sending everything but the fee to `destination`.
+    ///
+    /// The transaction is version 2 with the nSequence (and, for absolute
+    /// locks, nLockTime) the leaf requires. The input carries the leaf script
+    /// and control block in `tap_scripts`, the receiver key origin with the
+    /// leaf hash in `tap_key_origins`, and the MuSig2 internal key and merkle
+    /// root, so any taproot-aware wallet holding the receiver key can sign it.
+    ///
+    /// # Arguments
+    ///
+    /// * `gift_outpoint` - The funded gift output
+    /// * `gift_value` - Amount held by the gift output
+    /// * `destination` - Script the redeemed funds are sent to
+    /// * `fee_rate` - Fee rate for the redemption transaction
+    ///
+    /// # Returns
+    ///
+    /// The unsigned PSBT or a TransactionError if the fee leaves only dust.
+    pub fn redeem_after_timelock(
+        &self,
+        gift_outpoint: OutPoint,
+        gift_value: Amount,
+        destination: ScriptBuf,
+        fee_rate: FeeRate,
+    ) -> Result<Psbt, Error> {
+        let leaf = self
+            .script
+            .leaves(&self.keys)?
+            .into_iter()
+            .find(|leaf| leaf.role == LeafRole::Receiver)
+            .ok_or_else(|| Error::ScriptError("Gift has no receiver leaf".to_string()))?;
+        let (_, spend_info) = self.script.create_taproot_tree(&self.keys)?;
+        let control_block = leaf.control_block(&spend_info)?;
+
+        let witness = fee::spend_witness(&self.keys, &self.script, SpendPath::Leaf(LeafRole::Receiver))?;
+        let mut psbt = self.spend_gift(gift_outpoint, gift_value, destination, fee_rate, witness, Some(leaf.timelock))?;
+
+        let input = &mut psbt.inputs[0];
+        let leaf_hash = TapLeafHash::from_script(&leaf.script, LeafVersion::TapScript);
+        input.tap_key_origins.insert(
+            self.keys.receiver_x_only_pub()?,
+            (vec![leaf_hash], key_origin(&self.keys.receiver, self.keys.index)?),
+        );
+        input.tap_scripts.insert(control_block, (leaf.script, LeafVersion::TapScript));
+
+        Ok(psbt)
+    }
+
+    /// Builds an unsigned PSBT that spends the gift through the MuSig2
+    /// keypath, sending everything but the fee to `destination`.
+    ///
+    /// The keypath needs no timelock, so the input is final and signals
+    /// replaceability. The input lists both participants in the BIP-373
+    /// participant field, so giver and receiver can sign it with the `coop`
+    /// commands or any wallet that implements BIP-373.
+    ///
+    /// # Returns
+    ///
+    /// The unsigned PSBT or a TransactionError if the fee leaves only dust.
+    pub fn spend_cooperatively(
+        &self,
+        gift_outpoint: OutPoint,
+        gift_value: Amount,
+        destination: ScriptBuf,
+        fee_rate: FeeRate,
+    ) -> Result<Psbt, Error> {
+        let mut psbt = self.spend_gift(
+            gift_outpoint,
+            gift_value,
+            destination,
+            fee_rate,
+            fee::spend_witness(&self.keys, &self.script, SpendPath::KeyPath)?,
+            None,
+        )?;
+
+        set_musig2_participants(
+            &mut psbt.inputs[0],
+            self.keys.aggregate_musig2_pubkey()?,
+            &self.keys.musig2_participants()?,
+        );
+
+        Ok(psbt)
+    }
+
+    /// Builds a version 2 PSBT spending the gift output to `destination`.
+    ///
+    /// `witness` predicts the spending witness for the fee, and `timelock`
+    /// sets the nSequence and nLockTime of a script-path spend. The input
+    /// carries the gift's `witness_utxo`, internal key and merkle root.
+    fn spend_gift(
+        &self,
+        gift_outpoint: OutPoint,
+        gift_value: Amount,
+        destination: ScriptBuf,
+        fee_rate: FeeRate,
+        witness: InputWeightPrediction,
+        timelock: Option<Timelock>,
+    ) -> Result<Psbt, Error> {
+        let (gift_script_pubkey, spend_info) = self.script.create_taproot_tree(&self.keys)?;
+
+        let fee = fee_at(fee_rate, fee::transaction_weight(&[witness], &[destination.len()]))?;
+        let value = gift_value
+            .checked_sub(fee)
+            .filter(|value| *value >= destination.minimal_non_dust())
+            .ok_or_else(|| {
+                Error::TransactionError(format!(
+                    "Gift of {} cannot pay a fee of {} and leave more than dust",
+                    gift_value, fee
+                ))
+            })?;
+
+        let (sequence, lock_time) = match timelock {
+            Some(timelock) => (timelock.to_sequence()?, timelock.to_lock_time()?),
+            None => (Sequence::ENABLE_RBF_NO_LOCKTIME, LockTime::ZERO),
+        };
+
+        let unsigned_tx = Transaction {
+            version: Version::TWO,
+            lock_time,
+            input: vec![TxIn {
+                previous_output: gift_outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: destination,
+            }],
+        };
+
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx)
+            .map_err(|e| Error::TransactionError(format!("Failed to create PSBT: {}", e)))?;
+
+        let input = &mut psbt.inputs[0];
+        input.witness_utxo = Some(TxOut {
+            value: gift_value,
+            script_pubkey: gift_script_pubkey,
+        });
+        input.tap_internal_key = Some(spend_info.internal_key());
+        input.tap_merkle_root = spend_info.merkle_root();
+
+        Ok(psbt)
+    }
+
+    /// Finalizes a fully signed PSBT and extracts the transaction.
+    ///
+    /// Works for funding, redemption and cooperative PSBTs: keypath inputs
+    /// use their `tap_key_sig`, aggregated first from BIP-373 partial
+    /// signatures where needed, and script-path inputs are satisfied from
+    /// `tap_scripts` and `tap_script_sigs`, which also checks the
+    /// transaction meets the leaf's timelock.
+    ///
+    /// # Returns
+    ///
+    /// The broadcastable transaction or a TransactionError naming what is
+    /// missing.
+    pub fn finalize(mut psbt: Psbt) -> Result<Transaction, Error> {
+        for index in 0..psbt.inputs.len() {
+            let input = &psbt.inputs[index];
+            if input.tap_key_sig.is_none() && !musig2_partial_signatures(input)?.is_empty() {
+                aggregate_musig2_signature(&mut psbt, index)?;
+            }
+        }
+
+        psbt.finalize_mut(&Secp256k1::verification_only()).map_err(|errors| {
+            let reasons: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            Error::TransactionError(format!("Failed to finalize PSBT: {}", reasons.join("; ")))
+        })?;
+
+        psbt.extract_tx()
+            .map_err(|e| Error::TransactionError(format!("Failed to extract transaction: {}", e)))
+    }
+
+    /// Checks a UTXO is controlled by the giver key at its index and returns
+    /// the weight of the witness that will spend it.
+    pub(crate) fn input_weight(&self, utxo: &GiftUtxo) -> Result<InputWeightPrediction, Error> {
+        let key = self.keys.giver.public_key_at(utxo.index)?;
+
+        if utxo.script_pubkey == self.giver_script_pubkey(utxo.index)? {
+            return Ok(InputWeightPrediction::P2TR_KEY_DEFAULT_SIGHASH);
+        }
+        if utxo.script_pubkey == ScriptBuf::new_p2wpkh(&CompressedPublicKey(key).wpubkey_hash()) {
+            return Ok(InputWeightPrediction::P2WPKH_MAX);
+        }
+
+        Err(Error::TransactionError(format!(
+            "UTXO {} is not a P2TR or P2WPKH output of the giver key at index {}",
+            utxo.outpoint, utxo.index
+        )))
+    }
+}
+
+/// Returns the origin of `source` at `index`, falling back to the key's own
+/// fingerprint with an empty path for raw keys.
+fn key_origin(source: &KeySource, index: u32) -> Result<bip32::KeySource, Error> {
+    match source.key_origin_at(index)? {
+        Some(origin) => Ok(origin),
+        None => Ok((source.at_index(index)?.master_fingerprint(), DerivationPath::master())),
+    }
+}
+
+/// Parses a fee rate in sat/vB, such as `2` or `1.5`.
+pub fn parse_fee_rate(rate: &str) -> Result<FeeRate, Error> {
+    let sat_per_vb: f64 = rate
+        .trim()
+        .parse()
+        .map_err(|_| Error::TransactionError(format!("Invalid fee rate '{}' (expected sat/vB, e.g. 2 or 1.5)", rate)))?;
+
+    if !sat_per_vb.is_finite() || sat_per_vb < 1.0 {
+        return Err(Error::TransactionError(format!(
+            "Fee rate of {} sat/vB is below the 1 sat/vB relay minimum",
+            rate
+        )));
+    }
+
+    // FeeRate counts sat per 1000 weight units; one vbyte is four of them
+    Ok(FeeRate::from_sat_per_kwu((sat_per_vb * 250.0).ceil() as u64))
+}
+
+/// Encodes a BIP-373 participant list.
+fn participants_value(participants: &[PublicKey]) -> Vec<u8> {
+    participants.iter().flat_map(|key| key.serialize()).collect()
+}
+
+/// Lists the participants of a MuSig2 aggregate key in a PSBT input
+/// (BIP-373 `PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS`), in aggregation order.
+pub fn set_musig2_participants(input: &mut psbt::Input, aggregate: PublicKey, participants: &[PublicKey]) {
+    input.unknown.insert(
+        raw::Key {
+            type_value: PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS,
+            key: aggregate.serialize().to_vec(),
+        },
+        participants_value(participants),
+    );
+}
+
+/// Reads every MuSig2 aggregate key and its participants from a PSBT input.
+pub fn musig2_participants(input: &psbt::Input) -> Result<BTreeMap<PublicKey, Vec<PublicKey>>, Error> {
+    let invalid = || Error::TransactionError("Invalid BIP-373 participant field".to_string());
+    let mut aggregates = BTreeMap::new();
+
+    for (key, value) in &input.unknown {
+        if key.type_value != PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS {
+            continue;
+        }
+        if value.is_empty() || value.len() % 33 != 0 {
+            return Err(invalid());
+        }
+        let aggregate = PublicKey::from_slice(&key.key).map_err(|_| invalid())?;
+        let participants = value
+            .chunks(33)
+            .map(|chunk| PublicKey::from_slice(chunk).map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        aggregates.insert(aggregate, participants);
+    }
+
+    Ok(aggregates)
+}
+
+/// Adds a participant's public nonce to a PSBT input (BIP-373
+/// `PSBT_IN_MUSIG2_PUB_NONCE`), replacing any earlier one.
+pub fn set_musig2_pub_nonce(input: &mut psbt::Input, signer: MuSig2Signer, nonce: &PubNonce) {
+    input.unknown.insert(
+        raw::Key {
+            type_value: PSBT_IN_MUSIG2_PUB_NONCE,
+            key: signer.to_key_data(),
+        },
+        nonce.to_bytes().to_vec(),
+    );
+}
+
+/// Reads the public nonces in a PSBT input.
+pub fn musig2_pub_nonces(input: &psbt::Input) -> Result<BTreeMap<MuSig2Signer, PubNonce>, Error> {
+    read_musig2_field(input, PSBT_IN_MUSIG2_PUB_NONCE, |value| PubNonce::from_bytes(value).ok())
+}
+
+/// Adds a participant's partial signature to a PSBT input (BIP-373
+/// `PSBT_IN_MUSIG2_PARTIAL_SIG`), replacing any earlier one.
+pub fn set_musig2_partial_signature(input: &mut psbt::Input, signer: MuSig2Signer, signature: PartialSignature) {
+    input.unknown.insert(
+        raw::Key {
+            type_value: PSBT_IN_MUSIG2_PARTIAL_SIG,
+            key: signer.to_key_data(),
+        },
+        signature.serialize().to_vec(),
+    );
+}
+
+/// Reads the partial signatures in a PSBT input.
+pub fn musig2_partial_signatures(input: &psbt::Input) -> Result<BTreeMap<MuSig2Signer, PartialSignature>, Error> {
+    read_musig2_field(input, PSBT_IN_MUSIG2_PARTIAL_SIG, |value| PartialSignature::from_slice(value).ok())
+}
+
+/// Removes every partial signature from a PSBT input, as needed when a
+/// nonce they were made with is replaced.
+pub fn clear_musig2_partial_signatures(input: &mut psbt::Input) {
+    input.unknown.retain(|key, _| key.type_value != PSBT_IN_MUSIG2_PARTIAL_SIG);
+}
+
+fn read_musig2_field<T>(
+    input: &psbt::Input,
+    type_value: u8,
+    parse: impl Fn(&[u8]) -> Option<T>,
+) -> Result<BTreeMap<MuSig2Signer, T>, Error> {
+    input
+        .unknown
+        .iter()
+        .filter(|(key, _)| key.type_value == type_value)
+        .map(|(key, value)| {
+            let signer = MuSig2Signer::from_key_data(&key.key)?;
+            let value = parse(value)
+                .ok_or_else(|| Error::TransactionError(format!("Invalid BIP-373 value for {}", signer.participant)))?;
+            Ok((signer, value))
+        })
+        .collect()
+}
+
+/// Returns the BIP-341 keypath sighash of a PSBT input.
+pub fn key_spend_sighash(psbt: &Psbt, index: usize) -> Result<[u8; 32], Error> {
+    let prevouts = psbt
+        .inputs
+        .iter()
+        .map(|input| input.witness_utxo.clone())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| Error::TransactionError("Every input needs a witness_utxo to sign taproot".to_string()))?;
+
+    SighashCache::new(&psbt.unsigned_tx)
+        .taproot_key_spend_signature_hash(index, &Prevouts::All(&prevouts), TapSighashType::Default)
+        .map(|sighash| sighash.to_byte_array())
+        .map_err(|e| Error::TransactionError(format!("Failed to compute sighash: {}", e)))
+}
+
+/// Returns the MuSig2 context for the keypath of a PSBT input: the
+/// participants listed for its internal key, tweaked by its merkle root.
+///
+/// # Returns
+///
+/// The context and the plain aggregate key, or a TransactionError if the
+/// input has no participants for its internal key or they do not
+/// aggregate to the key being spent.
+pub fn key_path_context(input: &psbt::Input) -> Result<(KeyAggContext, PublicKey), Error> {
+    let internal_key = input
+        .tap_internal_key
+        .ok_or_else(|| Error::TransactionError("Input has no tap_internal_key".to_string()))?;
+    let (aggregate, participants) = musig2_participants(input)?
+        .into_iter()
+        .find(|(aggregate, _)| aggregate.x_only_public_key().0 == internal_key)
+        .ok_or_else(|| Error::TransactionError("Input lists no MuSig2 participants for its internal key".to_string()))?;
+
+    let points = participants.iter().map(to_point).collect::<Result<Vec<_>, _>>()?;
+    let ctx = KeyAggContext::new(points)
+        .map_err(|e| Error::TransactionError(format!("MuSig2 key aggregation failed: {}", e)))?;
+    if from_point(ctx.aggregated_pubkey_untweaked())? != aggregate {
+        return Err(Error::TransactionError(
+            "MuSig2 participants do not aggregate to the listed key".to_string(),
+        ));
+    }
+
+    let ctx = match input.tap_merkle_root {
+        Some(root) => ctx.with_taproot_tweak(&root.to_byte_array()),
+        None => ctx.with_unspendable_taproot_tweak(),
+    }
+    .map_err(|e| Error::TransactionError(format!("MuSig2 taproot tweak failed: {}", e)))?;
+
+    let output_key = TweakedPublicKey::dangerous_assume_tweaked(point_to_x_only(ctx.aggregated_pubkey())?);
+    let spends_output = input
+        .witness_utxo
+        .as_ref()
+        .is_some_and(|utxo| utxo.script_pubkey == ScriptBuf::new_p2tr_tweaked(output_key));
+    if !spends_output {
+        return Err(Error::TransactionError(
+            "MuSig2 key does not match the output being spent".to_string(),
+        ));
+    }
+
+    Ok((ctx, aggregate))
+}
+
+/// Aggregates the BIP-373 nonces and partial signatures of a keypath input
+/// into its `tap_key_sig`.
+///
+/// # Returns
+///
+/// Ok once the signature is set, or a TransactionError if a participant's
+/// nonce or partial signature is missing or invalid.
+pub fn aggregate_musig2_signature(psbt: &mut Psbt, index: usize) -> Result<(), Error> {
+    let message = key_spend_sighash(psbt, index)?;
+    let input = &psbt.inputs[index];
+    let (ctx, aggregate) = key_path_context(input)?;
+    let nonces = musig2_pub_nonces(input)?;
+    let partials = musig2_partial_signatures(input)?;
+
+    let mut public_nonces = Vec::new();
+    let mut signatures = Vec::new();
+    for participant in ctx.pubkeys() {
+        let signer = MuSig2Signer::key_path(from_point(*participant)?, aggregate);
+        let nonce = nonces
+            .get(&signer)
+            .ok_or_else(|| Error::TransactionError(format!("Missing MuSig2 nonce from {}", signer.participant)))?;
+        let partial = partials.get(&signer).ok_or_else(|| {
+            Error::TransactionError(format!("Missing MuSig2 partial signature from {}", signer.participant))
+        })?;
+        public_nonces.push((signer, nonce.clone()));
+        signatures.push(*partial);
     }
-}
\ No newline at end of file
+
+    let aggregated_nonce = AggNonce::sum(public_nonces.iter().map(|(_, nonce)| nonce.clone()));
+    for ((signer, nonce), partial) in public_nonces.iter().zip(&signatures) {
+        musig2::verify_partial(&ctx, *partial, &aggregated_nonce, to_point(&signer.participant)?, nonce, message)
+            .map_err(|e| {
+                Error::TransactionError(format!("Invalid partial signature from {}: {}", signer.participant, e))
+            })?;
+    }
+
+    let signature: [u8; 64] = musig2::aggregate_partial_signatures(&ctx, &aggregated_nonce, signatures, message)
+        .map_err(|e| Error::TransactionError(format!("Failed to aggregate MuSig2 signatures: {}", e)))?;
+    let signature = schnorr::Signature::from_slice(&signature)
+        .map_err(|e| Error::TransactionError(format!("Invalid aggregate signature: {}", e)))?;
+
+    psbt.inputs[index].tap_key_sig = Some(taproot::Signature {
+        signature,
+        sighash_type: TapSighashType::Default,
+    });
+    Ok(())
+}
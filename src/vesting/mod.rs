@@ -0,0 +1,194 @@
+use bitcoin::{Amount, Network};
+use crate::batch::{BatchGift, BatchPlan};
+use crate::fee::P2TR_DUST_LIMIT;
+use crate::timelock::Timelock;
+use crate::Error;
+
+/// Average time between blocks, used to estimate when block locks end.
+const SECONDS_PER_BLOCK: u32 = 600;
+
+/// One part of a vesting gift, spendable by the receiver once its timelock passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tranche {
+    pub timelock: Timelock,
+    pub amount: Amount,
+}
+
+/// A total amount split into tranches that unlock one after another.
+///
+/// Every tranche becomes its own gift output, with the same giver and
+/// receiver keys at its own derivation index, so that all of them can be
+/// funded in one transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VestingSchedule {
+    pub total: Amount,
+    pub tranches: Vec<Tranche>,
+}
+
+impl VestingSchedule {
+    /// Splits `total` across the given timelocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `total` - Amount vested over the whole schedule
+    /// * `timelocks` - Unlock condition of each tranche, in order
+    /// * `shares` - Percentage of `total` in each tranche, summing to 100;
+    ///   `None` splits evenly
+    ///
+    /// # Returns
+    ///
+    /// The schedule, with any rounding remainder in the last tranche, or a
+    /// VestingError if a tranche would be below the dust limit.
+    pub fn new(total: Amount, timelocks: Vec<Timelock>, shares: Option<Vec<u64>>) -> Result<Self, Error> {
+        if timelocks.is_empty() {
+            return Err(Error::VestingError("A vesting schedule needs at least one tranche".to_string()));
+        }
+        let shares = match shares {
+            Some(shares) if shares.len() != timelocks.len() => {
+                return Err(Error::VestingError("Every tranche needs a share".to_string()));
+            }
+            Some(shares) if shares.iter().sum::<u64>() != 100 => {
+                return Err(Error::VestingError(format!(
+                    "Tranche shares add up to {}%, not 100%",
+                    shares.iter().sum::<u64>()
+                )));
+            }
+            Some(shares) => shares,
+            None => vec![1; timelocks.len()],
+        };
+        for timelock in &timelocks {
+            timelock.validate()?;
+        }
+
+        let weight: u64 = shares.iter().sum();
+        let mut amounts: Vec<u64> = shares
+            .iter()
+            .map(|share| (u128::from(total.to_sat()) * u128::from(*share) / u128::from(weight)) as u64)
+            .collect();
+        let remainder = total.to_sat() - amounts.iter().sum::<u64>();
+        *amounts.last_mut().expect("at least one tranche") += remainder;
+
+        let tranches: Vec<Tranche> = timelocks
+            .into_iter()
+            .zip(amounts)
+            .map(|(timelock, amount)| Tranche {
+                timelock,
+                amount: Amount::from_sat(amount),
+            })
+            .collect();
+        if let Some(position) = tranches.iter().position(|tranche| tranche.amount < P2TR_DUST_LIMIT) {
+            return Err(Error::VestingError(format!(
+                "Tranche {} would hold {} sat, below the {} sat dust limit",
+                position + 1,
+                tranches[position].amount.to_sat(),
+                P2TR_DUST_LIMIT.to_sat()
+            )));
+        }
+
+        Ok(Self { total, tranches })
+    }
+
+    /// Parses a schedule of comma-separated tranches, each a timelock in
+    /// the notation `Timelock` parses with an optional `:percent` share,
+    /// e.g. `2027-01-01:40,2028-01-01:60`. Either every tranche has a
+    /// share or none does.
+    pub fn parse(total: Amount, spec: &str) -> Result<Self, Error> {
+        let mut timelocks = Vec::new();
+        let mut shares = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            // `height:900000` is a timelock of its own, so the share is the
+            // part after the last colon only when one follows the timelock
+            let (timelock, share) = match entry.rsplit_once(':') {
+                Some((timelock, share)) if timelock != "height" => (timelock, Some(share)),
+                _ => (entry, None),
+            };
+            timelocks.push(timelock.parse()?);
+            if let Some(share) = share {
+                let share = share
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| Error::VestingError(format!("Invalid tranche share in '{}'", entry)))?;
+                shares.push(share);
+            }
+        }
+
+        let shares = match shares.len() {
+            0 => None,
+            n if n == timelocks.len() => Some(shares),
+            _ => return Err(Error::VestingError("Give a share for every tranche or for none".to_string())),
+        };
+        Self::new(total, timelocks, shares)
+    }
+
+    /// Splits `total` evenly into `count` tranches unlocking every `step`
+    /// after funding: `step`, twice `step` and so on. `step` must be a
+    /// relative timelock, and the last tranche must still fit BIP-68.
+    pub fn every(total: Amount, count: u32, step: Timelock) -> Result<Self, Error> {
+        let timelocks = (1..=count)
+            .map(|n| {
+                let timelock = match step {
+                    Timelock::Blocks(blocks) => blocks.checked_mul(n).map(Timelock::Blocks),
+                    Timelock::Seconds(seconds) => seconds.checked_mul(n).map(Timelock::Seconds),
+                    _ => {
+                        return Err(Error::VestingError(
+                            "A vesting step must be relative, in blocks or a duration".to_string(),
+                        ))
+                    }
+                };
+                let timelock = timelock
+                    .ok_or_else(|| Error::TimelockError("Vesting schedule is too long".to_string()))?;
+                timelock.validate().map(|_| timelock)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Self::new(total, timelocks, None)
+    }
+
+    /// Splits `total` evenly into `count` tranches unlocking on the same
+    /// day of successive years, from the `YYYY-MM-DD` date `first`.
+    pub fn yearly(total: Amount, count: u32, first: &str) -> Result<Self, Error> {
+        let invalid = || Error::TimelockError(format!("Invalid date '{}' (expected YYYY-MM-DD)", first));
+        let (year, rest) = first.trim().split_once('-').ok_or_else(invalid)?;
+        let year: u32 = year.parse().map_err(|_| invalid())?;
+
+        let timelocks = (0..count)
+            .map(|n| Timelock::unlock_date(&format!("{:04}-{}", year + n, rest)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Self::new(total, timelocks, None)
+    }
+
+    /// Turns each tranche into a gift from `giver` to `receiver`, at
+    /// consecutive derivation indices starting at `first_index`.
+    pub fn plan(&self, giver: &str, receiver: &str, first_index: u32, network: Network) -> Result<BatchPlan, Error> {
+        let count = self.tranches.len();
+        let gifts = self
+            .tranches
+            .iter()
+            .enumerate()
+            .map(|(position, tranche)| BatchGift {
+                label: format!("tranche {}/{}", position + 1, count),
+                receiver: receiver.to_string(),
+                amount: tranche.amount,
+                timelock: tranche.timelock,
+                index: None,
+            })
+            .collect();
+
+        BatchPlan::new(giver, gifts, first_index, network)
+    }
+}
+
+/// Describes when a tranche's receiver leaf becomes spendable.
+pub fn spendable_from(timelock: &Timelock) -> String {
+    match timelock {
+        Timelock::Blocks(blocks) => format!(
+            "{} blocks after funding (about {} days)",
+            blocks,
+            (u64::from(*blocks) * u64::from(SECONDS_PER_BLOCK) + 43_200) / 86_400
+        ),
+        Timelock::Seconds(_) => format!("{} after funding", timelock),
+        Timelock::UnlockHeight(height) => format!("block height {}", height),
+        Timelock::UnlockTime(_) => format!("{} (median time past)", timelock),
+    }
+}
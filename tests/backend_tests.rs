@@ -0,0 +1,193 @@
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{Amount, FeeRate, Txid};
+use serde_json::json;
+use mallowbtc::backend::{self, BitcoindBackend, ElectrumBackend, EsploraBackend, MockBackend};
+use mallowbtc::test_harness::fixtures::{gift_transaction, payment};
+use mallowbtc::test_harness::{FakeServer, Reply};
+use mallowbtc::{ChainBackend, Error};
+
+/// Funding looks up giver UTXOs, and the gift is found once the funding transaction is broadcast
+#[test]
+fn test_fund_and_find_gift_through_backend() {
+    let gift_tx = gift_transaction(144);
+    let chain = MockBackend::new(100).with_fee_rate(FeeRate::from_sat_per_vb_u32(3));
+    chain.add_transaction(payment(gift_tx.giver_script_pubkey(2).unwrap(), 200_000), Some(90));
+    chain.add_transaction(payment(gift_tx.giver_script_pubkey(30).unwrap(), 50_000), Some(95));
+
+    // Index 30 lies beyond the searched indices
+    let utxos = backend::giver_utxos(&chain, &gift_tx, 0..20).expect("Should look up UTXOs");
+    assert_eq!(utxos.len(), 1);
+    assert_eq!((utxos[0].index, utxos[0].confirmation_height), (2, Some(90)));
+
+    let fee_rate = chain.estimate_fee_rate(backend::DEFAULT_FEE_TARGET).unwrap();
+    let psbt = gift_tx
+        .fund(&utxos, Amount::from_sat(100_000), fee_rate, 3)
+        .expect("Should fund gift");
+    assert!(backend::gift_utxos(&chain, &gift_tx).unwrap().is_empty());
+
+    // The mock checks no signatures, so the unsigned transaction stands in for the signed one
+    let funding = psbt.unsigned_tx;
+    let txid = chain.broadcast(&funding).expect("Should broadcast");
+    let gift = backend::gift_utxos(&chain, &gift_tx).unwrap();
+    assert_eq!(gift.len(), 1);
+    assert_eq!(gift[0].outpoint.txid, txid);
+    assert_eq!(gift[0].confirmation_height, None);
+    // Only the change, at giver index 3, is left to the giver
+    let left = backend::giver_utxos(&chain, &gift_tx, 0..20).unwrap();
+    assert_eq!(left.iter().map(|utxo| utxo.index).collect::<Vec<_>>(), vec![3]);
+
+    chain.mine(10);
+    assert_eq!(chain.tip_height().unwrap(), 110);
+    assert_eq!(chain.confirmation(&txid), Some(Some(101)));
+    let gift = backend::gift_utxos(&chain, &gift_tx).unwrap();
+    let timelock = gift_tx.script().timelock();
+    assert_eq!(timelock.blocks_remaining(gift[0].confirmation_height, 110), Some(134));
+    assert_eq!(chain.transaction(&txid).unwrap(), Some(funding.clone()));
+
+    // Spending the same giver UTXO again is a double spend
+    let mut conflict = funding;
+    conflict.output[0].value = Amount::from_sat(90_000);
+    assert!(matches!(chain.broadcast(&conflict), Err(Error::BackendError(_))));
+}
+
+/// Without data, estimates fail the way a fresh regtest node's do
+#[test]
+fn test_mock_fee_estimate_unavailable() {
+    let chain = MockBackend::new(0);
+    assert!(matches!(chain.estimate_fee_rate(6), Err(Error::BackendError(_))));
+    assert_eq!(chain.transaction(&Txid::all_zeros()).unwrap(), None);
+}
+
+/// The Esplora client reads UTXOs, history, tip, transactions and fee estimates, and posts broadcasts
+#[test]
+fn test_esplora_backend() {
+    let gift_tx = gift_transaction(144);
+    let script_pubkey = gift_tx.giver_script_pubkey(0).unwrap();
+    let tx = payment(script_pubkey.clone(), 70_000);
+    let txid = tx.compute_txid();
+    let script_hash = sha256::Hash::hash(script_pubkey.as_bytes());
+
+    let server = FakeServer::esplora(vec![
+        (
+            &format!("GET /scripthash/{}/utxo", script_hash),
+            Reply::Ok(json!([
+                { "txid": txid, "vout": 0, "value": 70_000, "status": { "confirmed": true, "block_height": 120 } },
+                { "txid": txid, "vout": 1, "value": 5_000, "status": { "confirmed": false } },
+            ])),
+        ),
+        (
+            &format!("GET /scripthash/{}/txs", script_hash),
+            Reply::Ok(json!([{ "txid": txid, "status": { "confirmed": true, "block_height": 120 } }])),
+        ),
+        ("GET /blocks/tip/height", Reply::Ok(json!("130"))),
+        (&format!("GET /tx/{}/hex", txid), Reply::Ok(json!(serialize_hex(&tx)))),
+        ("GET /fee-estimates", Reply::Ok(json!({ "1": 20.5, "3": 10.0, "6": 4.2, "144": 1.0 }))),
+        ("POST /tx", Reply::Ok(json!(txid.to_string()))),
+    ])
+    .expect("Should start fake Esplora");
+    let esplora = EsploraBackend::new(&format!("{}/", server.url()));
+
+    let utxos = esplora.script_utxos(&script_pubkey).expect("Should fetch UTXOs");
+    assert_eq!(utxos.len(), 2);
+    assert_eq!(utxos[0].confirmation_height, Some(120));
+    assert_eq!(utxos[0].txout.script_pubkey, script_pubkey);
+    assert_eq!(utxos[1].confirmation_height, None);
+    assert_eq!(esplora.script_history(&script_pubkey).unwrap(), Some(vec![(tx.clone(), Some(120))]));
+
+    assert_eq!(esplora.tip_height().unwrap(), 130);
+    assert_eq!(esplora.transaction(&txid).unwrap(), Some(tx.clone()));
+    assert_eq!(esplora.transaction(&Txid::all_zeros()).unwrap(), None);
+    // 4.2 sat/vB for 6 blocks, and the closest shorter target for 5
+    assert_eq!(esplora.estimate_fee_rate(6).unwrap(), FeeRate::from_sat_per_kwu(1050));
+    assert_eq!(esplora.estimate_fee_rate(5).unwrap(), FeeRate::from_sat_per_kwu(2500));
+    assert_eq!(esplora.broadcast(&tx).unwrap(), txid);
+
+    let requests = server.requests();
+    assert_eq!(requests.last().unwrap(), &format!("POST /tx {}", serialize_hex(&tx)));
+}
+
+/// The Electrum client speaks line-delimited JSON-RPC with reversed scripthashes
+#[test]
+fn test_electrum_backend() {
+    let gift_tx = gift_transaction(144);
+    let script_pubkey = gift_tx.giver_script_pubkey(0).unwrap();
+    let tx = payment(script_pubkey.clone(), 70_000);
+    let txid = tx.compute_txid();
+    let mut reversed = sha256::Hash::hash(script_pubkey.as_bytes()).to_byte_array();
+    reversed.reverse();
+    assert_eq!(ElectrumBackend::script_hash(&script_pubkey), hex::encode(reversed));
+
+    let listunspent = format!("blockchain.scripthash.listunspent [\"{}\"]", hex::encode(reversed));
+    let server = FakeServer::electrum(vec![
+        (
+            &listunspent,
+            Reply::Ok(json!([
+                { "tx_hash": txid, "tx_pos": 0, "height": 120, "value": 70_000 },
+                { "tx_hash": txid, "tx_pos": 1, "height": 0, "value": 5_000 },
+            ])),
+        ),
+        ("blockchain.scripthash.listunspent", Reply::Ok(json!([]))),
+        ("blockchain.scripthash.get_history", Reply::Ok(json!([{ "tx_hash": txid, "height": 0 }]))),
+        ("blockchain.headers.subscribe", Reply::Ok(json!({ "height": 130, "hex": "00" }))),
+        (&format!("blockchain.transaction.get [\"{}\"]", txid), Reply::Ok(json!(serialize_hex(&tx)))),
+        ("blockchain.transaction.get", Reply::Err(2, "Transaction not found".to_string())),
+        ("blockchain.estimatefee", Reply::Ok(json!(0.00002))),
+        ("blockchain.transaction.broadcast", Reply::Ok(json!(txid.to_string()))),
+    ])
+    .expect("Should start fake Electrum server");
+    let electrum = ElectrumBackend::new(&format!("tcp://{}", server.address())).unwrap();
+
+    let utxos = electrum.script_utxos(&script_pubkey).expect("Should fetch UTXOs");
+    assert_eq!(utxos.iter().map(|utxo| utxo.confirmation_height).collect::<Vec<_>>(), vec![Some(120), None]);
+    assert!(electrum.script_utxos(&gift_tx.giver_script_pubkey(1).unwrap()).unwrap().is_empty());
+    assert_eq!(electrum.script_history(&script_pubkey).unwrap(), Some(vec![(tx.clone(), None)]));
+    assert_eq!(electrum.tip_height().unwrap(), 130);
+    assert_eq!(electrum.transaction(&txid).unwrap(), Some(tx.clone()));
+    assert_eq!(electrum.transaction(&Txid::all_zeros()).unwrap(), None);
+    assert_eq!(electrum.estimate_fee_rate(6).unwrap(), FeeRate::from_sat_per_vb_u32(2));
+    assert_eq!(electrum.broadcast(&tx).unwrap(), txid);
+
+    // Every call went over the one connection
+    assert_eq!(server.requests().len(), 9);
+    assert!(ElectrumBackend::new("ssl://electrum.example:50002").is_err());
+}
+
+/// The Bitcoin Core client sends authenticated JSON-RPC and maps its error codes
+#[test]
+fn test_bitcoind_backend() {
+    let gift_tx = gift_transaction(144);
+    let script_pubkey = gift_tx.giver_script_pubkey(0).unwrap();
+    let tx = payment(script_pubkey.clone(), 70_000);
+    let txid = tx.compute_txid();
+
+    let server = FakeServer::bitcoind(vec![
+        (
+            "scantxoutset",
+            Reply::Ok(json!({ "success": true, "unspents": [
+                { "txid": txid, "vout": 0, "scriptPubKey": script_pubkey.to_hex_string(), "amount": 0.0007, "height": 120 },
+            ]})),
+        ),
+        ("getblockcount", Reply::Ok(json!(130))),
+        (&format!("getrawtransaction [\"{}\"]", txid), Reply::Ok(json!(serialize_hex(&tx)))),
+        ("getrawtransaction", Reply::Err(-5, "No such mempool or blockchain transaction".to_string())),
+        ("estimatesmartfee", Reply::Ok(json!({ "feerate": 0.00002, "blocks": 6 }))),
+        ("sendrawtransaction", Reply::Err(-26, "non-final".to_string())),
+    ])
+    .expect("Should start fake bitcoind");
+    let bitcoind = BitcoindBackend::new(&server.url()).with_user_pass("user", "pass");
+
+    let utxos = bitcoind.script_utxos(&script_pubkey).expect("Should scan UTXO set");
+    assert_eq!(utxos[0].txout.value, Amount::from_sat(70_000));
+    assert_eq!(utxos[0].confirmation_height, Some(120));
+    assert_eq!(bitcoind.script_history(&script_pubkey).unwrap(), None);
+    assert_eq!(bitcoind.tip_height().unwrap(), 130);
+    assert_eq!(bitcoind.transaction(&txid).unwrap(), Some(tx.clone()));
+    assert_eq!(bitcoind.transaction(&Txid::all_zeros()).unwrap(), None);
+    assert_eq!(bitcoind.estimate_fee_rate(6).unwrap(), FeeRate::from_sat_per_vb_u32(2));
+    assert!(matches!(bitcoind.broadcast(&tx), Err(Error::BackendError(message)) if message.contains("non-final")));
+    assert_eq!(
+        server.requests()[0],
+        format!("scantxoutset [\"start\",[\"raw({})\"]]", script_pubkey.to_hex_string())
+    );
+}
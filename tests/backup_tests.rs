@@ -0,0 +1,61 @@
+use mallowbtc::backup::BACKUP_VERSION;
+use mallowbtc::{Error, GiftBackup, GiftRecord, GiftScript, Timelock};
+use mallowbtc::test_harness::fixtures::{GIVER_DESC, RECEIVER_DESC};
+
+fn backup() -> (GiftRecord, GiftBackup) {
+    let script = GiftScript::new(144).with_clawback(Timelock::Blocks(1000)).with_leaf_depths(vec![1, 1]);
+    let mut record = GiftRecord::new(GIVER_DESC, RECEIVER_DESC, 3, &script)
+        .expect("Should record gift")
+        .with_label(Some("Graduation".to_string()));
+    record.funding_outpoint = Some(format!("{}:1", "ab".repeat(32)));
+    let backup = GiftBackup::new(&record).expect("Should back up gift");
+    (record, backup)
+}
+
+/// A backup survives encoding and rebuilds the same gift, spending information included
+#[test]
+fn test_backup_round_trip() {
+    let (record, backup) = backup();
+    assert_eq!(backup.version, BACKUP_VERSION);
+    assert_eq!(backup.gift.leaves.len(), 2);
+    assert_eq!(backup.gift.giver.path.as_deref(), Some("[73c5da0a/86'/1'/0'/0/3]"));
+
+    let text = serde_json::to_string_pretty(&backup).unwrap();
+    let restored = GiftBackup::from_json(&text).expect("Should parse backup");
+    assert_eq!(restored, backup);
+
+    let rebuilt = restored.verify().expect("Backup should verify");
+    assert_eq!(rebuilt.address, record.address);
+    assert_eq!(rebuilt.descriptor, record.descriptor);
+    assert_eq!(rebuilt.label, record.label);
+    assert_eq!(rebuilt.created_at, record.created_at);
+    assert_eq!(rebuilt.funding_outpoint, record.funding_outpoint);
+    assert_eq!(rebuilt.funding_txid, Some("ab".repeat(32)));
+}
+
+/// Damaged files fail the checksum, and consistent but wrong ones fail verification
+#[test]
+fn test_backup_rejects_tampering() {
+    let (_, backup) = backup();
+
+    let text = serde_json::to_string(&backup).unwrap().replace("Graduation", "Graduatiom");
+    assert!(matches!(GiftBackup::from_json(&text), Err(Error::BackupError(_))));
+    assert!(GiftBackup::from_json("{\"format\": \"something-else\"}").is_err());
+
+    let mut newer = backup.clone();
+    newer.version = BACKUP_VERSION + 1;
+    assert!(GiftBackup::from_json(&serde_json::to_string(&newer).unwrap()).is_err());
+
+    // Another index derives other keys, so the address no longer matches
+    let mut wrong_index = backup.clone();
+    wrong_index.gift.index = 4;
+    wrong_index.checksum = wrong_index.gift.checksum().unwrap();
+    let reparsed = GiftBackup::from_json(&serde_json::to_string(&wrong_index).unwrap()).unwrap();
+    assert!(matches!(reparsed.verify(), Err(Error::BackupError(message)) if message.contains("address")));
+
+    // Leaves in the wrong order are caught even though the address matches
+    let mut wrong_leaf = backup;
+    wrong_leaf.gift.leaves.swap(0, 1);
+    wrong_leaf.checksum = wrong_leaf.gift.checksum().unwrap();
+    assert!(matches!(wrong_leaf.verify(), Err(Error::BackupError(message)) if message.contains("leaves")));
+}
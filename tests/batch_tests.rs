@@ -0,0 +1,87 @@
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, FeeRate, Network, OutPoint, Txid};
+use mallowbtc::batch::{parse_csv_manifest, parse_json_manifest};
+use mallowbtc::{BatchPlan, Error, GiftUtxo, SelectionOptions, Timelock};
+use mallowbtc::test_harness::fixtures::{GIVER_DESC, RECEIVER_DESC};
+
+fn csv_manifest() -> String {
+    format!(
+        "label,receiver,amount,timelock\n# comment\n\"Alice, 18th\",{r},100000,52560\n\nBob,{r},50000,2030-01-01\n",
+        r = RECEIVER_DESC
+    )
+}
+
+/// CSV and JSON manifests parse into the same rows, quotes and comments included
+#[test]
+fn test_parse_manifests() {
+    let rows = parse_csv_manifest(&csv_manifest()).expect("Should parse CSV");
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].label, "Alice, 18th");
+    assert_eq!(rows[0].amount, Amount::from_sat(100_000));
+    assert_eq!(rows[0].timelock, Timelock::Blocks(52560));
+    assert_eq!(rows[1].timelock, "2030-01-01".parse().unwrap());
+    assert_eq!(rows[1].index, None);
+
+    let json = format!(
+        r#"[{{"label": "Alice, 18th", "receiver": "{r}", "amount": 100000, "timelock": 52560}},
+            {{"label": "Bob", "receiver": "{r}", "amount": 50000, "timelock": "2030-01-01"}}]"#,
+        r = RECEIVER_DESC
+    );
+    assert_eq!(parse_json_manifest(&json).expect("Should parse JSON"), rows);
+
+    let missing_column = "label,receiver,amount\nAlice,x,1\n";
+    assert!(matches!(parse_csv_manifest(missing_column), Err(Error::BatchError(_))));
+    let bad_amount = format!("label,receiver,amount,timelock\nAlice,{},lots,52560\n", RECEIVER_DESC);
+    assert!(matches!(parse_csv_manifest(&bad_amount), Err(Error::BatchError(_))));
+}
+
+/// One PSBT pays every gift in manifest order, and the report describes each output
+#[test]
+fn test_batch_funding_and_report() {
+    let rows = parse_csv_manifest(&csv_manifest()).unwrap();
+    let plan = BatchPlan::new(GIVER_DESC, rows, 5, Network::Regtest).expect("Should plan batch");
+    assert_eq!(plan.gifts.iter().map(|planned| planned.index).collect::<Vec<_>>(), vec![5, 6]);
+    assert_eq!(plan.total(), Amount::from_sat(150_000));
+
+    let funder = &plan.gifts[0].transaction;
+    let utxo = GiftUtxo::new(
+        OutPoint::new(Txid::from_byte_array([1; 32]), 0),
+        Amount::from_sat(200_000),
+        funder.giver_script_pubkey(3).unwrap(),
+        3,
+    );
+    let psbt = plan
+        .fund(&[utxo], FeeRate::from_sat_per_vb_u32(2), 9, &SelectionOptions::default())
+        .expect("Should fund batch");
+
+    let outputs = &psbt.unsigned_tx.output;
+    assert_eq!(outputs.len(), 3);
+    for (output, planned) in outputs.iter().zip(&plan.gifts) {
+        assert_eq!(output.script_pubkey, planned.address.script_pubkey());
+        assert_eq!(output.value, planned.gift.amount);
+    }
+    assert_eq!(outputs[2].script_pubkey, funder.giver_script_pubkey(9).unwrap());
+
+    let report = plan.report(&psbt).expect("Should build report");
+    assert_eq!(report.funding_txid, psbt.unsigned_tx.compute_txid().to_string());
+    assert_eq!(report.gifts.len(), 2);
+    for (vout, gift) in report.gifts.iter().enumerate() {
+        assert_eq!(gift.address, plan.gifts[vout].address.to_string());
+        assert!(gift.outpoint.ends_with(&format!(":{}", vout)));
+        assert!(gift.descriptor.starts_with("tr("));
+        assert_eq!(gift.leaves.len(), 1);
+        assert!(!gift.leaves[0].control_block.is_empty());
+    }
+}
+
+/// Two rows at the same index would pay one address twice and are rejected
+#[test]
+fn test_batch_rejects_duplicate_addresses() {
+    let mut rows = parse_csv_manifest(&csv_manifest()).unwrap();
+    rows[1].timelock = rows[0].timelock;
+    rows[1].index = Some(0);
+    rows[0].index = Some(0);
+
+    let result = BatchPlan::new(GIVER_DESC, rows, 0, Network::Regtest);
+    assert!(matches!(result, Err(Error::BatchError(_))));
+}
@@ -0,0 +1,160 @@
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, FeeRate, OutPoint, Txid};
+use std::str::FromStr;
+use mallowbtc::coin_selection::select_utxos;
+use mallowbtc::{CoinSelection, Error, GiftUtxo, SelectionOptions};
+use mallowbtc::test_harness::fixtures::{gift_transaction, giver_utxo};
+
+fn options(strategy: CoinSelection) -> SelectionOptions {
+    SelectionOptions {
+        strategy,
+        ..Default::default()
+    }
+}
+
+fn selected(utxos: &[GiftUtxo]) -> Vec<u8> {
+    utxos.iter().map(|utxo| utxo.outpoint.txid.to_byte_array()[0]).collect()
+}
+
+/// Largest-first and oldest-first pick the UTXOs their names promise
+#[test]
+fn test_largest_and_oldest_first() {
+    let tx = gift_transaction(52560);
+    let utxos = [
+        giver_utxo(&tx, 1, 60_000, 0).with_confirmation_height(300),
+        giver_utxo(&tx, 2, 200_000, 0).with_confirmation_height(200),
+        giver_utxo(&tx, 3, 80_000, 0).with_confirmation_height(100),
+    ];
+    let fee_rate = FeeRate::from_sat_per_vb_u32(2);
+    let amount = Amount::from_sat(50_000);
+
+    let largest = select_utxos(&tx, &utxos, amount, fee_rate, 9, &options(CoinSelection::LargestFirst)).unwrap();
+    assert_eq!(selected(&largest), [2]);
+
+    let oldest = select_utxos(&tx, &utxos, amount, fee_rate, 9, &options(CoinSelection::OldestFirst)).unwrap();
+    assert_eq!(selected(&oldest), [3]);
+
+    let two = select_utxos(&tx, &utxos, Amount::from_sat(100_000), fee_rate, 9, &options(CoinSelection::OldestFirst)).unwrap();
+    assert_eq!(selected(&two), [3, 2]);
+
+    let all = select_utxos(&tx, &utxos, amount, fee_rate, 9, &options(CoinSelection::All)).unwrap();
+    assert_eq!(all.len(), 3);
+
+    let short = select_utxos(&tx, &utxos, Amount::from_sat(400_000), fee_rate, 9, &options(CoinSelection::LargestFirst));
+    assert!(matches!(short, Err(Error::CoinSelectionError(_))));
+}
+
+/// Branch and bound finds the UTXO that funds the gift without change
+#[test]
+fn test_branch_and_bound_is_changeless() {
+    let tx = gift_transaction(52560);
+    let fee_rate = FeeRate::from_sat_per_vb_u32(4);
+    let amount = Amount::from_sat(50_000);
+
+    // One P2TR keypath input and the gift output come to 444 weight units
+    let utxos = [
+        giver_utxo(&tx, 1, 150_000, 0).with_confirmation_height(100),
+        giver_utxo(&tx, 2, 50_444, 1).with_confirmation_height(100),
+        giver_utxo(&tx, 3, 250_000, 2).with_confirmation_height(100),
+    ];
+
+    let psbt = tx
+        .fund_with_selection(&utxos, amount, fee_rate, 9, &options(CoinSelection::BranchAndBound))
+        .expect("Should fund without change");
+    assert_eq!(psbt.unsigned_tx.input.len(), 1);
+    assert_eq!(psbt.unsigned_tx.input[0].previous_output, utxos[1].outpoint);
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    assert_eq!(psbt.fee().unwrap(), Amount::from_sat(444));
+}
+
+/// Included UTXOs are always spent, excluded ones never, and conflicts are refused
+#[test]
+fn test_includes_and_excludes() {
+    let tx = gift_transaction(52560);
+    let utxos = [
+        giver_utxo(&tx, 1, 60_000, 0).with_confirmation_height(300),
+        giver_utxo(&tx, 2, 200_000, 0).with_confirmation_height(200),
+        giver_utxo(&tx, 3, 80_000, 0).with_confirmation_height(100),
+    ];
+    let fee_rate = FeeRate::from_sat_per_vb_u32(2);
+    let amount = Amount::from_sat(50_000);
+
+    let mut opts = options(CoinSelection::LargestFirst);
+    opts.exclude = vec![utxos[1].outpoint];
+    assert_eq!(selected(&select_utxos(&tx, &utxos, amount, fee_rate, 9, &opts).unwrap()), [3]);
+
+    opts.include = vec![utxos[0].outpoint];
+    assert_eq!(selected(&select_utxos(&tx, &utxos, amount, fee_rate, 9, &opts).unwrap()), [1]);
+
+    opts.exclude = vec![utxos[0].outpoint];
+    assert!(matches!(select_utxos(&tx, &utxos, amount, fee_rate, 9, &opts), Err(Error::CoinSelectionError(_))));
+
+    let unknown = OutPoint::new(Txid::from_byte_array([7; 32]), 0);
+    opts.include = vec![unknown];
+    opts.exclude = Vec::new();
+    assert!(matches!(select_utxos(&tx, &utxos, amount, fee_rate, 9, &opts), Err(Error::CoinSelectionError(_))));
+}
+
+/// Privacy mode only spends UTXOs of one giver address together
+#[test]
+fn test_privacy_mode() {
+    let tx = gift_transaction(52560);
+    let utxos = [
+        giver_utxo(&tx, 1, 40_000, 0).with_confirmation_height(100),
+        giver_utxo(&tx, 2, 40_000, 1).with_confirmation_height(100),
+        giver_utxo(&tx, 3, 30_000, 1).with_confirmation_height(100),
+    ];
+    let fee_rate = FeeRate::from_sat_per_vb_u32(2);
+
+    let mut opts = options(CoinSelection::LargestFirst);
+    opts.privacy = true;
+    let chosen = select_utxos(&tx, &utxos, Amount::from_sat(60_000), fee_rate, 9, &opts).unwrap();
+    assert_eq!(selected(&chosen), [2, 3]);
+
+    // Without privacy mode the two largest UTXOs are mixed
+    let mixed = select_utxos(&tx, &utxos, Amount::from_sat(60_000), fee_rate, 9, &options(CoinSelection::LargestFirst)).unwrap();
+    assert_eq!(mixed.len(), 2);
+    assert_ne!(mixed[0].script_pubkey, mixed[1].script_pubkey);
+
+    let too_much = select_utxos(&tx, &utxos, Amount::from_sat(75_000), fee_rate, 9, &opts);
+    assert!(matches!(too_much, Err(Error::CoinSelectionError(_))));
+
+    opts.include = vec![utxos[0].outpoint, utxos[1].outpoint];
+    assert!(matches!(select_utxos(&tx, &utxos, Amount::from_sat(10_000), fee_rate, 9, &opts), Err(Error::CoinSelectionError(_))));
+}
+
+/// Spending everything in privacy mode skips script groups too small for the gift
+#[test]
+fn test_privacy_mode_spend_all() {
+    let tx = gift_transaction(52560);
+    let utxos = [
+        giver_utxo(&tx, 1, 20_000, 0).with_confirmation_height(100),
+        giver_utxo(&tx, 2, 40_000, 1).with_confirmation_height(100),
+        giver_utxo(&tx, 3, 30_000, 1).with_confirmation_height(100),
+    ];
+    let fee_rate = FeeRate::from_sat_per_vb_u32(2);
+
+    let mut opts = options(CoinSelection::All);
+    opts.privacy = true;
+    let chosen = select_utxos(&tx, &utxos, Amount::from_sat(60_000), fee_rate, 9, &opts).unwrap();
+    assert_eq!(selected(&chosen), [2, 3]);
+
+    // Without privacy mode every UTXO is spent, but only if they cover the fee too
+    let all = select_utxos(&tx, &utxos, Amount::from_sat(60_000), fee_rate, 9, &options(CoinSelection::All)).unwrap();
+    assert_eq!(selected(&all), [1, 2, 3]);
+    let short = select_utxos(&tx, &utxos, Amount::from_sat(90_000), fee_rate, 9, &options(CoinSelection::All));
+    assert!(matches!(short, Err(Error::CoinSelectionError(_))));
+
+    let too_much = select_utxos(&tx, &utxos, Amount::from_sat(70_000), fee_rate, 9, &opts);
+    assert!(matches!(too_much, Err(Error::CoinSelectionError(_))));
+}
+
+/// Strategies parse from their command-line names
+#[test]
+fn test_coin_selection_from_str() {
+    assert_eq!(CoinSelection::from_str("bnb").unwrap(), CoinSelection::BranchAndBound);
+    assert_eq!(CoinSelection::from_str("largest-first").unwrap(), CoinSelection::LargestFirst);
+    assert_eq!(CoinSelection::from_str("oldest-first").unwrap(), CoinSelection::OldestFirst);
+    assert_eq!(CoinSelection::from_str("all").unwrap(), CoinSelection::All);
+    assert!(CoinSelection::from_str("random").is_err());
+}
@@ -0,0 +1,140 @@
+use bitcoin::hashes::Hash;
+use musig2::BinaryEncoding;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::{Amount, FeeRate, OutPoint, Txid};
+use std::path::PathBuf;
+use mallowbtc::{CoopSession, Error, GiftKeys, GiftScript, GiftTransaction, NonceState};
+
+fn secret(n: u8) -> SecretKey {
+    let mut bytes = [0u8; 32];
+    bytes[31] = n;
+    SecretKey::from_slice(&bytes).expect("valid secret key")
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mallowbtc-{}-{}.json", name, std::process::id()))
+}
+
+fn start_session() -> CoopSession {
+    let secp = Secp256k1::new();
+    let keys = GiftKeys::new(secret(1).public_key(&secp), secret(2).public_key(&secp));
+    let gift_tx = GiftTransaction::new(keys, GiftScript::new(52560));
+    let destination = gift_tx.giver_script_pubkey(0).unwrap();
+    let psbt = gift_tx
+        .spend_cooperatively(
+            OutPoint::new(Txid::from_byte_array([7; 32]), 0),
+            Amount::from_sat(100_000),
+            destination,
+            FeeRate::from_sat_per_vb_u32(2),
+        )
+        .expect("Should build keypath PSBT");
+
+    CoopSession::start(&gift_tx, &psbt).expect("Should start session")
+}
+
+/// Giver and receiver exchange nonces and partial signatures through files and get a keypath spend
+#[test]
+fn test_cooperative_spend_round_trip() {
+    let session_path = temp_file("session");
+    let giver_nonce = temp_file("giver-nonce");
+    let receiver_nonce = temp_file("receiver-nonce");
+
+    start_session().save(&session_path).unwrap();
+
+    for (key, nonce_path) in [(secret(1), &giver_nonce), (secret(2), &receiver_nonce)] {
+        let mut session = CoopSession::load(&session_path).unwrap();
+        let state = session.generate_nonce(&key, None).expect("Should generate nonce");
+        state.save(nonce_path).unwrap();
+        session.save(&session_path).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(nonce_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "Nonce files are readable only by their owner");
+        }
+    }
+
+    for (key, nonce_path) in [(secret(2), &receiver_nonce), (secret(1), &giver_nonce)] {
+        let mut session = CoopSession::load(&session_path).unwrap();
+        let secnonce = NonceState::take_secret_nonce(nonce_path, &session, &key).expect("Should take nonce");
+        session.sign(&key, secnonce).expect("Should sign");
+        session.save(&session_path).unwrap();
+    }
+
+    let session = CoopSession::load(&session_path).unwrap();
+    let tx = session.combine().expect("Should combine partial signatures");
+    assert_eq!(tx.input[0].witness.len(), 1);
+    assert_eq!(tx.input[0].witness.nth(0).unwrap().len(), 64);
+
+    // The secret nonce is gone after signing, so it cannot sign again
+    let reused = NonceState::take_secret_nonce(&giver_nonce, &session, &secret(1));
+    assert!(matches!(reused, Err(Error::CoopError(_))));
+    assert!(NonceState::load(&giver_nonce).unwrap().secret_nonce.is_none());
+
+    for path in [session_path, giver_nonce, receiver_nonce] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Outsiders cannot join, signing waits for both nonces, and a new nonce voids old signatures
+#[test]
+fn test_cooperative_session_guards() {
+    let mut session = start_session();
+
+    assert!(matches!(session.generate_nonce(&secret(3), None), Err(Error::CoopError(_))));
+
+    let first = session.generate_nonce(&secret(1), None).unwrap();
+    assert!(matches!(session.aggregated_nonce(), Err(Error::CoopError(_))));
+
+    // Regenerating bumps the counter and replaces the published nonce
+    let second = session.generate_nonce(&secret(1), Some(&first)).unwrap();
+    assert_eq!(second.counter, first.counter + 1);
+    assert_ne!(second.public_nonce, first.public_nonce);
+    let published = session.nonces().unwrap();
+    assert_eq!(published.len(), 1);
+    assert_eq!(hex::encode(published.values().next().unwrap().to_bytes()), second.public_nonce);
+
+    // A stale nonce file no longer matches the session
+    let stale_path = temp_file("stale-nonce");
+    first.save(&stale_path).unwrap();
+    session.generate_nonce(&secret(2), None).unwrap();
+    let stale = NonceState::take_secret_nonce(&stale_path, &session, &secret(1)).unwrap();
+    assert!(matches!(session.sign(&secret(1), stale), Err(Error::CoopError(_))));
+    let _ = std::fs::remove_file(stale_path);
+}
+
+/// A second spend with the same session and nonce paths replaces the first session's nonce files
+#[test]
+fn test_back_to_back_sessions_share_nonce_files() {
+    let session_path = temp_file("repeat-session");
+    let giver_nonce = temp_file("repeat-giver-nonce");
+    let receiver_nonce = temp_file("repeat-receiver-nonce");
+
+    for _ in 0..2 {
+        start_session().save(&session_path).unwrap();
+
+        for (key, nonce_path) in [(secret(1), &giver_nonce), (secret(2), &receiver_nonce)] {
+            let mut session = CoopSession::load(&session_path).unwrap();
+            let previous = nonce_path.exists().then(|| NonceState::load(nonce_path).unwrap());
+            let state = session.generate_nonce(&key, previous.as_ref()).expect("Should generate nonce");
+            assert_eq!(state.counter, 0, "A new session starts its nonce counter over");
+            state.save(nonce_path).unwrap();
+            session.save(&session_path).unwrap();
+        }
+
+        for (key, nonce_path) in [(secret(2), &receiver_nonce), (secret(1), &giver_nonce)] {
+            let mut session = CoopSession::load(&session_path).unwrap();
+            let secnonce = NonceState::take_secret_nonce(nonce_path, &session, &key).expect("Should take nonce");
+            session.sign(&key, secnonce).expect("Should sign");
+            session.save(&session_path).unwrap();
+        }
+
+        let session = CoopSession::load(&session_path).unwrap();
+        session.combine().expect("Should combine partial signatures");
+    }
+
+    for path in [session_path, giver_nonce, receiver_nonce] {
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,133 @@
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{Keypair, Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::{taproot, Amount, FeeRate, OutPoint, Txid};
+use std::str::FromStr;
+use mallowbtc::fee::{self, FeeMode, P2TR_SCRIPT_LEN};
+use mallowbtc::{Error, GiftKeys, GiftScript, GiftTransaction, GiftUtxo, LeafRole, SpendPath, Timelock};
+use mallowbtc::test_harness::fixtures::GIVER_KEY;
+
+fn receiver_secret() -> SecretKey {
+    SecretKey::from_slice(&[[0u8; 31].as_slice(), &[2]].concat()).unwrap()
+}
+
+fn gift_keys() -> GiftKeys {
+    let secp = Secp256k1::new();
+    let giver = PublicKey::from_str(GIVER_KEY).unwrap();
+    GiftKeys::new(giver, receiver_secret().public_key(&secp))
+}
+
+/// The predicted script-path weight matches the signed redemption transaction exactly
+#[test]
+fn test_script_path_weight_is_exact() {
+    let secp = Secp256k1::new();
+    let script = GiftScript::new(144)
+        .with_clawback(Timelock::Blocks(1000))
+        .with_leaf_depths(vec![1, 1]);
+    let tx = GiftTransaction::new(gift_keys(), script);
+    let destination = tx.giver_script_pubkey(0).unwrap();
+    let predicted = fee::spend_weight(tx.keys(), tx.script(), SpendPath::Leaf(LeafRole::Receiver), destination.len()).unwrap();
+
+    let mut psbt = tx
+        .redeem_after_timelock(
+            OutPoint::new(Txid::from_byte_array([9; 32]), 1),
+            Amount::from_sat(100_000),
+            destination,
+            FeeRate::from_sat_per_vb_u32(3),
+        )
+        .unwrap();
+    assert_eq!(psbt.fee().unwrap(), FeeRate::from_sat_per_vb_u32(3).fee_wu(predicted).unwrap());
+
+    let (receiver, (leaf_hashes, _)) = psbt.inputs[0].tap_key_origins.iter().next().map(|(k, v)| (*k, v.clone())).unwrap();
+    let prevouts = [psbt.inputs[0].witness_utxo.clone().unwrap()];
+    let sighash = SighashCache::new(&psbt.unsigned_tx)
+        .taproot_script_spend_signature_hash(0, &Prevouts::All(&prevouts), leaf_hashes[0], TapSighashType::Default)
+        .unwrap();
+    let signature = secp.sign_schnorr(&Message::from(sighash), &Keypair::from_secret_key(&secp, &receiver_secret()));
+    psbt.inputs[0].tap_script_sigs.insert(
+        (receiver, leaf_hashes[0]),
+        taproot::Signature { signature, sighash_type: TapSighashType::Default },
+    );
+
+    let signed = GiftTransaction::finalize(psbt).unwrap();
+    assert_eq!(signed.weight(), predicted);
+}
+
+/// Keypath spends and funding transactions have the sizes wallets expect
+#[test]
+fn test_keypath_and_funding_sizes() {
+    let tx = GiftTransaction::new(gift_keys(), GiftScript::new(144));
+
+    let key_path = fee::spend_weight(tx.keys(), tx.script(), SpendPath::KeyPath, P2TR_SCRIPT_LEN).unwrap();
+    assert_eq!(fee::vsize(key_path), 111);
+
+    // A deeper leaf needs a longer control block, so it costs more to spend
+    let shallow = fee::spend_weight(tx.keys(), tx.script(), SpendPath::Leaf(LeafRole::Receiver), P2TR_SCRIPT_LEN).unwrap();
+    let deep_script = GiftScript::new(144).with_clawback(Timelock::Blocks(1000));
+    let deep = fee::spend_weight(tx.keys(), &deep_script, SpendPath::Leaf(LeafRole::Receiver), P2TR_SCRIPT_LEN).unwrap();
+    assert!(key_path < shallow);
+    assert_eq!(deep.to_wu() - shallow.to_wu(), 32);
+
+    let missing = fee::spend_weight(tx.keys(), tx.script(), SpendPath::Leaf(LeafRole::Clawback), P2TR_SCRIPT_LEN);
+    assert!(matches!(missing, Err(Error::ScriptError(_))));
+
+    let utxo = |n: u8, index: u32| {
+        let outpoint = OutPoint::new(Txid::from_byte_array([n; 32]), 0);
+        GiftUtxo::new(outpoint, Amount::from_sat(50_000), tx.giver_script_pubkey(index).unwrap(), index)
+    };
+    let utxos = [utxo(1, 0), utxo(2, 1)];
+    assert_eq!(tx.funding_weight(&utxos, Some(2)).unwrap().to_wu(), 846);
+    assert_eq!(fee::vsize(tx.funding_weight(&utxos, None).unwrap()), 169);
+}
+
+/// In "received" mode the giver adds the redemption fee to the gift output
+#[test]
+fn test_fee_modes() {
+    assert_eq!(FeeMode::from_str("sent").unwrap(), FeeMode::AmountSent);
+    assert_eq!(FeeMode::from_str("Received").unwrap(), FeeMode::AmountReceived);
+    assert!(matches!(FeeMode::from_str("split"), Err(Error::FeeError(_))));
+
+    let keys = gift_keys();
+    let script = GiftScript::new(144);
+    let fee = fee::redemption_fee(&keys, &script, SpendPath::Leaf(LeafRole::Receiver), FeeRate::from_sat_per_vb_u32(10)).unwrap();
+    let amount = Amount::from_sat(100_000);
+    assert_eq!(FeeMode::AmountSent.gift_output(amount, fee).unwrap(), amount);
+    assert_eq!(FeeMode::AmountReceived.gift_output(amount, fee).unwrap(), amount + fee);
+
+    assert_eq!(fee::format_fee_rate(FeeRate::from_sat_per_vb_u32(2)), "2");
+    assert_eq!(fee::format_fee_rate(FeeRate::from_sat_per_kwu(375)), "1.50");
+}
+
+/// Satisfaction weights cover every path, and small gifts get break-even rates and warnings
+#[test]
+fn test_claim_costs_and_viability() {
+    let keys = gift_keys();
+    let script = GiftScript::new(144)
+        .with_clawback(Timelock::Blocks(1000))
+        .with_leaf_depths(vec![1, 1]);
+
+    let weights = script.satisfaction_weights(&keys).unwrap();
+    let paths: Vec<SpendPath> = weights.iter().map(|(path, _)| *path).collect();
+    assert_eq!(
+        paths,
+        [SpendPath::KeyPath, SpendPath::Leaf(LeafRole::Receiver), SpendPath::Leaf(LeafRole::Clawback)]
+    );
+    // One 65-byte signature with its length prefix and the stack count
+    assert_eq!(weights[0].1.to_wu(), 66);
+
+    // Miniscript's bound counts a sighash byte but not the stack count, so it
+    // matches the exact witness of a default-sighash spend
+    let costs = fee::claim_costs(&keys, &script).unwrap();
+    let receiver = costs[1];
+    let receiver_witness = receiver.weight - costs[0].weight + weights[0].1;
+    assert_eq!(receiver.satisfaction_weight, receiver_witness);
+
+    let rate = receiver.break_even_fee_rate(Amount::from_sat(5_000)).unwrap();
+    let fee = receiver.fee(rate).unwrap();
+    assert!(fee <= Amount::from_sat(5_000 - 330));
+    assert!(receiver.break_even_fee_rate(Amount::from_sat(330)).is_none());
+
+    assert_eq!(fee::viability_warnings(&costs, Amount::from_sat(5_000)).len(), 1);
+    assert_eq!(fee::viability_warnings(&costs, Amount::from_sat(300)).len(), 1);
+    assert!(fee::viability_warnings(&costs, Amount::from_sat(1_000_000)).is_empty());
+}
@@ -1,14 +1,15 @@
 use bitcoin::secp256k1::PublicKey;
 use std::str::FromStr;
 use mallowbtc::GiftKeys;
+use mallowbtc::test_harness::fixtures::{GIVER_KEY, GIVER_TPUB, RECEIVER_KEY, RECEIVER_TPUB};
 
 /// Test basic key aggregation with direct public keys
 #[test]
 fn test_key_aggregation_direct() {
     // Use known test vectors for public keys
-    let pk1 = PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+    let pk1 = PublicKey::from_str(GIVER_KEY)
         .expect("valid test key 1");
-    let pk2 = PublicKey::from_str("02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5")
+    let pk2 = PublicKey::from_str(RECEIVER_KEY)
         .expect("valid test key 2");
 
     let gift_keys = GiftKeys::new(pk1, pk2);
@@ -29,9 +30,6 @@ fn test_key_aggregation_direct() {
 /// Test key aggregation workflow with derived keys
 #[test]
 fn test_key_aggregation_workflow() {
-    const GIVER_TPUB: &str = "tpubDCvNAJkUmvjcXrTzyui9M7ehe1EXGkUmF12jTuJ9JxiAmg3tuVgocse3x5zx87WeydqwJWftYkyRQ4d7wF2F5Gs8AdzhJHVXAnMYG9QzmQ6";
-    const RECEIVER_TPUB: &str = "tpubDCvNAJkUmvjcXrTzyui9M7ehe1EXGkUmF12jTuJ9JxiAmg3tuVgocse3x5zx87WeydqwJWftYkyRQ4d7wF2F5Gs8AdzhJHVXAnMYG9QzmQ6";
-
     let gift_keys = GiftKeys::from_tpubs(GIVER_TPUB, RECEIVER_TPUB)
         .expect("Should create from tpubs");
 
@@ -45,4 +43,28 @@ fn test_key_aggregation_workflow() {
 
     assert!(!agg.to_string().is_empty(), "Should produce non-empty aggregated key");
     assert_eq!(agg.to_string().len(), 64, "Should be 32-byte hex string");
-}
\ No newline at end of file
+}
+
+/// Each gift index derives its own child keys from the wildcard descriptors
+#[test]
+fn test_gift_index_derivation() {
+    let giver = format!("[73c5da0a/86'/1'/0']{}/0/*", GIVER_TPUB);
+    let receiver = format!("[143df5a6/86'/1'/1']{}/0/*", RECEIVER_TPUB);
+
+    let keys = GiftKeys::from_descriptor_strings(&giver, &receiver)
+        .expect("Should parse descriptors");
+    let first = keys.at_index(0).expect("index 0");
+    let second = keys.at_index(1).expect("index 1");
+
+    assert_ne!(first.giver_pub().unwrap(), second.giver_pub().unwrap());
+    assert_ne!(
+        first.aggregate_musig2_key().unwrap(),
+        second.aggregate_musig2_key().unwrap()
+    );
+
+    let (fingerprint, path) = second.giver_key_origin().unwrap().expect("origin");
+    assert_eq!(fingerprint.to_string(), "73c5da0a");
+    assert_eq!(path.to_string(), "86'/1'/0'/0/1");
+
+    assert!(keys.at_index(1 << 31).is_err(), "Hardened indices are rejected");
+}
@@ -0,0 +1,75 @@
+use bitcoin::Network;
+use mallowbtc::network::{network_for_address, parse_network};
+use mallowbtc::{GiftKeys, GiftScript};
+use mallowbtc::test_harness::fixtures::{as_mainnet, mainnet, GIVER_TPUB, RECEIVER_TPUB};
+
+/// Test network keys produce the matching bech32m prefix on every test network
+#[test]
+fn test_address_prefix_per_network() {
+    let keys = GiftKeys::from_tpubs(GIVER_TPUB, RECEIVER_TPUB).expect("Should create from tpubs");
+
+    for (name, prefix) in [("regtest", "bcrt1p"), ("testnet", "tb1p"), ("signet", "tb1p")] {
+        let network = parse_network(name).expect("known network");
+        let keys = keys.with_network(network).expect("tpubs are valid on test networks");
+        let address = GiftScript::new(144)
+            .with_network(network)
+            .create_address(&keys)
+            .expect("Should create address");
+
+        assert!(address.to_string().starts_with(prefix), "{} address {}", name, address);
+    }
+}
+
+/// tpubs are rejected on mainnet and xpubs on test networks
+#[test]
+fn test_key_network_mismatch_rejected() {
+    let tpub_keys = GiftKeys::from_tpubs(GIVER_TPUB, RECEIVER_TPUB).expect("Should create from tpubs");
+    assert!(tpub_keys.with_network(mainnet()).is_err());
+
+    let xpub_keys = GiftKeys::from_tpubs(&as_mainnet(GIVER_TPUB), &as_mainnet(RECEIVER_TPUB))
+        .expect("Should create from xpubs");
+    assert!(xpub_keys.with_network(Network::Regtest).is_err());
+    assert!(xpub_keys.with_network(Network::Signet).is_err());
+    assert!(xpub_keys.with_network(mainnet()).is_ok());
+}
+
+/// Keys and script must agree on the network
+#[test]
+fn test_script_network_must_match_keys() {
+    let keys = GiftKeys::from_tpubs(GIVER_TPUB, RECEIVER_TPUB)
+        .expect("Should create from tpubs")
+        .with_network(Network::Signet)
+        .expect("tpubs are valid on signet");
+
+    assert!(GiftScript::new(144).create_taproot_tree(&keys).is_err());
+    assert!(parse_network("litecoin").is_err());
+}
+
+/// The network of an address is inferred from its prefix, with signet read as testnet
+#[test]
+fn test_network_for_address() {
+    let keys = GiftKeys::from_tpubs(GIVER_TPUB, RECEIVER_TPUB).expect("Should create from tpubs");
+
+    for (name, expected) in [("regtest", "regtest"), ("testnet", "testnet"), ("signet", "testnet")] {
+        let network = parse_network(name).expect("known network");
+        let keys = keys.with_network(network).expect("tpubs are valid on test networks");
+        let address = GiftScript::new(144)
+            .with_network(network)
+            .create_address(&keys)
+            .expect("Should create address");
+
+        let unchecked = address.to_string().parse().expect("address parses");
+        let inferred = network_for_address(&unchecked).expect("network is inferred");
+        assert_eq!(inferred, parse_network(expected).unwrap(), "{} address {}", name, address);
+        assert!(unchecked.is_valid_for_network(network));
+    }
+
+    let keys = GiftKeys::from_tpubs(&as_mainnet(GIVER_TPUB), &as_mainnet(RECEIVER_TPUB))
+        .expect("Should create from xpubs");
+    let address = GiftScript::new(144)
+        .with_network(mainnet())
+        .create_address(&keys.with_network(mainnet()).expect("mainnet keys parse"))
+        .expect("Should create address");
+    let unchecked = address.to_string().parse().expect("address parses");
+    assert_eq!(network_for_address(&unchecked).unwrap(), mainnet());
+}
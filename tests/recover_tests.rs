@@ -0,0 +1,91 @@
+use bitcoin::{Address, Network};
+use std::str::FromStr;
+use mallowbtc::recover::{default_clawbacks, default_timelocks, parse_candidates};
+use mallowbtc::{Error, GiftScript, RecoverySearch, Timelock};
+use mallowbtc::test_harness::fixtures::{descriptor_keys, GIVER_DESC, RECEIVER_DESC};
+
+fn gift_address(index: u32, script: &GiftScript) -> Address {
+    let keys = descriptor_keys()
+        .at_index(index)
+        .expect("Should derive keys");
+    script.create_address(&keys).expect("Should create address")
+}
+
+fn search() -> RecoverySearch {
+    RecoverySearch {
+        indices: 0..5,
+        timelocks: parse_candidates("140-150,30d").unwrap(),
+        clawbacks: parse_candidates("1000,2000,60d").unwrap(),
+    }
+}
+
+/// The search finds the index, timelocks and layout of gifts with and without a clawback leaf
+#[test]
+fn test_recover_gift() {
+    let lone = gift_address(3, &GiftScript::new(144));
+    let recovered = search()
+        .run(GIVER_DESC, RECEIVER_DESC, &lone, Network::Regtest)
+        .expect("Search should run")
+        .expect("Gift should be found");
+    assert_eq!(recovered.keys.index, 3);
+    assert_eq!(recovered.script.timelock(), Timelock::Blocks(144));
+    assert_eq!(recovered.script.clawback(), None);
+
+    let with_clawback = GiftScript::from_timelock(Timelock::Seconds(30 * 86_400)).with_clawback(Timelock::Seconds(60 * 86_400));
+    let address = gift_address(4, &with_clawback);
+    let recovered = search()
+        .run(GIVER_DESC, RECEIVER_DESC, &address, Network::Regtest)
+        .unwrap()
+        .expect("Gift should be found");
+    assert_eq!(recovered.keys.index, 4);
+    assert_eq!(recovered.script.timelock(), Timelock::Seconds(30 * 86_400));
+    assert_eq!(recovered.script.clawback(), Some(Timelock::Seconds(60 * 86_400)));
+    assert_eq!(recovered.script.create_address(&recovered.keys).unwrap(), address);
+
+    // Outside the searched indices, nothing is found
+    let beyond = gift_address(5, &GiftScript::new(144));
+    assert!(search().run(GIVER_DESC, RECEIVER_DESC, &beyond, Network::Regtest).unwrap().is_none());
+
+    let segwit = Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080")
+        .unwrap()
+        .require_network(Network::Regtest)
+        .unwrap();
+    assert!(matches!(
+        search().run(GIVER_DESC, RECEIVER_DESC, &segwit, Network::Regtest),
+        Err(Error::RecoveryError(_))
+    ));
+}
+
+/// Candidate lists mix timelock notations with inclusive block ranges
+#[test]
+fn test_parse_candidates() {
+    assert_eq!(
+        parse_candidates("52560, 10-12,180d,height:900000,2030-01-01").unwrap(),
+        vec![
+            Timelock::Blocks(52560),
+            Timelock::Blocks(10),
+            Timelock::Blocks(11),
+            Timelock::Blocks(12),
+            Timelock::Seconds(180 * 86_400),
+            Timelock::UnlockHeight(900_000),
+            Timelock::UnlockTime(1_893_456_000),
+        ]
+    );
+    assert!(parse_candidates("200-100").is_err());
+    assert!(parse_candidates("1-70000").is_err());
+    assert!(parse_candidates("soon").is_err());
+    assert!(parse_candidates("").is_err());
+
+    let search = RecoverySearch::default();
+    assert_eq!(search.timelocks, default_timelocks());
+    assert_eq!(search.clawbacks, default_clawbacks());
+    assert!(default_timelocks().contains(&Timelock::Blocks(52560)));
+    assert!(default_timelocks().contains(&Timelock::Seconds(180 * 86_400)));
+    // Only pairs with a later clawback of the same unit are layouts
+    let small = RecoverySearch {
+        indices: 0..2,
+        timelocks: vec![Timelock::Blocks(100), Timelock::Seconds(86_400)],
+        clawbacks: vec![Timelock::Blocks(50), Timelock::Blocks(200), Timelock::Seconds(2 * 86_400)],
+    };
+    assert_eq!(small.candidates(), 2 * (2 + 2));
+}
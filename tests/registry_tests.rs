@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::PathBuf;
+use mallowbtc::registry::{Redemption, REGISTRY_VERSION};
+use mallowbtc::{Error, GiftRecord, GiftRegistry, GiftScript, Timelock};
+use mallowbtc::test_harness::fixtures::{GIVER_DESC, RECEIVER_DESC};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("mallowbtc-registry-{}-{}", name, std::process::id()))
+        .join("gifts.json")
+}
+
+/// Recorded gifts survive a save and load and rebuild to the same address
+#[test]
+fn test_registry_round_trip() {
+    let path = temp_path("round-trip");
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+    let mut registry = GiftRegistry::load(&path).expect("A missing registry is empty");
+    assert!(registry.gifts().is_empty());
+
+    let script = GiftScript::from_timelock(Timelock::Seconds(180 * 86_400))
+        .with_clawback(Timelock::Seconds(360 * 86_400))
+        .with_leaf_depths(vec![1, 1]);
+    let record = GiftRecord::new(GIVER_DESC, RECEIVER_DESC, 7, &script)
+        .expect("Should record gift")
+        .with_label(Some("Birthday".to_string()));
+    let first = registry.add(record.clone());
+    let second = registry.add(GiftRecord::new(GIVER_DESC, RECEIVER_DESC, 8, &GiftScript::new(144)).unwrap());
+    assert_eq!((first, second), (1, 2));
+    // The same gift again keeps its id and label
+    assert_eq!(registry.add(record.clone().with_label(None)), 1);
+    assert_eq!(registry.gifts().len(), 2);
+
+    registry.find_by_address_mut(&record.address).unwrap().redemption = Redemption::Pending {
+        txid: "00".repeat(32),
+        path: "receiver leaf".to_string(),
+    };
+    registry.save(&path).expect("Should save registry");
+    // Saving again replaces the file left by the first save
+    registry.save(&path).expect("Should save registry again");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "The registry is readable only by its owner");
+    }
+
+    let mut loaded = GiftRegistry::load(&path).expect("Should load registry");
+    let gift = loaded.get(1).expect("Gift 1 should be recorded");
+    assert_eq!(gift.label.as_deref(), Some("Birthday"));
+    assert_eq!(gift.timelock, Timelock::Seconds(180 * 86_400));
+    assert!(matches!(gift.redemption, Redemption::Pending { .. }));
+    let (keys, rebuilt) = gift.gift().expect("Should rebuild gift");
+    assert_eq!(rebuilt.create_address(&keys).unwrap().to_string(), gift.address);
+    assert_eq!(rebuilt.descriptor(&keys).unwrap().to_string(), gift.descriptor);
+
+    // Forgotten ids are not handed out again
+    assert!(loaded.forget(2).is_some());
+    assert!(loaded.forget(2).is_none());
+    let third = loaded.add(GiftRecord::new(GIVER_DESC, RECEIVER_DESC, 9, &GiftScript::new(144)).unwrap());
+    assert_eq!(third, 3);
+
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
+
+/// A registry written by a newer version is refused instead of being overwritten
+#[test]
+fn test_registry_rejects_newer_version() {
+    let path = temp_path("newer");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let newer = format!("{{\"version\": {}, \"next_id\": 1, \"gifts\": []}}", REGISTRY_VERSION + 1);
+    fs::write(&path, newer).unwrap();
+    assert!(matches!(GiftRegistry::load(&path), Err(Error::RegistryError(_))));
+
+    fs::write(&path, "not json").unwrap();
+    assert!(matches!(GiftRegistry::load(&path), Err(Error::RegistryError(_))));
+
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,62 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use mallowbtc::safety::{check_mainnet_gift, confirmation_matches};
+use mallowbtc::{GiftKeys, GiftScript, Timelock};
+use mallowbtc::test_harness::fixtures::{as_mainnet, mainnet, GIVER_TPUB, RECEIVER_TPUB};
+
+fn mainnet_keys(giver: &str, receiver: &str) -> GiftKeys {
+    GiftKeys::from_descriptor_strings(giver, receiver)
+        .expect("Should parse descriptors")
+        .with_network(mainnet())
+        .expect("mainnet keys parse")
+}
+
+fn script(timelock: Timelock) -> GiftScript {
+    GiftScript::from_timelock(timelock).with_network(mainnet())
+}
+
+/// The automatic mainnet checks catch missing origins, reused keys and odd timelocks
+#[test]
+fn test_mainnet_gate_checks() {
+    let giver = format!("[73c5da0a/86'/0'/0']{}/0/*", as_mainnet(GIVER_TPUB));
+    let receiver = format!("[143df5a6/86'/0'/1']{}/0/*", as_mainnet(RECEIVER_TPUB));
+    let bare_receiver = format!("{}/0/*", as_mainnet(RECEIVER_TPUB));
+
+    let keys = mainnet_keys(&giver, &receiver);
+    assert!(check_mainnet_gift(&keys, &script(Timelock::Blocks(52560))).is_ok());
+
+    // Out-of-range timelocks
+    assert!(check_mainnet_gift(&keys, &script(Timelock::Blocks(6))).is_err());
+    assert!(check_mainnet_gift(&keys, &script(Timelock::Blocks(70000))).is_err());
+
+    // Absolute locks that have already passed
+    assert!(check_mainnet_gift(&keys, &script(Timelock::UnlockHeight(700_000))).is_err());
+    assert!(check_mainnet_gift(&keys, &script(Timelock::unlock_date("2020-01-01").unwrap())).is_err());
+    assert!(check_mainnet_gift(&keys, &script(Timelock::unlock_date("2100-01-01").unwrap())).is_err());
+
+    // Absolute locks further ahead than the horizon, such as a mistyped height
+    let in_five_years = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 5 * 31_557_600;
+    assert!(check_mainnet_gift(&keys, &script(Timelock::UnlockTime(in_five_years as u32))).is_ok());
+    assert!(check_mainnet_gift(&keys, &script(Timelock::UnlockHeight(1_000_000))).is_ok());
+    assert!(check_mainnet_gift(&keys, &script(Timelock::UnlockHeight(9_000_000))).is_err());
+
+    // The clawback leaf is held to the same ranges
+    let clawback = |timelock| script(Timelock::Blocks(52560)).with_clawback(timelock);
+    assert!(check_mainnet_gift(&keys, &clawback(Timelock::Blocks(60_000))).is_ok());
+    assert!(check_mainnet_gift(&keys, &clawback(Timelock::Blocks(6))).is_err());
+    assert!(check_mainnet_gift(&keys, &clawback(Timelock::Seconds(3600))).is_err());
+    assert!(check_mainnet_gift(&keys, &clawback(Timelock::UnlockHeight(700_000))).is_err());
+
+    // Receiver without a [fingerprint/path] origin
+    assert!(check_mainnet_gift(&mainnet_keys(&giver, &bare_receiver), &script(Timelock::Blocks(52560))).is_err());
+
+    // Giver and receiver are the same key
+    assert!(check_mainnet_gift(&mainnet_keys(&giver, &giver), &script(Timelock::Blocks(52560))).is_err());
+}
+
+/// Only the exact phrase confirms a mainnet gift
+#[test]
+fn test_confirmation_phrase() {
+    assert!(confirmation_matches("I UNDERSTAND\n"));
+    assert!(!confirmation_matches("i understand"));
+    assert!(!confirmation_matches("yes"));
+}
@@ -1,6 +1,7 @@
 use bitcoin::secp256k1::PublicKey;
 use std::str::FromStr;
-use mallowbtc::{GiftKeys, GiftScript};
+use mallowbtc::{Error, GiftKeys, GiftScript, LeafRole, Timelock};
+use mallowbtc::test_harness::fixtures::{descriptor_keys, test_keys, GIVER_KEY, RECEIVER_KEY};
 
 /// Tests the complete script creation workflow including timelock and taproot construction
 #[test]
@@ -9,15 +10,15 @@ fn test_script_creation_workflow() {
     let script = GiftScript::new(52560);
 
     // Use known test vectors for keys
-    let giver = PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+    let giver = PublicKey::from_str(GIVER_KEY)
         .expect("valid giver key");
-    let receiver = PublicKey::from_str("02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5")
+    let receiver = PublicKey::from_str(RECEIVER_KEY)
         .expect("valid receiver key");
 
     let gift_keys = GiftKeys::new(giver, receiver);
 
     // Create and verify the timelock script
-    let timelock_script = script.create_timelock_script(gift_keys.receiver)
+    let timelock_script = script.create_timelock_script(gift_keys.receiver_x_only_pub().expect("valid receiver x-only key"))
         .expect("Should create timelock script");
     
     println!("\n=== Script Creation ===");
@@ -26,7 +27,7 @@ fn test_script_creation_workflow() {
     println!("ASM: {}", timelock_script.to_asm_string());
 
     // Create and verify the complete taproot output
-    let p2tr_script = script.create_taproot_tree(&gift_keys)
+    let (p2tr_script, spend_info) = script.create_taproot_tree(&gift_keys)
         .expect("Should create taproot tree");
     let address = bitcoin::Address::from_script(&p2tr_script, bitcoin::Network::Regtest)
         .expect("Should create valid address");
@@ -36,7 +37,114 @@ fn test_script_creation_workflow() {
     println!("Script (asm): {}", p2tr_script.to_asm_string());
     println!("Address: {}", address);
     
+    // The keypath must be the MuSig2 aggregate, not either party alone
+    assert_eq!(
+        spend_info.internal_key(),
+        gift_keys.aggregate_musig2_key().expect("Aggregation should succeed")
+    );
+
     // Basic sanity checks
     assert!(address.to_string().starts_with("bcrt1"));
     assert!(timelock_script.to_asm_string().contains("OP_CSV"));
-}
\ No newline at end of file
+}
+
+/// The exported tr() descriptor is the miniscript form of the same output
+#[test]
+fn test_descriptor_matches_taproot_output() {
+    let keys = descriptor_keys()
+        .at_index(3)
+        .expect("index 3");
+    let script = GiftScript::new(52560);
+
+    let descriptor = script.descriptor(&keys).expect("Should build descriptor");
+    let text = descriptor.to_string();
+    println!("\nDescriptor: {}", text);
+
+    assert!(text.starts_with(&format!("tr({},", keys.aggregate_musig2_key().unwrap())));
+    assert!(text.contains("and_v(v:pk([143df5a6/86'/1'/1']"));
+    assert!(text.contains("/0/3),older(52560))"));
+    assert!(text.contains('#'), "Descriptor should carry a checksum");
+
+    let address = descriptor
+        .at_derivation_index(0)
+        .expect("definite descriptor")
+        .address(bitcoin::Network::Regtest)
+        .expect("Should derive address");
+    assert_eq!(address, script.create_address(&keys).expect("Should create address"));
+}
+
+/// A clawback adds a second leaf for the giver, and every leaf gets a control block
+#[test]
+fn test_clawback_leaf_tree() {
+    let keys = test_keys();
+    let script = GiftScript::new(52560).with_clawback(Timelock::Blocks(65000));
+
+    let leaves = script.leaves(&keys).expect("Should build leaves");
+    assert_eq!(leaves.len(), 2);
+    assert_eq!(leaves[0].role, LeafRole::Receiver);
+    assert_eq!(leaves[1].role, LeafRole::Clawback);
+    assert_eq!(leaves[1].timelock, Timelock::Blocks(65000));
+    assert!(leaves.iter().all(|leaf| leaf.depth == 1));
+
+    let giver = keys.giver_x_only_pub().unwrap().serialize();
+    assert!(leaves[1].script.as_bytes().windows(32).any(|window| window == giver));
+
+    let (_, spend_info) = script.create_taproot_tree(&keys).expect("Should create taproot tree");
+    for leaf in &leaves {
+        let control_block = leaf.control_block(&spend_info).expect("Every leaf has a control block");
+        // One 32-byte merkle branch for a depth-1 leaf
+        assert_eq!(control_block.serialize().len(), 33 + 32);
+    }
+
+    // Explicit depths matching the default give the same output
+    let explicit = GiftScript::new(52560)
+        .with_clawback(Timelock::Blocks(65000))
+        .with_leaf_depths(vec![1, 1]);
+    assert_eq!(
+        explicit.create_address(&keys).unwrap(),
+        script.create_address(&keys).unwrap()
+    );
+}
+
+/// The clawback must use the gift's unit and expire after it, and depths must form a tree
+#[test]
+fn test_clawback_and_depth_validation() {
+    let keys = test_keys();
+
+    let shorter = GiftScript::new(52560).with_clawback(Timelock::Blocks(52560));
+    assert!(matches!(shorter.leaves(&keys), Err(Error::TimelockError(_))));
+
+    let mixed = GiftScript::new(52560).with_clawback(Timelock::UnlockHeight(1_000_000));
+    assert!(matches!(mixed.leaves(&keys), Err(Error::TimelockError(_))));
+
+    let wrong_count = GiftScript::new(52560).with_leaf_depths(vec![1, 1]);
+    assert!(matches!(wrong_count.create_taproot_tree(&keys), Err(Error::ScriptError(_))));
+
+    let incomplete = GiftScript::new(52560)
+        .with_clawback(Timelock::Blocks(65000))
+        .with_leaf_depths(vec![1, 2]);
+    assert!(matches!(incomplete.create_taproot_tree(&keys), Err(Error::ScriptError(_))));
+}
+
+/// The descriptor carries both leaves and derives the same address as the tree
+#[test]
+fn test_clawback_descriptor_matches_taproot_output() {
+    let keys = descriptor_keys()
+        .at_index(3)
+        .expect("index 3");
+    let script = GiftScript::from_timelock(Timelock::UnlockHeight(1_000_000))
+        .with_clawback(Timelock::UnlockHeight(1_050_000));
+
+    let descriptor = script.descriptor(&keys).expect("Should build descriptor");
+    let text = descriptor.to_string();
+    assert!(text.contains("after(1000000)"));
+    assert!(text.contains("and_v(v:pk([73c5da0a/86'/1'/0']"));
+    assert!(text.contains("after(1050000)"));
+
+    let address = descriptor
+        .at_derivation_index(0)
+        .expect("definite descriptor")
+        .address(bitcoin::Network::Regtest)
+        .expect("Should derive address");
+    assert_eq!(address, script.create_address(&keys).expect("Should create address"));
+}
@@ -0,0 +1,112 @@
+use bitcoin::absolute::LockTime;
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+use mallowbtc::backend::MockBackend;
+use mallowbtc::{ChainBackend, GiftKeys, GiftScript, GiftStatus, LeafRole, SpendPath, Timelock, WatchedGift};
+use mallowbtc::test_harness::fixtures::{descriptor_keys, payment, RECEIVER_KEY};
+
+fn gift() -> (GiftKeys, GiftScript) {
+    let keys = descriptor_keys()
+        .at_index(5)
+        .expect("Should derive keys");
+    (keys, GiftScript::new(144).with_clawback(Timelock::Blocks(1000)))
+}
+
+/// A transaction spending `outpoint` with `witness`; the mock checks no signatures
+fn spend(outpoint: OutPoint, witness: Witness) -> Transaction {
+    Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(40_000),
+            script_pubkey: ScriptBuf::new_op_return([]),
+        }],
+    }
+}
+
+/// The descriptor printed by create watches the same address and leaves as the keys
+#[test]
+fn test_watch_from_descriptor() {
+    let (keys, script) = gift();
+    let watched = WatchedGift::new(&keys, &script).expect("Should watch gift");
+    assert_eq!(watched.address, script.create_address(&keys).unwrap());
+    let leaves = script.leaves(&keys).unwrap();
+    assert_eq!(watched.leaves.len(), 2);
+    for (watched, leaf) in watched.leaves.iter().zip(&leaves) {
+        assert_eq!((watched.role, &watched.script, watched.timelock), (leaf.role, &leaf.script, leaf.timelock));
+    }
+
+    let descriptor = script.descriptor(&keys).unwrap().to_string();
+    let from_descriptor = WatchedGift::from_descriptor_str(&descriptor, 0, script.network()).unwrap();
+    assert_eq!(from_descriptor.address, watched.address);
+    assert_eq!(from_descriptor.leaves, watched.leaves);
+
+    assert!(WatchedGift::from_descriptor_str(&format!("wpkh({})", RECEIVER_KEY), 0, script.network()).is_err());
+}
+
+/// Each output counts down from its own confirmation, and spends are told apart by their witness
+#[test]
+fn test_gift_status_through_backend() {
+    let (keys, script) = gift();
+    let watched = WatchedGift::new(&keys, &script).unwrap();
+    let chain = MockBackend::new(200);
+
+    let status = GiftStatus::fetch(&chain, &watched).unwrap();
+    assert!(status.outputs.is_empty());
+    assert!(status.complete_history);
+
+    let early = payment(watched.address.script_pubkey(), 50_000);
+    let late = payment(watched.address.script_pubkey(), 60_000);
+    let pending = payment(watched.address.script_pubkey(), 70_000);
+    chain.add_transaction(early.clone(), Some(100));
+    chain.add_transaction(late.clone(), Some(150));
+    chain.add_transaction(pending, None);
+    // Unrelated outputs are not the gift's
+    chain.add_transaction(payment(ScriptBuf::new_op_return([1]), 1_000), Some(120));
+
+    let status = GiftStatus::fetch(&chain, &watched).unwrap();
+    assert_eq!(status.tip_height, 200);
+    let countdowns: Vec<_> = status.outputs.iter().map(|output| output.blocks_remaining.clone()).collect();
+    assert!(countdowns.contains(&vec![(LeafRole::Receiver, Some(43)), (LeafRole::Clawback, Some(899))]));
+    assert!(countdowns.contains(&vec![(LeafRole::Receiver, Some(93)), (LeafRole::Clawback, Some(949))]));
+    assert!(countdowns.contains(&vec![(LeafRole::Receiver, None), (LeafRole::Clawback, None)]));
+    assert!(status.outputs.iter().all(|output| output.spent.is_none()));
+
+    // The receiver claims the early output through its leaf, the late one is spent cooperatively
+    let (_, spend_info) = script.create_taproot_tree(&keys).unwrap();
+    let receiver_leaf = &script.leaves(&keys).unwrap()[0];
+    let leaf_witness = Witness::from_slice(&[
+        vec![0; 64],
+        receiver_leaf.script.to_bytes(),
+        receiver_leaf.control_block(&spend_info).unwrap().serialize(),
+    ]);
+    let early_outpoint = OutPoint::new(early.compute_txid(), 0);
+    let late_outpoint = OutPoint::new(late.compute_txid(), 0);
+    let claim = chain.broadcast(&spend(early_outpoint, leaf_witness)).unwrap();
+    chain.mine(1);
+    let coop = chain.broadcast(&spend(late_outpoint, Witness::from_slice(&[vec![0; 64]]))).unwrap();
+
+    let status = GiftStatus::fetch(&chain, &watched).unwrap();
+    let spent = |outpoint| {
+        status
+            .outputs
+            .iter()
+            .find(|output| output.outpoint == outpoint)
+            .and_then(|output| output.spent.clone())
+            .expect("Output should be spent")
+    };
+    let early_spend = spent(early_outpoint);
+    assert_eq!(early_spend.txid, claim);
+    assert_eq!(early_spend.confirmation_height, Some(201));
+    assert_eq!(early_spend.path, Some(SpendPath::Leaf(LeafRole::Receiver)));
+    let late_spend = spent(late_outpoint);
+    assert_eq!(late_spend.txid, coop);
+    assert_eq!(late_spend.confirmation_height, None);
+    assert_eq!(late_spend.path, Some(SpendPath::KeyPath));
+}
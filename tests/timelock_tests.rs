@@ -0,0 +1,115 @@
+use mallowbtc::{Error, GiftScript, Timelock};
+use mallowbtc::test_harness::fixtures::test_keys;
+
+/// Block timelocks are limited to the 16 bits BIP-68 provides
+#[test]
+fn test_block_timelock_range() {
+    assert!(matches!(Timelock::blocks(0), Err(Error::TimelockError(_))));
+    assert!(matches!(Timelock::blocks(Timelock::MAX_BLOCKS + 1), Err(Error::TimelockError(_))));
+
+    let max = Timelock::blocks(Timelock::MAX_BLOCKS).expect("65535 blocks is valid");
+    assert_eq!(max.to_sequence().unwrap().to_consensus_u32(), 0xFFFF);
+    assert!(max.to_sequence().unwrap().is_height_locked());
+    assert_eq!(max.script_value().unwrap(), 0xFFFF);
+}
+
+/// Building a script with an unencodable timelock fails instead of producing a bad leaf
+#[test]
+fn test_script_rejects_oversized_timelock() {
+    let keys = test_keys();
+    let receiver = keys.receiver_x_only_pub().expect("valid receiver key");
+
+    assert!(matches!(
+        GiftScript::new(70000).create_timelock_script(receiver),
+        Err(Error::TimelockError(_))
+    ));
+    assert!(GiftScript::new(70000).create_taproot_tree(&keys).is_err());
+    assert!(GiftScript::new(52560).create_taproot_tree(&keys).is_ok());
+}
+
+/// Absolute unlock dates and heights build an OP_CLTV leaf with the matching nLockTime
+#[test]
+fn test_absolute_timelocks() {
+    let date = Timelock::unlock_date("2030-01-01").expect("valid date");
+    assert_eq!(date, Timelock::UnlockTime(1_893_456_000));
+    assert_eq!(date.to_string(), "2030-01-01 00:00:00 UTC");
+    assert_eq!(date.to_lock_time().unwrap().to_consensus_u32(), 1_893_456_000);
+    assert!(date.to_sequence().unwrap().enables_absolute_lock_time());
+
+    // Leap days are accepted only in leap years
+    assert!(Timelock::unlock_date("2028-02-29").is_ok());
+    assert!(Timelock::unlock_date("2030-02-29").is_err());
+    assert!(Timelock::unlock_date("1970-01-01").is_err(), "Timestamps below 500000000 are heights");
+    assert!(Timelock::unlock_date("next year").is_err());
+
+    let height = Timelock::unlock_height(900_000).expect("valid height");
+    assert_eq!(height.script_value().unwrap(), 900_000);
+    assert!(Timelock::unlock_height(500_000_000).is_err(), "Heights must stay below the time threshold");
+
+    let keys = test_keys();
+    let script = GiftScript::from_timelock(date)
+        .create_timelock_script(keys.receiver_x_only_pub().unwrap())
+        .expect("Should create CLTV script");
+    assert!(script.to_asm_string().contains("OP_CLTV"));
+    assert!(!script.to_asm_string().contains("OP_CSV"));
+}
+
+/// Durations are encoded in 512-second units with the BIP-68 type flag, rounding up
+#[test]
+fn test_relative_time_timelocks() {
+    let half_year = Timelock::lock_for("180d").expect("valid duration");
+    assert_eq!(half_year, Timelock::Seconds(15_552_000));
+    // 180 days is exactly 30375 units of 512 seconds
+    assert_eq!(half_year.effective_seconds().unwrap(), Some(15_552_000));
+
+    let sequence = half_year.to_sequence().unwrap();
+    assert!(sequence.is_time_locked());
+    assert_eq!(sequence.to_consensus_u32(), (1 << 22) | 30_375);
+    assert_eq!(half_year.script_value().unwrap(), i64::from(sequence.to_consensus_u32()));
+
+    // 10 hours is 70.3 units and rounds up to 71
+    let ten_hours = Timelock::lock_for("10h").expect("valid duration");
+    assert_eq!(ten_hours.effective_seconds().unwrap(), Some(71 * 512));
+
+    assert!(Timelock::lock_for("400d").is_err(), "Longer than 65535 units");
+    assert!(Timelock::lock_for("0d").is_err());
+    assert!(Timelock::lock_for("6 months").is_err());
+    assert_eq!(Timelock::Blocks(144).effective_seconds().unwrap(), None);
+}
+
+/// Durations ending in a multi-byte character are rejected rather than split mid-character
+#[test]
+fn test_non_ascii_duration_suffix() {
+    for duration in ["10µ", "5д", "µ", "3日"] {
+        assert!(matches!(Timelock::lock_for(duration), Err(Error::TimelockError(_))), "{}", duration);
+        assert!(matches!(duration.parse::<Timelock>(), Err(Error::TimelockError(_))), "{}", duration);
+    }
+}
+
+/// The compact notation used by --clawback picks the unit from the text
+#[test]
+fn test_timelock_from_str() {
+    assert_eq!("52560".parse::<Timelock>().unwrap(), Timelock::Blocks(52560));
+    assert_eq!("180d".parse::<Timelock>().unwrap(), Timelock::Seconds(180 * 86_400));
+    assert_eq!("height:1000000".parse::<Timelock>().unwrap(), Timelock::UnlockHeight(1_000_000));
+    assert_eq!("2030-01-01".parse::<Timelock>().unwrap(), Timelock::UnlockTime(1_893_456_000));
+    assert!(matches!("height:abc".parse::<Timelock>(), Err(Error::TimelockError(_))));
+    assert!(matches!("soon".parse::<Timelock>(), Err(Error::TimelockError(_))));
+}
+
+/// Blocks remaining count toward the first block that may include the spend
+#[test]
+fn test_blocks_remaining() {
+    // Confirmed at 100 with a 10-block lock: first spendable in block 110
+    let relative = Timelock::Blocks(10);
+    assert_eq!(relative.blocks_remaining(Some(100), 105), Some(4));
+    assert_eq!(relative.blocks_remaining(Some(100), 109), Some(0));
+    assert_eq!(relative.blocks_remaining(None, 109), None);
+
+    // nLockTime 900000 is final in block 900001
+    let absolute = Timelock::UnlockHeight(900_000);
+    assert_eq!(absolute.blocks_remaining(None, 899_990), Some(10));
+    assert_eq!(absolute.blocks_remaining(None, 950_000), Some(0));
+
+    assert_eq!(Timelock::Seconds(86_400).blocks_remaining(Some(100), 500), None);
+}
@@ -0,0 +1,203 @@
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{Keypair, Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::transaction::Version;
+use bitcoin::{taproot, Amount, FeeRate, OutPoint, Sequence, Txid, XOnlyPublicKey};
+use std::str::FromStr;
+use mallowbtc::transaction::{
+    key_path_context, key_spend_sighash, musig2_participants, musig2_partial_signatures, parse_fee_rate,
+    set_musig2_partial_signature, set_musig2_pub_nonce, MuSig2Signer, PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS,
+};
+use musig2::{AggNonce, PartialSignature, SecNonce};
+use mallowbtc::{Error, GiftKeys, GiftScript, GiftTransaction, Timelock};
+use mallowbtc::test_harness::fixtures::{gift_transaction, giver_utxo, GIVER_KEY};
+
+/// The funding PSBT pays the gift address, returns change and carries key origins
+#[test]
+fn test_funding_psbt() {
+    let tx = gift_transaction(52560);
+    let utxos = [giver_utxo(&tx, 1, 100_000, 5), giver_utxo(&tx, 2, 20_000, 6)];
+    let fee_rate = FeeRate::from_sat_per_vb_u32(2);
+
+    let psbt = tx
+        .fund(&utxos, Amount::from_sat(50_000), fee_rate, 7)
+        .expect("Should build funding PSBT");
+
+    let gift_address = tx.script().create_address(tx.keys()).unwrap();
+    let outputs = &psbt.unsigned_tx.output;
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].script_pubkey, gift_address.script_pubkey());
+    assert_eq!(outputs[0].value, Amount::from_sat(50_000));
+    assert_eq!(outputs[1].script_pubkey, tx.giver_script_pubkey(7).unwrap());
+
+    // Two P2TR keypath inputs and two P2TR outputs come to 211.5 vbytes
+    let fee = psbt.fee().expect("Inputs carry witness_utxo");
+    assert_eq!(fee, Amount::from_sat(423));
+    assert_eq!(outputs[1].value, Amount::from_sat(120_000 - 50_000 - 423));
+
+    for (input, utxo) in psbt.inputs.iter().zip(&utxos) {
+        let (_, (leaf_hashes, (fingerprint, path))) = input.tap_key_origins.iter().next().expect("Input has a key origin");
+        assert!(leaf_hashes.is_empty());
+        assert_eq!(fingerprint.to_string(), "73c5da0a");
+        assert_eq!(path.to_string(), format!("86'/1'/0'/0/{}", utxo.index));
+        assert!(input.tap_internal_key.is_some());
+    }
+
+    let (_, (_, (_, change_path))) = psbt.outputs[1].tap_key_origins.iter().next().expect("Change has a key origin");
+    assert_eq!(change_path.to_string(), "86'/1'/0'/0/7");
+    assert_eq!(psbt.outputs[0].tap_internal_key, Some(tx.keys().aggregate_musig2_key().unwrap()));
+    assert_eq!(psbt.xpub.len(), 1);
+
+    let (participants_key, participants) = psbt.outputs[0].unknown.iter().next().expect("Gift output lists participants");
+    assert_eq!(participants_key.type_value, PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS);
+    assert_eq!(participants_key.key, tx.keys().aggregate_musig2_pubkey().unwrap().serialize());
+    assert_eq!(participants.len(), 66);
+}
+
+/// Change below the dust limit is left to the fee, and shortfalls are reported
+#[test]
+fn test_funding_change_and_shortfall() {
+    let tx = gift_transaction(52560);
+    let fee_rate = FeeRate::from_sat_per_vb_u32(1);
+
+    let psbt = tx
+        .fund(&[giver_utxo(&tx, 1, 50_200, 0)], Amount::from_sat(50_000), fee_rate, 1)
+        .expect("Should build funding PSBT without change");
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    assert_eq!(psbt.fee().unwrap(), Amount::from_sat(200));
+
+    let short = tx.fund(&[giver_utxo(&tx, 1, 50_000, 0)], Amount::from_sat(50_000), fee_rate, 1);
+    assert!(matches!(short, Err(Error::TransactionError(_))));
+
+    let dust = tx.fund(&[giver_utxo(&tx, 1, 50_000, 0)], Amount::from_sat(100), fee_rate, 1);
+    assert!(matches!(dust, Err(Error::TransactionError(_))));
+}
+
+/// UTXOs that the giver key at the stated index does not control are refused
+#[test]
+fn test_funding_rejects_foreign_utxos() {
+    let tx = gift_transaction(52560);
+    let mut utxo = giver_utxo(&tx, 1, 100_000, 5);
+    utxo.index = 4;
+
+    let result = tx.fund(&[utxo.clone()], Amount::from_sat(50_000), FeeRate::from_sat_per_vb_u32(1), 7);
+    assert!(matches!(result, Err(Error::TransactionError(_))));
+
+    utxo.index = 5;
+    let twice = tx.fund(&[utxo.clone(), utxo], Amount::from_sat(50_000), FeeRate::from_sat_per_vb_u32(1), 7);
+    assert!(matches!(twice, Err(Error::TransactionError(_))));
+}
+
+/// Fee rates are given in sat/vB and may be fractional
+#[test]
+fn test_parse_fee_rate() {
+    assert_eq!(parse_fee_rate("2").unwrap(), FeeRate::from_sat_per_vb_u32(2));
+    assert_eq!(parse_fee_rate("1.5").unwrap().to_sat_per_kwu(), 375);
+    assert!(parse_fee_rate("0.5").is_err());
+    assert!(parse_fee_rate("fast").is_err());
+}
+
+/// The redemption PSBT carries the receiver leaf, and once signed it finalizes into a valid witness
+#[test]
+fn test_redeem_after_timelock() {
+    let secp = Secp256k1::new();
+    let receiver_secret = SecretKey::from_slice(&[[0u8; 31].as_slice(), &[2]].concat()).unwrap();
+    let giver = PublicKey::from_str(GIVER_KEY).unwrap();
+    let keys = GiftKeys::new(giver, receiver_secret.public_key(&secp));
+    let tx = GiftTransaction::new(keys, GiftScript::new(144).with_clawback(Timelock::Blocks(1000)));
+
+    let gift_outpoint = OutPoint::new(Txid::from_byte_array([9; 32]), 1);
+    let destination = tx.giver_script_pubkey(0).unwrap();
+    let mut psbt = tx
+        .redeem_after_timelock(gift_outpoint, Amount::from_sat(100_000), destination, FeeRate::from_sat_per_vb_u32(2))
+        .expect("Should build redemption PSBT");
+
+    assert_eq!(psbt.unsigned_tx.version, Version::TWO);
+    assert_eq!(psbt.unsigned_tx.input[0].sequence, Sequence::from_height(144));
+    let input = psbt.inputs[0].clone();
+    assert_eq!(input.tap_scripts.len(), 1);
+    let (control_block, (leaf_script, _)) = input.tap_scripts.iter().next().unwrap();
+    let output_key = XOnlyPublicKey::from_slice(&input.witness_utxo.as_ref().unwrap().script_pubkey.as_bytes()[2..]).unwrap();
+    assert!(control_block.verify_taproot_commitment(&secp, output_key, leaf_script));
+    let (receiver, (leaf_hashes, _)) = input.tap_key_origins.iter().next().unwrap();
+    assert_eq!(*receiver, tx.keys().receiver_x_only_pub().unwrap());
+    let leaf_hash = leaf_hashes[0];
+
+    // Unsigned PSBTs cannot be finalized
+    assert!(matches!(GiftTransaction::finalize(psbt.clone()), Err(Error::TransactionError(_))));
+
+    let prevouts = [psbt.inputs[0].witness_utxo.clone().unwrap()];
+    let sighash = SighashCache::new(&psbt.unsigned_tx)
+        .taproot_script_spend_signature_hash(0, &Prevouts::All(&prevouts), leaf_hash, TapSighashType::Default)
+        .unwrap();
+    let keypair = Keypair::from_secret_key(&secp, &receiver_secret);
+    let signature = secp.sign_schnorr(&Message::from(sighash), &keypair);
+    psbt.inputs[0].tap_script_sigs.insert(
+        (*receiver, leaf_hash),
+        taproot::Signature { signature, sighash_type: TapSighashType::Default },
+    );
+
+    let signed = GiftTransaction::finalize(psbt).expect("Should finalize signed PSBT");
+    let witness = &signed.input[0].witness;
+    assert_eq!(witness.len(), 3);
+    assert_eq!(witness.nth(0).unwrap().len(), 64);
+    assert_eq!(witness.nth(1).unwrap(), leaf_script.as_bytes());
+}
+
+/// BIP-373 nonces and partial signatures survive serialization and aggregate into a keypath signature
+#[test]
+fn test_musig2_psbt_fields() {
+    let secp = Secp256k1::new();
+    let secrets: Vec<SecretKey> = (1u8..=2)
+        .map(|n| SecretKey::from_slice(&[[0u8; 31].as_slice(), &[n]].concat()).unwrap())
+        .collect();
+    let keys = GiftKeys::new(secrets[0].public_key(&secp), secrets[1].public_key(&secp));
+    let tx = GiftTransaction::new(keys, GiftScript::new(144));
+    let destination = tx.giver_script_pubkey(0).unwrap();
+    let mut psbt = tx
+        .spend_cooperatively(
+            OutPoint::new(Txid::from_byte_array([9; 32]), 0),
+            Amount::from_sat(100_000),
+            destination,
+            FeeRate::from_sat_per_vb_u32(2),
+        )
+        .expect("Should build keypath PSBT");
+
+    let aggregate = tx.keys().aggregate_musig2_pubkey().unwrap();
+    let listed = musig2_participants(&psbt.inputs[0]).unwrap();
+    assert_eq!(listed.get(&aggregate), Some(&tx.keys().musig2_participants().unwrap()));
+
+    let (ctx, _) = key_path_context(&psbt.inputs[0]).expect("Participants should pay the gift");
+    let message = key_spend_sighash(&psbt, 0).unwrap();
+    let secnonces: Vec<SecNonce> = secrets
+        .iter()
+        .map(|secret| {
+            let secnonce = SecNonce::build([secret.secret_bytes()[31]; 32]).with_message(&message).build();
+            let signer = MuSig2Signer::key_path(secret.public_key(&secp), aggregate);
+            set_musig2_pub_nonce(&mut psbt.inputs[0], signer, &secnonce.public_nonce());
+            secnonce
+        })
+        .collect();
+    let aggregated_nonce = AggNonce::sum(secnonces.iter().map(|nonce| nonce.public_nonce()));
+
+    // A missing partial signature is reported rather than finalized
+    assert!(matches!(GiftTransaction::finalize(psbt.clone()), Err(Error::TransactionError(_))));
+
+    for (secret, secnonce) in secrets.iter().zip(secnonces) {
+        let seckey = musig2::secp::Scalar::from_slice(&secret.secret_bytes()).unwrap();
+        let partial: PartialSignature = musig2::sign_partial(&ctx, seckey, secnonce, &aggregated_nonce, message).unwrap();
+        let signer = MuSig2Signer::key_path(secret.public_key(&secp), aggregate);
+        set_musig2_partial_signature(&mut psbt.inputs[0], signer, partial);
+    }
+
+    let psbt = bitcoin::Psbt::deserialize(&psbt.serialize()).expect("BIP-373 fields should round-trip");
+    assert_eq!(musig2_partial_signatures(&psbt.inputs[0]).unwrap().len(), 2);
+
+    let output_key = XOnlyPublicKey::from_slice(&psbt.inputs[0].witness_utxo.as_ref().unwrap().script_pubkey.as_bytes()[2..]).unwrap();
+    let signed = GiftTransaction::finalize(psbt).expect("Should aggregate and finalize");
+    let witness = &signed.input[0].witness;
+    assert_eq!(witness.len(), 1);
+    let signature = bitcoin::secp256k1::schnorr::Signature::from_slice(witness.nth(0).unwrap()).unwrap();
+    secp.verify_schnorr(&signature, &Message::from_digest(message), &output_key)
+        .expect("Aggregate signature should verify against the gift output key");
+}
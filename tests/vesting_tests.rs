@@ -0,0 +1,86 @@
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, FeeRate, Network, OutPoint, Txid};
+use mallowbtc::vesting::spendable_from;
+use mallowbtc::{Error, GiftUtxo, SelectionOptions, Timelock, VestingSchedule};
+use mallowbtc::test_harness::fixtures::{GIVER_DESC, RECEIVER_DESC};
+
+fn amounts(schedule: &VestingSchedule) -> Vec<u64> {
+    schedule.tranches.iter().map(|tranche| tranche.amount.to_sat()).collect()
+}
+
+/// Even, yearly and relative schedules split the total exactly, remainder last
+#[test]
+fn test_schedule_generators() {
+    let total = Amount::from_sat(1_000_003);
+
+    let yearly = VestingSchedule::yearly(total, 4, "2027-01-01").expect("Should build yearly schedule");
+    assert_eq!(amounts(&yearly), vec![250_000, 250_000, 250_000, 250_003]);
+    assert_eq!(yearly.tranches[3].timelock, Timelock::unlock_date("2030-01-01").unwrap());
+
+    let every = VestingSchedule::every(total, 4, Timelock::Blocks(13140)).expect("Should build relative schedule");
+    let timelocks: Vec<Timelock> = every.tranches.iter().map(|tranche| tranche.timelock).collect();
+    assert_eq!(
+        timelocks,
+        vec![Timelock::Blocks(13140), Timelock::Blocks(26280), Timelock::Blocks(39420), Timelock::Blocks(52560)]
+    );
+    assert_eq!(spendable_from(&timelocks[3]), "52560 blocks after funding (about 365 days)");
+
+    // Five steps of 13140 blocks exceed what BIP-68 can express
+    assert!(matches!(
+        VestingSchedule::every(total, 5, Timelock::Blocks(13140)),
+        Err(Error::TimelockError(_))
+    ));
+    assert!(matches!(
+        VestingSchedule::every(total, 2, Timelock::UnlockHeight(900_000)),
+        Err(Error::VestingError(_))
+    ));
+}
+
+/// Explicit schedules take shares per tranche, which must cover the whole total
+#[test]
+fn test_parse_schedule() {
+    let total = Amount::from_sat(1_000_000);
+
+    let schedule = VestingSchedule::parse(total, "90d:10, height:900000:30%, 2028-06-01:60").expect("Should parse");
+    assert_eq!(amounts(&schedule), vec![100_000, 300_000, 600_000]);
+    assert_eq!(schedule.tranches[1].timelock, Timelock::UnlockHeight(900_000));
+
+    let even = VestingSchedule::parse(total, "height:900000,height:950000").unwrap();
+    assert_eq!(amounts(&even), vec![500_000, 500_000]);
+
+    for spec in ["90d:10,180d:80", "90d:10,180d", ""] {
+        assert!(matches!(VestingSchedule::parse(total, spec), Err(Error::VestingError(_))));
+    }
+    // Tranches below the dust limit could never be claimed
+    assert!(matches!(
+        VestingSchedule::parse(Amount::from_sat(1000), "90d,180d,270d,360d"),
+        Err(Error::VestingError(_))
+    ));
+}
+
+/// All tranches are funded by one transaction, each at its own index and timelock
+#[test]
+fn test_vesting_plan_funding() {
+    let schedule = VestingSchedule::yearly(Amount::from_sat(400_000), 4, "2027-01-01").unwrap();
+    let plan = schedule
+        .plan(GIVER_DESC, RECEIVER_DESC, 10, Network::Regtest)
+        .expect("Should plan tranches");
+    assert_eq!(plan.gifts.iter().map(|planned| planned.index).collect::<Vec<_>>(), vec![10, 11, 12, 13]);
+    assert_eq!(plan.gifts[0].gift.label, "tranche 1/4");
+
+    let utxo = GiftUtxo::new(
+        OutPoint::new(Txid::from_byte_array([1; 32]), 0),
+        Amount::from_sat(500_000),
+        plan.gifts[0].transaction.giver_script_pubkey(0).unwrap(),
+        0,
+    );
+    let psbt = plan
+        .fund(&[utxo], FeeRate::from_sat_per_vb_u32(2), 1, &SelectionOptions::default())
+        .expect("Should fund tranches");
+    assert_eq!(psbt.unsigned_tx.output.len(), 5);
+    for ((output, planned), tranche) in psbt.unsigned_tx.output.iter().zip(&plan.gifts).zip(&schedule.tranches) {
+        assert_eq!(output.script_pubkey, planned.address.script_pubkey());
+        assert_eq!(output.value, tranche.amount);
+        assert_eq!(planned.transaction.script().timelock(), tranche.timelock);
+    }
+}